@@ -12,37 +12,442 @@
 
 use anyhow::{anyhow, Result};
 use csv::ReaderBuilder;
-use clap::{builder::PossibleValuesParser, Parser};
+use clap::{ArgAction, Args, builder::PossibleValuesParser, Command, Parser, Subcommand, ValueEnum};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Map, Value};
 
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::fs::{DirBuilder, File, read_to_string};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 use rpfm_lib::games::supported_games::SupportedGames;
 
+use crate::logging::parse_log_filter;
+use crate::utils::{config_path, glob_match};
+
+// Name of the config file, in the config folder.
+const CONFIG_FILE_NAME: &str = "twpatcher.toml";
+
+// Name of the folder where profiles are saved, in the config folder.
+const PROFILES_FOLDER: &str = "profiles";
+
+// Features that can be limited to some Packs of the load order with --feature-scope.
+const SCOPABLE_FEATURES: [&str; 33] = [
+    "skip_intro_videos", "enable_logging", "remove_trait_limit", "remove_siege_attacker", "remove_battle_timer",
+    "unlock_all_factions", "remove_agent_caps", "remove_unit_caps", "translation_language", "unit_multiplier",
+    "remove_supply_lines", "no_attrition", "unlock_battle_camera", "immortal_characters", "disable_endgame",
+    "normalize_ai_difficulty", "garrison_multiplier", "campaign_movement_multiplier", "starting_treasury", "research_rate_multiplier",
+    "construction_time_multiplier", "upkeep_multiplier", "income_multiplier", "public_order_bonus", "corruption_multiplier",
+    "xp_multiplier", "magic_multiplier", "skill_points_per_level", "universal_rebalancer", "table_patch",
+    "lua_patch", "enable_dev_ui", "sql_script",
+];
+
+// Names of the available commands, plus the one clap adds by default.
+const COMMANDS: [&str; 7] = ["patch", "check", "clean", "games", "translate", "sql", "help"];
+
+// Flags clap handles at the top level, before any command.
+const TOP_LEVEL_FLAGS: [&str; 4] = ["-h", "--help", "-V", "--version"];
+
 //---------------------------------------------------------------------------//
 //                          Struct/Enum Definitions
 //---------------------------------------------------------------------------//
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-pub(crate) struct Cli {
+#[command(author, version, about, long_about = None, args_override_self = true)]
+pub struct Cli {
 
-    /// Make output more detailed.
-    #[arg(short, long)]
-    pub verbose: bool,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+
+    /// Patch the load order. This is the default command, used if no other command is provided.
+    #[command(args_override_self = true)]
+    Patch(PatchArgs),
+
+    /// Check that the game, the load order and the schema can be found and loaded, without patching anything.
+    Check(CheckArgs),
+
+    /// Remove the Pack generated by TWPatcher.
+    Clean(CleanArgs),
+
+    /// List the supported games, and where they're installed, if they are.
+    Games(GamesArgs),
+
+    /// Patch the load order only with the translations for the provided language.
+    Translate(TranslateArgs),
+
+    /// Patch the load order only with the provided SQL scripts.
+    Sql(SqlArgs),
+}
+
+/// Formats of load order files we can read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadOrderFormat {
+
+    /// User script format used by the game and most launchers, with `mod "pack_name.pack";` lines.
+    #[default]
+    Ca,
+
+    /// JSON export of WH3 Mod Manager.
+    Wh3mm,
+
+    /// XML export of Kaedrin Mod Manager.
+    Kmm,
+
+    /// Plain list of Packs or Workshop item ids, one per line. Lines starting with # are ignored.
+    TxtList,
+}
+
+/// Channels we can get updates from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateChannelArg {
+
+    /// Only stable releases.
+    #[default]
+    Stable,
+
+    /// Stable and beta releases.
+    Beta,
+}
+
+/// Ways we can report the progress of the long steps of a run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProgressMode {
+
+    /// Log the progress every 10%.
+    #[default]
+    Text,
+
+    /// Print the progress to stdout as JSON lines, with the event (started, progress or finished), the step, the current item and the total of items.
+    Json,
+
+    /// Do not report progress.
+    None,
+}
+
+/// Compression formats we can use for the generated Pack.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackCompression {
+
+    /// Do not compress the files of the Pack.
+    None,
+
+    /// LZMA. Supported since Warhammer 2. Smaller Packs, but slow to save.
+    Lzma,
+
+    /// Zstandard. Supported only in recent versions of Warhammer 3. Fast, with Packs a bit bigger than with LZMA.
+    Zstd,
+}
+
+/// How to write the load order as dependencies of the generated Pack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackDependencies {
+
+    /// Hard dependencies in the games that need them, and soft dependencies in the older games, as they crash with hard ones.
+    #[default]
+    Auto,
+
+    /// Hard dependencies, which the game needs to be loaded before the Pack.
+    Hard,
+
+    /// Soft dependencies, which are only listed in the Pack.
+    Soft,
+}
+
+/// How to round the unit sizes after applying the unit multiplier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnitMultiplierRounding {
+
+    /// Round down.
+    Floor,
+
+    /// Round to the nearest number.
+    #[default]
+    Round,
+
+    /// Round up.
+    Ceil,
+}
+
+/// Providers we can use to machine translate texts.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MachineTranslationProvider {
+
+    /// DeepL API. The API key is read from the TWPATCHER_DEEPL_API_KEY environment variable.
+    Deepl,
+
+    /// LibreTranslate API. The server is read from the TWPATCHER_LIBRETRANSLATE_URL environment variable, and the API key, if needed, from TWPATCHER_LIBRETRANSLATE_API_KEY.
+    Libretranslate,
+}
+
+/// Scope of a feature, limiting the Packs of the load order it works with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureScope {
+
+    /// Key of the feature, as the name of its argument with underscores instead of dashes.
+    pub feature: String,
+
+    /// If true, only the Packs matching the patterns are used. If false, all the Packs except the ones matching the patterns are used.
+    pub only: bool,
+
+    /// Pack names, with * and ? as wildcards.
+    pub patterns: Vec<String>,
+}
+
+/// External SQLite database attached to the SQL database before running the SQL scripts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SqlAttach {
+
+    /// Schema name used by the scripts to access the database's tables, as in `alias.table`.
+    pub alias: String,
+
+    /// Path of the database file.
+    pub path: PathBuf,
+}
+
+#[derive(Args, Clone, Default, Serialize)]
+pub struct PatchArgs {
+
+    /// Path of a JSON manifest with the options to use. Any option available through the CLI can be declared in it.
+    ///
+    /// Options passed through the CLI take priority over the ones in the manifest. Check the README for the manifest format.
+    #[arg(long, value_name = "MANIFEST_PATH")]
+    #[serde(skip)]
+    pub manifest: Option<PathBuf>,
+
+    /// Ignore the twpatcher.toml config file, if it exists.
+    #[arg(long)]
+    #[serde(skip)]
+    pub no_config: bool,
+
+    /// Name of a profile to load the options from. Profiles are saved per game with --save-profile.
+    ///
+    /// Options passed through the CLI or a manifest take priority over the ones in the profile.
+    #[arg(long, value_name = "PROFILE_NAME")]
+    #[serde(skip)]
+    pub profile: Option<String>,
+
+    /// Save all the options used in this execution (including the ones from the config file, the manifest and the profile) as a profile with the provided name.
+    ///
+    /// The profile is only saved if the patch works.
+    #[arg(long, value_name = "PROFILE_NAME")]
+    #[serde(skip)]
+    pub save_profile: Option<String>,
+
+    /// Make output more detailed. Repeat it for more detail: -v shows the details of each feature, -vv adds internal details, and -vvv adds the details of the libraries used.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
 
     /// Makes TWPatcher skip the updates check done at the start.
     #[arg(short, long)]
     pub skip_updates_check: bool,
 
+    /// Channel used to check for updates. Beta includes beta releases, which may be less stable.
+    #[arg(long, value_enum, value_name = "UPDATE_CHANNEL", default_value_t)]
+    pub update_channel: UpdateChannelArg,
+
+    /// Check for updates and report them, but never download or install them.
+    ///
+    /// It can also be enabled with `auto_install_updates = false` in the config file.
+    #[arg(long)]
+    pub check_updates_only: bool,
+
+    /// Path of the log file. If not provided, a new log file is created on each run in the logs folder of the config folder, keeping only the most recent ones.
+    ///
+    /// The log has one JSON object per line, with the run id, the time, the level, the section of the run and the message. It can only be set through the CLI.
+    #[arg(long, value_name = "LOG_PATH")]
+    #[serde(skip)]
+    pub log_file: Option<PathBuf>,
+
+    /// Log levels per section of the run or per module, in the format used by env_logger, like `translation_language=debug,sql_script=warn`.
+    ///
+    /// Sections are the keys of the features (as the name of their argument, with underscores), plus startup, load_order, schema and save.
+    /// A level without a name applies to everything else. Levels are: off, error, warn, info, debug and trace.
+    #[arg(long, value_parser = log_filter_parser, value_name = "FILTERS")]
+    pub log_filter: Option<String>,
+
+    /// Run in portable mode: the config and caches are kept in a config folder next to the executable, and updates are only reported, never installed.
+    ///
+    /// It's also enabled if there is a portable.txt file next to the executable.
+    #[arg(long)]
+    #[serde(skip)]
+    pub portable: bool,
+
+    /// Restore the version of TWPatcher used before the last update, and exit. It cannot be used with other options.
+    ///
+    /// Remember to use --skip-updates-check in later executions, or the update will be installed again.
+    #[arg(long, exclusive = true)]
+    #[serde(skip)]
+    pub rollback: bool,
+
+    /// Keep running as a server, receiving patch requests as JSON-RPC messages through stdin, one per line.
+    ///
+    /// The vanilla data, the schema and the SQL database are kept between requests, so re-patching after a load order change is way faster.
+    /// Check the README for the format of the requests.
+    #[arg(long)]
+    #[serde(skip)]
+    pub serve: bool,
+
+    /// Keep running after patching, and patch again each time the load order file, the mod list file or the Runcher profile changes.
+    #[arg(long, conflicts_with = "serve")]
+    #[serde(skip)]
+    pub watch: bool,
+
+    /// With --watch, also patch again each time a Pack is added, removed or changed in the data folder.
+    #[arg(long, requires = "watch")]
+    #[serde(skip)]
+    pub watch_data: bool,
+
+    /// Makes TWPatcher skip the schema update done at the start, and use the schemas it already has.
+    #[arg(long)]
+    pub skip_schema_update: bool,
+
+    /// Do not connect to the internet. This skips the updates check and the schema and translation downloads.
+    ///
+    /// The schema of the game needs to have been downloaded in a previous execution for this to work.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Commit (or any other git revision) of the schemas repo to use, instead of the latest one.
+    ///
+    /// Useful if a schema update breaks something in the middle of a campaign.
+    #[arg(long, value_name = "REVISION")]
+    pub schema_rev: Option<String>,
+
+    /// Folder with the schemas to use, instead of the ones downloaded from the schemas repo. The schemas in it are used as they are, without updating them.
+    #[arg(long, value_name = "SCHEMA_FOLDER", conflicts_with_all = ["schema_repo", "schema_rev"])]
+    pub schema_path: Option<PathBuf>,
+
+    /// URL of a git repo to download the schemas from, instead of the official one. It must have the same layout as the official repo.
+    #[arg(long, value_name = "SCHEMA_REPO_URL")]
+    pub schema_repo: Option<String>,
+
+    /// URL of a git repo to download the community translations from, instead of the official one. It must be a mirror of the official repo.
+    #[arg(long, value_name = "TRANSLATIONS_REPO_URL")]
+    pub translations_repo: Option<String>,
+
+    /// URL of a git repo to download the SQL presets from, instead of the official one. It must be a mirror of the official repo.
+    #[arg(long, value_name = "SQL_PRESETS_REPO_URL")]
+    pub sql_presets_repo: Option<String>,
+
+    /// Proxy to use for all network operations: update checks, and schema, translation and SQL preset downloads.
+    ///
+    /// It can also be set with the TWPATCHER_PROXY environment variable. If none is provided, the usual HTTPS_PROXY and HTTP_PROXY variables are used.
+    #[arg(long, value_name = "PROXY_URL")]
+    pub proxy: Option<String>,
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// How to report the progress of the long steps (loading the game data, building the SQL database, merging the translations and saving the Pack).
+    #[arg(long, value_enum, value_name = "PROGRESS_MODE", default_value_t)]
+    pub progress: ProgressMode,
+
+    /// If a feature fails, keep applying the rest of them instead of stopping, and report the failed ones at the end.
+    ///
+    /// The changes done by the failed features are discarded. The Pack is still generated, and TWPatcher exits with code 7 if any feature failed.
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Only print errors, and the path of the generated Pack at the end. Everything is still saved to the log. Implies --no-wait.
+    ///
+    /// Useful for launchers running TWPatcher in the background.
+    #[arg(short, long)]
+    pub quiet: bool,
+
     /// Game we are using this tool for.
     #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
     pub game: String,
 
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
     /// Name of the file that contains the load order. Has to exist in the game folder.
     ///
     /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon. In these TWPatcher will automatically use the user.script file instead.
-    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME")]
-    pub load_order_file_name: String,
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME", required_unless_present_any = ["runcher_profile", "mods", "mods_from", "serve"])]
+    pub load_order_file_name: Option<String>,
+
+    /// Format of the load order file.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon.
+    #[arg(long, value_enum, value_name = "LOAD_ORDER_FORMAT", default_value_t)]
+    pub load_order_format: LoadOrderFormat,
+
+    /// Name of a Runcher profile to read the load order from, instead of using a load order file.
+    ///
+    /// Workshop mods enabled in the profile are read from their Workshop folder.
+    #[arg(long, value_name = "PROFILE_NAME", conflicts_with_all = ["load_order_file_name", "mods", "mods_from"])]
+    pub runcher_profile: Option<String>,
+
+    /// List of Packs or Workshop item ids to use as load order, in order and separated with commas, instead of using a load order file.
+    #[arg(long, value_name = "PACK_NAMES", value_delimiter = ',', conflicts_with_all = ["load_order_file_name", "mods_from"])]
+    pub mods: Option<Vec<String>>,
+
+    /// Path of a file with the list of Packs or Workshop item ids to use as load order, one per line, instead of using a load order file.
+    ///
+    /// Use - to read the list from stdin.
+    #[arg(long, value_name = "PATH", conflicts_with = "load_order_file_name")]
+    pub mods_from: Option<PathBuf>,
+
+    /// Fail if any Pack in the load order is missing, cannot be read, is for another game, or is duplicated, instead of just warning about it and skipping it.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Do not search the load order folders for movie Packs not in the load order.
+    ///
+    /// Movie Packs are loaded by the game even if they're not in the load order, so this may make TWPatcher miss some of the mods the game loads.
+    /// Only use it if the search is too slow and you don't use movie Packs.
+    #[arg(long)]
+    pub no_movie_scan: bool,
+
+    /// Maximum amount of threads to use. If not provided, all the available cores are used.
+    ///
+    /// It's set once at startup, so in server mode the threads of each request are ignored.
+    #[arg(long, value_name = "THREADS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub threads: Option<u32>,
+
+    /// Packs to remove from the load order before patching, so they're ignored by all the features (yes, admits multiple ones).
+    ///
+    /// Supports * and ? wildcards, like "reshade*.pack".
+    #[arg(long, value_name = "PACK_NAME")]
+    pub exclude_pack: Option<Vec<String>>,
+
+    /// Limit the Packs of the load order a feature works with (yes, admits multiple ones).
+    ///
+    /// The format is FEATURE=only:PACKS or FEATURE=except:PACKS, where FEATURE is the name of the feature's argument
+    /// with underscores instead of dashes, and PACKS is a list of Pack names separated with commas. Pack names support * and ? wildcards.
+    /// For example: "unit_multiplier=only:my_overhaul.pack".
+    #[arg(long, value_parser = feature_scope_parser, value_name = "FEATURE=only|except:PACKS")]
+    pub feature_scope: Option<Vec<FeatureScope>>,
+
+    /// Path where to save a JSON report with all the files in the generated Pack, the features that produced them and,
+    /// for tables, how many rows were changed. The report is always written to the log.
+    #[arg(long, value_name = "REPORT_PATH")]
+    pub report: Option<PathBuf>,
+
+    /// Path where to save a report of the files and table rows overwritten between the mods of the load order,
+    /// and which mod wins in each case. Saved as HTML if the path ends in .html, or as Markdown otherwise.
+    #[arg(long, value_name = "CONFLICTS_REPORT_PATH")]
+    pub conflicts_report: Option<PathBuf>,
 
     /// Path where we want the Pack to be generated. If no Path is provided, the Pack will be generated in /data.
     ///
@@ -50,9 +455,26 @@ pub(crate) struct Cli {
     #[arg(short = 'p', long, value_name = "GENERATED_PACK_PATH")]
     pub generated_pack_path: Option<String>,
 
+    /// Compress the files of the generated Pack, if the game supports it. Useful to reduce the size of Packs with a lot of translations, at the cost of slower saves.
+    ///
+    /// If the game doesn't support the format, the Pack is saved with the best format it supports, or without compression.
+    #[arg(long, value_enum, value_name = "COMPRESSION_FORMAT")]
+    pub compress: Option<PackCompression>,
+
+    /// How to write the load order as dependencies of the generated Pack. Only change it if the game crashes or sorts the Packs wrong with the default.
+    #[arg(long, value_enum, value_name = "DEPENDENCIES_MODE", default_value_t)]
+    pub pack_dependencies: PackDependencies,
+
+    /// Do not write the load order as dependencies of the generated Pack. Takes priority over --pack-dependencies.
+    #[arg(long)]
+    pub no_pack_dependencies: bool,
+
     /// If supported, enable the script logging system of the game.
     ///
-    /// Supported only in: Warhammer 2, Warhammer 3, Troy, Pharaoh, Pharaoh Dynasties.
+    /// In Shogun 2, Rome 2, Attila and Thrones, which lack a logging system, the campaign scripts are wrapped
+    /// so their output and errors end up in a twpatcher_script_log.txt file in the game folder.
+    ///
+    /// Supported only in: Shogun 2, Rome 2, Attila, Thrones, Warhammer 2, Warhammer 3, Troy, Three Kingdoms, Pharaoh, Pharaoh Dynasties.
     #[arg(short, long)]
     pub enable_logging: bool,
 
@@ -66,10 +488,70 @@ pub(crate) struct Cli {
 
     /// Remove the "Siege Attacker" attribute from everything but artillery units.
     ///
-    /// Supported only in: Warhammer 3.
+    /// Supported only in: Warhammer 2, Warhammer 3, Troy.
     #[arg(short = 'a', long)]
     pub remove_siege_attacker: bool,
 
+    /// Remove the time limit of battles, so timed battles (like sieges) never end due to running out of time.
+    ///
+    /// Supported only in: Rome 2, Attila, Thrones, Warhammer, Warhammer 2, Warhammer 3, Three Kingdoms, Troy, Pharaoh, Pharaoh Dynasties.
+    #[arg(long)]
+    pub remove_battle_timer: bool,
+
+    /// Make all factions (except rebels) selectable in all campaigns.
+    ///
+    /// Supported only in: Rome 2, Warhammer 2, Warhammer 3.
+    #[arg(long)]
+    pub unlock_all_factions: bool,
+
+    /// Remove the agent caps and the cooldowns of agent actions, so you can recruit as many heroes as you want.
+    ///
+    /// Supported only in: Warhammer 3, Three Kingdoms.
+    #[arg(long)]
+    pub remove_agent_caps: bool,
+
+    /// Remove the recruitment caps of units, so you can recruit as many units of the same type as you want.
+    ///
+    /// Supported only in: Warhammer 3, Troy, Pharaoh, Pharaoh Dynasties.
+    #[arg(long)]
+    pub remove_unit_caps: bool,
+
+    /// Remove the bonuses the AI gets on higher campaign difficulties, while keeping the ones that affect the player.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3, Three Kingdoms.
+    #[arg(long)]
+    pub normalize_ai_difficulty: bool,
+
+    /// Remove the supply lines penalty, so having more armies doesn't increase the upkeep of all of them.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3.
+    #[arg(long)]
+    pub remove_supply_lines: bool,
+
+    /// Remove the damage attrition does to armies in campaign.
+    ///
+    /// Supported only in: Attila, Warhammer 3.
+    #[arg(long)]
+    pub no_attrition: bool,
+
+    /// Unlock the height and pitch limits of the battle camera, so you can take cinematic screenshots.
+    ///
+    /// Supported only in: Attila, Warhammer 2, Warhammer 3, Three Kingdoms.
+    #[arg(long)]
+    pub unlock_battle_camera: bool,
+
+    /// Make characters immortal, so they never die of old age, wounds or after battles.
+    ///
+    /// Supported only in: Warhammer, Warhammer 2, Warhammer 3, Three Kingdoms.
+    #[arg(long)]
+    pub immortal_characters: bool,
+
+    /// Disable the endgame crises (Warhammer 3) and the chaos invasion (Warhammer 2), so they never trigger.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3.
+    #[arg(long)]
+    pub disable_endgame: bool,
+
     /// Language for which TWPatcher will apply translations and patch locs for.
     ///
     /// Make sure to use this if you have the "no text from mods and my game is not in english" issue.
@@ -79,9 +561,51 @@ pub(crate) struct Cli {
     /// is the XX of the language you're using in the game.
     ///
     /// For example, for spanish, the file is called local_sp.pack, so here you'll have to use "sp".
+    ///
+    /// You can also provide a list of languages separated with commas, like "sp,fr". The first one has to be the one you're using in the game,
+    /// and the rest are used as fallbacks for the texts that are not translated (or are outdated) in the first one.
     #[arg(short, long, value_name = "TRANSLATION_LANGUAGE")]
     pub translation_language: Option<String>,
 
+    /// Folder where to save a report of the texts that couldn't be translated, with a TSV file per Pack.
+    ///
+    /// The report includes texts without translation, texts that fell back to english, and texts removed by the optimizer.
+    /// Only used if --translation-language is provided.
+    #[arg(long, value_name = "TRANSLATION_REPORT_FOLDER")]
+    pub translation_report: Option<PathBuf>,
+
+    /// Extra folders to read translations from (yes, admits multiple ones), with priority over the local and community translations.
+    ///
+    /// Each folder needs to have the same layout as the translations folder: a folder per game, with the translations of each Pack inside.
+    /// Useful to test translations still in progress.
+    #[arg(long, value_name = "TRANSLATION_FOLDER")]
+    pub translation_dir: Option<Vec<PathBuf>>,
+
+    /// Packs to leave out of the translation process (yes, admits multiple ones), so they keep their own locs as they are.
+    ///
+    /// Supports * and ? wildcards. Only used if --translation-language is provided.
+    #[arg(long, value_name = "PACK_NAME")]
+    pub no_translate_pack: Option<Vec<String>>,
+
+    /// Do not optimize the translated texts. Use it if some texts from mods are missing after translating them.
+    ///
+    /// Only used if --translation-language is provided.
+    #[arg(long)]
+    pub no_translation_optimize: bool,
+
+    /// Machine translate the texts without a community translation, using the provided provider.
+    ///
+    /// Translated texts are cached locally, so they're only requested once. In offline mode, only cached texts are used.
+    /// Only used if --translation-language is provided.
+    #[arg(long, value_enum, value_name = "PROVIDER")]
+    pub machine_translate: Option<MachineTranslationProvider>,
+
+    /// Replace the default fonts of the game with the ones for the translation language, taken from the game's data, so its texts are shown properly.
+    ///
+    /// Only needed for chinese, japanese and korean. Only used if --translation-language is provided.
+    #[arg(long)]
+    pub patch_fonts: bool,
+
     /// Multiplier to apply to unit sizes to make them bigger. In case of single entities, it multiplies their health instead.
     ///
     /// It also takes care of multiplying certain parameters that scale with difficulty, like tower and magic damage,
@@ -91,11 +615,108 @@ pub(crate) struct Cli {
     #[arg(short = 'm', long, value_name = "MULTIPLIER")]
     pub unit_multiplier: Option<f64>,
 
+    /// How to round the unit sizes after applying the unit multiplier.
+    ///
+    /// Only used if --unit-multiplier is provided.
+    #[arg(long, value_enum, default_value_t, value_name = "ROUNDING")]
+    pub unit_multiplier_rounding: UnitMultiplierRounding,
+
+    /// Maximum amount of men a unit can have after applying the unit multiplier, to not go over the limits of the engine.
+    ///
+    /// Units that were already bigger than this are not made smaller. Units with engines or mounts are capped keeping the amount of men per engine or mount.
+    /// Only used if --unit-multiplier is provided.
+    #[arg(long, value_name = "SIZE")]
+    pub unit_size_cap: Option<i32>,
+
+    /// Path to a file with the units that should not be scaled by the unit multiplier, like regiments of renown that are already huge. One per line.
+    ///
+    /// Each line can be an unit key or a pattern with * and ? as wildcards. In Warhammer 3 both main unit and land unit keys are checked.
+    /// In Three Kingdoms, only land unit keys. Empty lines and lines starting with # are ignored. Only used if --unit-multiplier is provided.
+    #[arg(long, value_name = "UNITS_FILE")]
+    pub unit_multiplier_exclude: Option<PathBuf>,
+
+    /// Multiplier to apply to the amount of units in settlement garrisons. Unlike the unit multiplier, it doesn't touch unit sizes,
+    /// so you can make settlements harder to take without changing the size of field armies.
+    ///
+    /// It has to be 1 or greater, as garrison units can only be added, not removed.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(long, value_parser = garrison_multiplier_parser, value_name = "MULTIPLIER")]
+    pub garrison_multiplier: Option<f64>,
+
+    /// Multiplier to apply to the reserves and recharge rate of the winds of magic in battle.
+    ///
+    /// Useful to keep magic relevant when using the unit multiplier, or just to make battles more (or less) magic-heavy.
+    ///
+    /// Supported only in: Warhammer, Warhammer 2, Warhammer 3.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub magic_multiplier: Option<f64>,
+
+    /// Multiplier to apply to the campaign movement range of armies and characters.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3, Three Kingdoms.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub campaign_movement_multiplier: Option<f64>,
+
+    /// Amount of money all factions start the campaign with. The same amount is used on all difficulties.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(long, value_name = "AMOUNT")]
+    pub starting_treasury: Option<i32>,
+
+    /// Multiplier to apply to the research rate of technologies. For example, 2 means technologies take half the research to unlock.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub research_rate_multiplier: Option<f64>,
+
+    /// Multiplier to apply to the construction time of buildings. For example, 0.5 means buildings take half the turns to build.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub construction_time_multiplier: Option<f64>,
+
+    /// Multiplier to apply to the upkeep of all units.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub upkeep_multiplier: Option<f64>,
+
+    /// Multiplier to apply to the income buildings generate.
+    ///
+    /// Supported only in: Rome 2, Attila, Thrones of Britannia, Warhammer, Warhammer 2, Warhammer 3.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub income_multiplier: Option<f64>,
+
+    /// Multiplier to apply to the experience characters gain. For example, 2 means characters level up twice as fast.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3, Three Kingdoms.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub xp_multiplier: Option<f64>,
+
+    /// Amount of skill points characters get on each level up.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3, Three Kingdoms, Troy, Pharaoh, Pharaoh Dynasties.
+    #[arg(long, value_name = "SKILL_POINTS")]
+    pub skill_points_per_level: Option<u32>,
+
+    /// Public order bonus (or penalty, if negative) to apply to all the provinces of the player.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3.
+    #[arg(long, value_name = "PUBLIC_ORDER", allow_negative_numbers = true)]
+    pub public_order_bonus: Option<i32>,
+
+    /// Multiplier to apply to the effects that add corruption to provinces, from buildings and characters.
+    ///
+    /// Supported only in: Warhammer 2, Warhammer 3.
+    #[arg(long, value_parser = multiplier_parser, value_name = "MULTIPLIER")]
+    pub corruption_multiplier: Option<f64>,
+
     /// EXPERIMENTAL
     ///
     /// It tries to rebalance your load order around the overhaul you specify.
     ///
-    /// Supported only in: Warhammer 3.
+    /// Supported only in: Warhammer 3, Three Kingdoms.
     #[arg(short, long, value_name = "BASE_MOD")]
     pub universal_rebalancer: Option<String>,
 
@@ -104,44 +725,1278 @@ pub(crate) struct Cli {
     /// It tries to execute the provided sql scripts (yes, admits multiple ones) over the load order.
     ///
     /// For each script, the param is a string with the script path, followed by all the consecutive params in order, everything separated with ;.
+    /// Params can also be provided by name, as key=value. Params without a value nor a default value are asked for, if possible.
     #[arg(long, value_parser = sql_script_parser, value_name = "SCRIPT_PATH;PARAMS")]
+    #[serde(serialize_with = "sql_script_serializer")]
     pub sql_script: Option<Vec<(PathBuf, Vec<String>)>>,
 
+    /// EXPERIMENTAL
+    ///
+    /// It executes all the .sql scripts in the provided folder over the load order, sorted by name, after the ones provided with --sql-script.
+    ///
+    /// The params of each script are read from a file with the same name and the .params extension, one param per line.
+    #[arg(long, value_name = "SCRIPT_FOLDER")]
+    pub sql_script_dir: Option<PathBuf>,
+
+    /// EXPERIMENTAL
+    ///
+    /// It executes the provided SQL scripts (yes, admits multiple ones) from the community scripts repo, by name, after the ones provided with --sql-script and --sql-script-dir.
+    ///
+    /// For each script, the param is a string with the script name, followed by all the consecutive params in order, everything separated with ;.
+    /// Params can also be provided by name, as key=value.
+    #[arg(long, value_parser = sql_preset_parser, value_name = "SCRIPT_NAME;PARAMS")]
+    #[serde(serialize_with = "sql_preset_serializer")]
+    pub sql_preset: Option<Vec<(String, Vec<String>)>>,
+
+    /// EXPERIMENTAL
+    ///
+    /// It executes the provided SQL queries (yes, admits multiple ones) over the load order, after the SQL scripts.
+    ///
+    /// Unlike scripts, queries have no metadata, so the tables they edit are taken from their INSERT, REPLACE, UPDATE and DELETE statements.
+    #[arg(long, value_name = "QUERY")]
+    pub sql_query: Option<Vec<String>>,
+
+    /// EXPERIMENTAL
+    ///
+    /// It attaches the provided SQLite databases (yes, admits multiple ones) to the SQL database, so SQL scripts and queries can read from them.
+    ///
+    /// Their tables are accessed as `alias.table_name`. Attached databases are never modified by TWPatcher, but scripts can still write to them.
+    #[arg(long, value_parser = sql_attach_parser, value_name = "ALIAS=DB_PATH")]
+    pub sql_attach: Option<Vec<SqlAttach>>,
+
+    /// Export the SQL database with the vanilla and modded data merged, before any SQL script is applied, so it can be explored with other tools.
+    ///
+    /// If --export-db-tables is provided, the path is a folder, and the tables are exported to CSV files in it.
+    #[arg(long, value_name = "PATH")]
+    pub export_db: Option<PathBuf>,
+
+    /// Tables to export to CSV with --export-db, separated with commas. Supports * and ? wildcards.
+    #[arg(long, value_name = "TABLE_NAMES", value_delimiter = ',', requires = "export_db")]
+    pub export_db_tables: Option<Vec<String>>,
+
+    /// It applies the table patches in the provided TOML files (yes, admits multiple ones) over the load order.
+    ///
+    /// Each patch describes a table, a column, an optional row filter, and an operation (set, multiply or add) to apply to that column.
+    /// Check the README for the file format.
+    #[arg(long, value_name = "TABLE_PATCH_PATH")]
+    pub table_patch: Option<Vec<PathBuf>>,
+
+    /// It executes the provided Lua scripts (yes, admits multiple ones) over the load order.
+    ///
+    /// Scripts get access to a `twpatcher` global with functions to query and modify tables, locs and text files. Check the README for the available functions.
+    #[arg(long, value_name = "LUA_PATCH_PATH")]
+    pub lua_patch: Option<Vec<PathBuf>>,
+
     /// It enables the dev-restricted parts of the UI. Note that the dev-restricted buttons may require things not shipped with the game, and will not work.
     #[arg(short = 'd', long)]
     pub enable_dev_ui: bool,
+
+    /// UI files to skip when enabling the dev-restricted parts of the UI (yes, admits multiple ones).
+    ///
+    /// Each value is a path inside the game's data, and it can contain * and ? as wildcards. For example: "ui/campaign ui/*".
+    #[arg(long, value_name = "GLOB")]
+    pub dev_ui_exclude: Option<Vec<String>>,
 }
 
-//---------------------------------------------------------------------------//
-//                          Custom parsers
-//---------------------------------------------------------------------------//
+#[derive(Args)]
+pub struct CheckArgs {
 
-fn sql_script_parser(src: &str) -> Result<(PathBuf, Vec<String>)> {
-    let mut reader = ReaderBuilder::new()
-        .delimiter(b';')
-        .quoting(true)
-        .has_headers(false)
-        .flexible(true)
-        .from_reader(src.as_bytes());
+    /// Make output more detailed. Repeat it for more detail: -v shows the details of each feature, -vv adds internal details, and -vvv adds the details of the libraries used.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
 
-    if let Some(Ok(record)) = reader.records().next() {
-        if record.is_empty() {
-            return Err(anyhow!("Incorrect CSV input."));
-        } else {
-            let path = PathBuf::from(&record[0]);
-            if !path.is_file() {
-                return Err(anyhow!("Path {} doesn't belong to a valid file.", &record[0]));
-            }
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
 
-            let params = if record.len() >= 2 {
-                (1..record.len()).map(|x| record[x].to_owned()).collect::<Vec<_>>()
-            } else {
-                vec![]
-            };
+    /// Makes TWPatcher skip the schema update done at the start, and use the schemas it already has.
+    #[arg(long)]
+    pub skip_schema_update: bool,
 
-            return Ok((path, params));
-        }
-    }
+    /// Do not connect to the internet. This skips the schema download.
+    ///
+    /// The schema of the game needs to have been downloaded in a previous execution for this to work.
+    #[arg(long)]
+    pub offline: bool,
 
-    Err(anyhow!("Incorrect CSV input."))
+    /// Commit (or any other git revision) of the schemas repo to use, instead of the latest one.
+    #[arg(long, value_name = "REVISION")]
+    pub schema_rev: Option<String>,
+
+    /// Folder with the schemas to use, instead of the ones downloaded from the schemas repo. The schemas in it are used as they are, without updating them.
+    #[arg(long, value_name = "SCHEMA_FOLDER", conflicts_with_all = ["schema_repo", "schema_rev"])]
+    pub schema_path: Option<PathBuf>,
+
+    /// URL of a git repo to download the schemas from, instead of the official one. It must have the same layout as the official repo.
+    #[arg(long, value_name = "SCHEMA_REPO_URL")]
+    pub schema_repo: Option<String>,
+
+    /// Proxy to use to download the schemas.
+    ///
+    /// It can also be set with the TWPATCHER_PROXY environment variable. If none is provided, the usual HTTPS_PROXY and HTTP_PROXY variables are used.
+    #[arg(long, value_name = "PROXY_URL")]
+    pub proxy: Option<String>,
+
+    /// Game we are using this tool for.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Name of the file that contains the load order. Has to exist in the game folder.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon. In these TWPatcher will automatically use the user.script file instead.
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME")]
+    pub load_order_file_name: String,
+
+    /// Format of the load order file.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon.
+    #[arg(long, value_enum, value_name = "LOAD_ORDER_FORMAT", default_value_t)]
+    pub load_order_format: LoadOrderFormat,
+
+    /// Fail if any Pack in the load order is missing, cannot be read, is for another game, or is duplicated, instead of just warning about it and skipping it.
+    #[arg(long)]
+    pub strict: bool,
+}
+
+#[derive(Args)]
+pub struct GamesArgs {
+
+    /// Print the list as JSON, for launchers and scripts.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Name of the load order file to check in the folder of each installed game.
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME", default_value = "used_mods.txt")]
+    pub load_order_file_name: String,
+}
+
+#[derive(Args)]
+pub struct CleanArgs {
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Game we are using this tool for.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Path where the Pack was generated, if it was generated with a custom path.
+    #[arg(short = 'p', long, value_name = "GENERATED_PACK_PATH")]
+    pub generated_pack_path: Option<String>,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct TranslateArgs {
+
+    #[command(subcommand)]
+    pub command: Option<TranslateCommands>,
+
+    /// Make output more detailed. Repeat it for more detail: -v shows the details of each feature, -vv adds internal details, and -vvv adds the details of the libraries used.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Makes TWPatcher skip the updates check done at the start.
+    #[arg(short, long)]
+    pub skip_updates_check: bool,
+
+    /// Channel used to check for updates. Beta includes beta releases, which may be less stable.
+    #[arg(long, value_enum, value_name = "UPDATE_CHANNEL", default_value_t)]
+    pub update_channel: UpdateChannelArg,
+
+    /// Makes TWPatcher skip the schema update done at the start, and use the schemas it already has.
+    #[arg(long)]
+    pub skip_schema_update: bool,
+
+    /// Do not connect to the internet. This skips the updates check and the schema and translation downloads.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Game we are using this tool for.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Name of the file that contains the load order. Has to exist in the game folder.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon. In these TWPatcher will automatically use the user.script file instead.
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME")]
+    pub load_order_file_name: String,
+
+    /// Format of the load order file.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon.
+    #[arg(long, value_enum, value_name = "LOAD_ORDER_FORMAT", default_value_t)]
+    pub load_order_format: LoadOrderFormat,
+
+    /// Path where we want the Pack to be generated. If no Path is provided, the Pack will be generated in /data.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(short = 'p', long, value_name = "GENERATED_PACK_PATH")]
+    pub generated_pack_path: Option<String>,
+
+    /// Language for which TWPatcher will apply translations and patch locs for.
+    #[arg(short, long, value_name = "TRANSLATION_LANGUAGE")]
+    pub translation_language: String,
+}
+
+#[derive(Subcommand)]
+pub enum TranslateCommands {
+
+    /// Export the texts of each Pack in the load order to TSV files, so they can be used as a base for new translations.
+    Export(TranslateExportArgs),
+
+    /// Compare the english texts of the game against the ones the community translations are based on, to find the texts that need to be retranslated after a game update.
+    Diff(TranslateDiffArgs),
+}
+
+#[derive(Args)]
+pub struct TranslateExportArgs {
+
+    /// Make output more detailed. Repeat it for more detail: -v shows the details of each feature, -vv adds internal details, and -vvv adds the details of the libraries used.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Makes TWPatcher skip the schema update done at the start, and use the schemas it already has.
+    #[arg(long)]
+    pub skip_schema_update: bool,
+
+    /// Do not connect to the internet. This skips the schema download.
+    ///
+    /// The schema of the game needs to have been downloaded in a previous execution for this to work.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Commit (or any other git revision) of the schemas repo to use, instead of the latest one.
+    #[arg(long, value_name = "REVISION")]
+    pub schema_rev: Option<String>,
+
+    /// Folder with the schemas to use, instead of the ones downloaded from the schemas repo. The schemas in it are used as they are, without updating them.
+    #[arg(long, value_name = "SCHEMA_FOLDER", conflicts_with_all = ["schema_repo", "schema_rev"])]
+    pub schema_path: Option<PathBuf>,
+
+    /// URL of a git repo to download the schemas from, instead of the official one. It must have the same layout as the official repo.
+    #[arg(long, value_name = "SCHEMA_REPO_URL")]
+    pub schema_repo: Option<String>,
+
+    /// Proxy to use to download the schemas.
+    ///
+    /// It can also be set with the TWPATCHER_PROXY environment variable. If none is provided, the usual HTTPS_PROXY and HTTP_PROXY variables are used.
+    #[arg(long, value_name = "PROXY_URL")]
+    pub proxy: Option<String>,
+
+    /// Game we are using this tool for.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Name of the file that contains the load order. Has to exist in the game folder.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon. In these TWPatcher will automatically use the user.script file instead.
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME")]
+    pub load_order_file_name: String,
+
+    /// Format of the load order file.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon.
+    #[arg(long, value_enum, value_name = "LOAD_ORDER_FORMAT", default_value_t)]
+    pub load_order_format: LoadOrderFormat,
+
+    /// Folder where the TSV files will be exported to. They're exported to a subfolder with the key of the game, one file per Pack.
+    #[arg(short, long, value_name = "OUTPUT_PATH")]
+    pub output_path: PathBuf,
+}
+
+#[derive(Args)]
+pub struct TranslateDiffArgs {
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Do not connect to the internet. This skips the download of the community translations.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// URL of a git repo to download the community translations from, instead of the official one. It must be a mirror of the official repo.
+    #[arg(long, value_name = "TRANSLATIONS_REPO_URL")]
+    pub translations_repo: Option<String>,
+
+    /// Proxy to use to download the community translations.
+    ///
+    /// It can also be set with the TWPATCHER_PROXY environment variable. If none is provided, the usual HTTPS_PROXY and HTTP_PROXY variables are used.
+    #[arg(long, value_name = "PROXY_URL")]
+    pub proxy: Option<String>,
+
+    /// Game we are using this tool for. It needs to be in english.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Path of the TSV file where the differences will be saved.
+    #[arg(short, long, value_name = "OUTPUT_PATH")]
+    pub output_path: PathBuf,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct SqlArgs {
+
+    #[command(subcommand)]
+    pub command: Option<SqlCommands>,
+
+    /// Make output more detailed. Repeat it for more detail: -v shows the details of each feature, -vv adds internal details, and -vvv adds the details of the libraries used.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Makes TWPatcher skip the updates check done at the start.
+    #[arg(short, long)]
+    pub skip_updates_check: bool,
+
+    /// Channel used to check for updates. Beta includes beta releases, which may be less stable.
+    #[arg(long, value_enum, value_name = "UPDATE_CHANNEL", default_value_t)]
+    pub update_channel: UpdateChannelArg,
+
+    /// Makes TWPatcher skip the schema update done at the start, and use the schemas it already has.
+    #[arg(long)]
+    pub skip_schema_update: bool,
+
+    /// Do not connect to the internet. This skips the updates check and the schema and translation downloads.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Game we are using this tool for.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Name of the file that contains the load order. Has to exist in the game folder.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon. In these TWPatcher will automatically use the user.script file instead.
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME")]
+    pub load_order_file_name: String,
+
+    /// Format of the load order file.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon.
+    #[arg(long, value_enum, value_name = "LOAD_ORDER_FORMAT", default_value_t)]
+    pub load_order_format: LoadOrderFormat,
+
+    /// Path where we want the Pack to be generated. If no Path is provided, the Pack will be generated in /data.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon, Shogun 2.
+    #[arg(short = 'p', long, value_name = "GENERATED_PACK_PATH")]
+    pub generated_pack_path: Option<String>,
+
+    /// SQL scripts to execute over the load order (yes, admits multiple ones).
+    ///
+    /// For each script, the param is a string with the script path, followed by all the consecutive params in order, everything separated with ;.
+    /// Params can also be provided by name, as key=value. Params without a value nor a default value are asked for, if possible.
+    #[arg(long, required_unless_present = "sql_script_dir", value_parser = sql_script_parser, value_name = "SCRIPT_PATH;PARAMS")]
+    pub sql_script: Vec<(PathBuf, Vec<String>)>,
+
+    /// Folder with SQL scripts to execute over the load order, sorted by name, after the ones provided with --sql-script.
+    ///
+    /// The params of each script are read from a file with the same name and the .params extension, one param per line.
+    #[arg(long, value_name = "SCRIPT_FOLDER")]
+    pub sql_script_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum SqlCommands {
+
+    /// Check that a SQL script is valid: that its metadata can be read, that the tables it uses exist in the schema, and that it executes without errors.
+    ///
+    /// The script is executed against a temporary in-memory database, so nothing is patched.
+    Validate(SqlValidateArgs),
+}
+
+#[derive(Args)]
+pub struct SqlValidateArgs {
+
+    /// Make output more detailed. Repeat it for more detail: -v shows the details of each feature, -vv adds internal details, and -vvv adds the details of the libraries used.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Close immediately on errors, instead of waiting 60 seconds to give you time to read the log. Useful for automation.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Makes TWPatcher skip the schema update done at the start, and use the schemas it already has.
+    #[arg(long)]
+    pub skip_schema_update: bool,
+
+    /// Do not connect to the internet. This skips the schema download.
+    ///
+    /// The schema of the game needs to have been downloaded in a previous execution for this to work.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Commit (or any other git revision) of the schemas repo to use, instead of the latest one.
+    #[arg(long, value_name = "REVISION")]
+    pub schema_rev: Option<String>,
+
+    /// Folder with the schemas to use, instead of the ones downloaded from the schemas repo. The schemas in it are used as they are, without updating them.
+    #[arg(long, value_name = "SCHEMA_FOLDER", conflicts_with_all = ["schema_repo", "schema_rev"])]
+    pub schema_path: Option<PathBuf>,
+
+    /// URL of a git repo to download the schemas from, instead of the official one. It must have the same layout as the official repo.
+    #[arg(long, value_name = "SCHEMA_REPO_URL")]
+    pub schema_repo: Option<String>,
+
+    /// Proxy to use to download the schemas.
+    ///
+    /// It can also be set with the TWPATCHER_PROXY environment variable. If none is provided, the usual HTTPS_PROXY and HTTP_PROXY variables are used.
+    #[arg(long, value_name = "PROXY_URL")]
+    pub proxy: Option<String>,
+
+    /// Game we are using this tool for.
+    #[arg(short, long, value_name = "GAME", value_parser = PossibleValuesParser::new(SupportedGames::default().game_keys_sorted().to_vec()))]
+    pub game: String,
+
+    /// Folder where the game is installed. Use it if the game is not found automatically, like with moved Steam libraries or Wine prefixes.
+    #[arg(long, value_name = "GAME_PATH")]
+    pub game_path: Option<PathBuf>,
+
+    /// Data folder of the game. Use it if the game has its data folder in a non-standard place.
+    #[arg(long, value_name = "DATA_PATH")]
+    pub data_path: Option<PathBuf>,
+
+    /// Name of the file that contains the load order, if you want to check the script against the modded data too. Has to exist in the game folder.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon. In these TWPatcher will automatically use the user.script file instead.
+    #[arg(short, long, value_name = "LOAD_ORDER_FILE_NAME")]
+    pub load_order_file_name: Option<String>,
+
+    /// Format of the load order file.
+    ///
+    /// NOT SUPPORTED/IGNORED IN: Empire, Napoleon.
+    #[arg(long, value_enum, value_name = "LOAD_ORDER_FORMAT", default_value_t)]
+    pub load_order_format: LoadOrderFormat,
+
+    /// SQL script to validate. It's a string with the script path, followed by all the consecutive params in order, everything separated with ;.
+    ///
+    /// Params can also be provided by name, as key=value.
+    #[arg(value_parser = sql_script_parser, value_name = "SCRIPT_PATH;PARAMS")]
+    pub sql_script: (PathBuf, Vec<String>),
+}
+
+//---------------------------------------------------------------------------//
+//                          Implementations
+//---------------------------------------------------------------------------//
+
+impl Cli {
+
+    /// This function parses the arguments passed to the program.
+    ///
+    /// If no command is provided, the patch command is used, so the tool keeps working with the arguments used before commands existed.
+    pub fn parse_args() -> Result<Self> {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+
+        // Without arguments, or with a top-level flag, we let clap show the list of commands or the version.
+        let is_top_level = args.get(1)
+            .map(|arg| {
+                let arg = arg.to_string_lossy();
+                COMMANDS.contains(&&*arg) || TOP_LEVEL_FLAGS.contains(&&*arg)
+            })
+            .unwrap_or(true);
+
+        if !is_top_level {
+            args.insert(1, OsString::from("patch"));
+        }
+
+        // Only the patch command supports config files, profiles and manifests. Rollbacks cannot be used with other options.
+        if args.get(1).is_some_and(|arg| arg == "patch") && !args.iter().any(|arg| arg == "--rollback") {
+            let command_args = args.split_off(2);
+            args.append(&mut PatchArgs::merge_args(command_args)?);
+        }
+
+        Ok(Self::parse_from(args))
+    }
+}
+
+impl Commands {
+
+    /// This function returns if we should skip the wait before closing on errors.
+    pub fn no_wait(&self) -> bool {
+        match self {
+            Self::Patch(args) => args.no_wait,
+            Self::Check(args) => args.no_wait,
+            Self::Clean(args) => args.no_wait,
+            Self::Games(_) => false,
+            Self::Translate(args) => match args.command {
+                Some(TranslateCommands::Export(ref args)) => args.no_wait,
+                Some(TranslateCommands::Diff(ref args)) => args.no_wait,
+                None => args.no_wait,
+            },
+            Self::Sql(args) => match args.command {
+                Some(SqlCommands::Validate(ref args)) => args.no_wait,
+                None => args.no_wait,
+            },
+        }
+    }
+
+    /// This function returns the verbosity level requested for the command.
+    pub fn verbosity(&self) -> u8 {
+        match self {
+            Self::Patch(args) => args.verbose,
+            Self::Check(args) => args.verbose,
+            Self::Clean(_) => 0,
+            Self::Games(_) => 0,
+            Self::Translate(args) => match args.command {
+                Some(TranslateCommands::Export(ref args)) => args.verbose,
+                Some(TranslateCommands::Diff(_)) => 0,
+                None => args.verbose,
+            },
+            Self::Sql(args) => match args.command {
+                Some(SqlCommands::Validate(ref args)) => args.verbose,
+                None => args.verbose,
+            },
+        }
+    }
+
+    /// This function returns how to report progress.
+    pub fn progress(&self) -> ProgressMode {
+        match self {
+            Self::Patch(args) => args.progress,
+            _ => ProgressMode::default(),
+        }
+    }
+
+    /// This function returns if only errors should be printed.
+    pub fn quiet(&self) -> bool {
+        match self {
+            Self::Patch(args) => args.quiet,
+            Self::Games(args) => args.json,
+            _ => false,
+        }
+    }
+
+    /// This function returns the maximum amount of threads requested for the command, if any.
+    pub fn threads(&self) -> Option<u32> {
+        match self {
+            Self::Patch(args) => args.threads,
+            _ => None,
+        }
+    }
+
+    /// This function returns the log filters requested for the command, if any.
+    pub fn log_filter(&self) -> Option<&str> {
+        match self {
+            Self::Patch(args) => args.log_filter.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl FeatureScope {
+
+    /// This function checks if a Pack is within the scope.
+    pub fn contains(&self, pack_name: &str) -> bool {
+        let is_match = self.patterns.iter().any(|pattern| glob_match(pattern, pack_name));
+        is_match == self.only
+    }
+}
+
+impl Display for FeatureScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}:{}", self.feature, if self.only { "only" } else { "except" }, self.patterns.join(","))
+    }
+}
+
+impl Serialize for FeatureScope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Display for SqlAttach {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.alias, self.path.display())
+    }
+}
+
+impl Serialize for SqlAttach {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl From<TranslateArgs> for PatchArgs {
+    fn from(args: TranslateArgs) -> Self {
+        Self {
+            verbose: args.verbose,
+            skip_updates_check: args.skip_updates_check,
+            update_channel: args.update_channel,
+            offline: args.offline,
+            skip_schema_update: args.skip_schema_update,
+            no_wait: args.no_wait,
+            game: args.game,
+            game_path: args.game_path,
+            data_path: args.data_path,
+            load_order_file_name: Some(args.load_order_file_name),
+            load_order_format: args.load_order_format,
+            generated_pack_path: args.generated_pack_path,
+            translation_language: Some(args.translation_language),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<SqlArgs> for PatchArgs {
+    fn from(args: SqlArgs) -> Self {
+        Self {
+            verbose: args.verbose,
+            skip_updates_check: args.skip_updates_check,
+            update_channel: args.update_channel,
+            offline: args.offline,
+            skip_schema_update: args.skip_schema_update,
+            no_wait: args.no_wait,
+            game: args.game,
+            game_path: args.game_path,
+            data_path: args.data_path,
+            load_order_file_name: Some(args.load_order_file_name),
+            load_order_format: args.load_order_format,
+            generated_pack_path: args.generated_pack_path,
+            sql_script: Some(args.sql_script).filter(|scripts| !scripts.is_empty()),
+            sql_script_dir: args.sql_script_dir,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<CheckArgs> for PatchArgs {
+    fn from(args: CheckArgs) -> Self {
+        Self {
+            verbose: args.verbose,
+            offline: args.offline,
+            skip_schema_update: args.skip_schema_update,
+            schema_rev: args.schema_rev,
+            schema_path: args.schema_path,
+            schema_repo: args.schema_repo,
+            proxy: args.proxy,
+            no_wait: args.no_wait,
+            game: args.game,
+            game_path: args.game_path,
+            data_path: args.data_path,
+            load_order_file_name: Some(args.load_order_file_name),
+            load_order_format: args.load_order_format,
+            strict: args.strict,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<TranslateExportArgs> for PatchArgs {
+    fn from(args: TranslateExportArgs) -> Self {
+        Self {
+            verbose: args.verbose,
+            offline: args.offline,
+            skip_schema_update: args.skip_schema_update,
+            schema_rev: args.schema_rev,
+            schema_path: args.schema_path,
+            schema_repo: args.schema_repo,
+            proxy: args.proxy,
+            no_wait: args.no_wait,
+            game: args.game,
+            game_path: args.game_path,
+            data_path: args.data_path,
+            load_order_file_name: Some(args.load_order_file_name),
+            load_order_format: args.load_order_format,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<SqlValidateArgs> for PatchArgs {
+    fn from(args: SqlValidateArgs) -> Self {
+        Self {
+            verbose: args.verbose,
+            offline: args.offline,
+            skip_schema_update: args.skip_schema_update,
+            schema_rev: args.schema_rev,
+            schema_path: args.schema_path,
+            schema_repo: args.schema_repo,
+            proxy: args.proxy,
+            no_wait: args.no_wait,
+            game: args.game,
+            game_path: args.game_path,
+            data_path: args.data_path,
+            load_order_file_name: args.load_order_file_name,
+            load_order_format: args.load_order_format,
+            ..Default::default()
+        }
+    }
+}
+
+impl PatchArgs {
+
+    /// This function builds the patch arguments from a list of options, in the same format as the manifests.
+    ///
+    /// Like with the CLI, the options are merged with the ones in the config file.
+    pub fn from_options(options: &Map<String, Value>) -> Result<Self> {
+        let options_args = options_args(options)?.into_iter().map(OsString::from).collect::<Vec<_>>();
+        let args = [OsString::from(env!("CARGO_PKG_NAME")), OsString::from("patch")].into_iter()
+            .chain(Self::merge_args(options_args)?)
+            .collect::<Vec<_>>();
+
+        match Cli::try_parse_from(args)?.command {
+            Commands::Patch(args) => Ok(args),
+            _ => Err(anyhow!("The options provided are not valid patch options.")),
+        }
+    }
+
+    /// This function merges the arguments passed to the patch command with the ones in the config file, the profile and the manifest, if any.
+    ///
+    /// The arguments are merged in this order: config file, profile, manifest, CLI. As later arguments override earlier ones, the CLI ones take priority.
+    ///
+    /// Flags enabled in the config file, the profile or the manifest can be disabled in the CLI with `--no-<flag>`.
+    fn merge_args(cli_args: Vec<OsString>) -> Result<Vec<OsString>> {
+        let manifest_args = match arg_value(&cli_args, &["--manifest"]) {
+            Some(path) => manifest_args(Path::new(&path))?.into_iter().map(OsString::from).collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        // The config file and the profiles depend on the game, so we need to find it first.
+        let manifest_and_cli_args = manifest_args.iter().chain(cli_args.iter()).cloned().collect::<Vec<_>>();
+        let game = arg_value(&manifest_and_cli_args, &["-g", "--game"]).map(|game| game.to_string_lossy().to_string());
+
+        let config_args = if !cli_args.iter().any(|arg| arg == "--no-config") {
+            config_args(game.as_deref())?.into_iter().map(OsString::from).collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+
+        let profile_args = match arg_value(&manifest_and_cli_args, &["--profile"]) {
+            Some(name) => {
+                let game = game.as_deref().ok_or_else(|| anyhow!("Profiles are per game, so you need to provide a game to load a profile."))?;
+                manifest_args(&profile_path(game, &name.to_string_lossy())?)?.into_iter().map(OsString::from).collect::<Vec<_>>()
+            }
+            None => vec![],
+        };
+
+        Ok(merge_arg_lists(config_args, profile_args, manifest_args, cli_args))
+    }
+
+    /// This function saves the current options as a profile for the current game.
+    ///
+    /// Profiles use the same format as the manifests.
+    pub fn save_profile(&self, name: &str) -> Result<()> {
+        let path = profile_path(&self.game, name)?;
+        if let Some(parent) = path.parent() {
+            DirBuilder::new().recursive(true).create(parent)?;
+        }
+
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// This function returns the path of a profile for the provided game.
+fn profile_path(game: &str, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        return Err(anyhow!("Invalid profile name: {}. Profile names cannot be empty or contain '/', '\\' or '.'.", name));
+    }
+
+    Ok(config_path()?.join(PROFILES_FOLDER).join(game).join(format!("{}.json", name)))
+}
+
+/// This function merges the arguments of each source, from lower to higher priority, applying the `--no-<flag>` arguments of the CLI.
+fn merge_arg_lists(config_args: Vec<OsString>, profile_args: Vec<OsString>, manifest_args: Vec<OsString>, cli_args: Vec<OsString>) -> Vec<OsString> {
+    let args = config_args.into_iter()
+        .chain(profile_args)
+        .chain(manifest_args)
+        .collect::<Vec<_>>();
+
+    disable_flags(args, cli_args)
+}
+
+/// This function applies the `--no-<flag>` arguments of the CLI, removing the flags they disable from the previous arguments.
+///
+/// These arguments are not known by clap, so they're removed too. Flags which are already named `--no-<something>` are left as they are.
+fn disable_flags(mut args: Vec<OsString>, cli_args: Vec<OsString>) -> Vec<OsString> {
+    let command = PatchArgs::augment_args(Command::new("patch"));
+    let flags = command.get_arguments()
+        .filter(|arg| matches!(arg.get_action(), ArgAction::SetTrue))
+        .filter_map(|arg| arg.get_long())
+        .collect::<Vec<_>>();
+
+    for arg in cli_args {
+        let disabled_flag = arg.to_str()
+            .and_then(|arg| arg.strip_prefix("--no-"))
+            .filter(|flag| flags.contains(flag) && !flags.contains(&&*format!("no-{}", flag)))
+            .map(|flag| format!("--{}", flag));
+
+        match disabled_flag {
+            Some(flag) => args.retain(|arg| *arg != *flag),
+            None => args.push(arg),
+        }
+    }
+
+    args
+}
+
+/// This function returns the value of the last instance of an argument from a list of raw arguments, if found.
+fn arg_value(args: &[OsString], names: &[&str]) -> Option<OsString> {
+    let mut value = None;
+    for (index, arg) in args.iter().enumerate() {
+        let arg_str = arg.to_string_lossy();
+        for name in names {
+            if arg_str == *name {
+                value = args.get(index + 1).cloned();
+            } else if let Some(arg_value) = arg_str.strip_prefix(&format!("{}=", name)) {
+                value = Some(OsString::from(arg_value));
+            }
+        }
+    }
+
+    value
+}
+
+/// This function turns a manifest into a list of CLI arguments.
+///
+/// The manifest is a JSON object with the long name of each option as key. Flags use booleans, options with multiple values use arrays,
+/// and SQL scripts can use objects with a `path` and a `params` list.
+fn manifest_args(path: &Path) -> Result<Vec<String>> {
+    let data = read_to_string(path).map_err(|error| anyhow!("Error reading manifest {}: {}", path.to_string_lossy(), error))?;
+    let manifest: Value = serde_json::from_str(&data).map_err(|error| anyhow!("Error parsing manifest {}: {}", path.to_string_lossy(), error))?;
+    let manifest = manifest.as_object().ok_or_else(|| anyhow!("Error parsing manifest {}: it's not a JSON object.", path.to_string_lossy()))?;
+
+    options_args(manifest)
+}
+
+/// This function turns the config file into a list of CLI arguments.
+///
+/// The config file is a TOML file with a `default` table with the options for all games, and a table per game with the options for that game.
+/// Both use the same format as the manifests.
+fn config_args(game: Option<&str>) -> Result<Vec<String>> {
+    let path = config_path()?.join(CONFIG_FILE_NAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    let data = read_to_string(&path).map_err(|error| anyhow!("Error reading config file {}: {}", path.to_string_lossy(), error))?;
+    let config: toml::Table = toml::from_str(&data).map_err(|error| anyhow!("Error parsing config file {}: {}", path.to_string_lossy(), error))?;
+
+    let mut args = vec![];
+    for section in ["default"].into_iter().chain(game) {
+        if let Some(options) = config.get(section) {
+            match serde_json::to_value(options)? {
+                Value::Object(options) => args.append(&mut options_args(&options)?),
+                _ => return Err(anyhow!("Error parsing config file {}: {} is not a table.", path.to_string_lossy(), section)),
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// This function turns a list of options into a list of CLI arguments.
+fn options_args(options: &Map<String, Value>) -> Result<Vec<String>> {
+    let mut args = vec![];
+    for (key, value) in options {
+
+        // Manifests cannot chain other manifests or profiles, and the config cannot disable itself.
+        if ["manifest", "no_config", "profile", "save_profile"].contains(&&*key.replace('-', "_")) {
+            continue;
+        }
+
+        // Verbosity is a counter, so it needs to be repeated instead of having a value.
+        if key == "verbose" {
+            if let Some(level) = value.as_u64() {
+                args.extend((0..level).map(|_| "--verbose".to_owned()));
+                continue;
+            }
+        }
+
+        // Alias for launchers, which usually think about this as a setting, not a flag.
+        if key.replace('-', "_") == "auto_install_updates" {
+            if let Value::Bool(false) = value {
+                args.push("--check-updates-only".to_owned());
+            }
+
+            continue;
+        }
+
+        let arg = format!("--{}", key.replace('_', "-"));
+        match value {
+            Value::Null | Value::Bool(false) => {},
+            Value::Bool(true) => args.push(arg),
+            Value::Array(values) => {
+                for value in values {
+                    args.push(format!("{}={}", arg, option_value(key, value)?));
+                }
+            }
+            _ => args.push(format!("{}={}", arg, option_value(key, value)?)),
+        }
+    }
+
+    Ok(args)
+}
+
+/// This function turns a single option value into its CLI representation.
+fn option_value(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(value.to_owned()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Object(object) => match object.get("path").or_else(|| object.get("name")).and_then(|path| path.as_str()) {
+            Some(path) => {
+                let mut values = vec![path.to_owned()];
+                if let Some(params) = object.get("params").and_then(|params| params.as_array()) {
+                    for param in params {
+                        values.push(option_value(key, param)?);
+                    }
+                }
+
+                Ok(values.join(";"))
+            }
+            None => Err(anyhow!("Invalid value for {}: objects need a \"path\" or a \"name\".", key)),
+        }
+        _ => Err(anyhow!("Invalid value for {}: {}.", key, value)),
+    }
+}
+
+/// This function serializes the SQL scripts in the format used by the manifests.
+fn sql_script_serializer<S: Serializer>(scripts: &Option<Vec<(PathBuf, Vec<String>)>>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match scripts {
+        Some(scripts) => serializer.collect_seq(scripts.iter().map(|(path, params)| json!({
+            "path": path.to_string_lossy(),
+            "params": params,
+        }))),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// This function serializes the SQL presets in the format used by the manifests.
+fn sql_preset_serializer<S: Serializer>(presets: &Option<Vec<(String, Vec<String>)>>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match presets {
+        Some(presets) => serializer.collect_seq(presets.iter().map(|(name, params)| json!({
+            "name": name,
+            "params": params,
+        }))),
+        None => serializer.serialize_none(),
+    }
+}
+
+//---------------------------------------------------------------------------//
+//                          Custom parsers
+//---------------------------------------------------------------------------//
+
+fn feature_scope_parser(src: &str) -> Result<FeatureScope> {
+    let (feature, scope) = src.split_once('=').ok_or_else(|| anyhow!("Invalid feature scope {}. The format is FEATURE=only:PACKS or FEATURE=except:PACKS.", src))?;
+    let feature = feature.trim().replace('-', "_");
+    if !SCOPABLE_FEATURES.contains(&feature.as_str()) {
+        return Err(anyhow!("Feature {} cannot be scoped. Valid features are: {}.", feature, SCOPABLE_FEATURES.join(", ")));
+    }
+
+    let (only, patterns) = match scope.split_once(':') {
+        Some(("only", patterns)) => (true, patterns),
+        Some(("except", patterns)) => (false, patterns),
+        _ => return Err(anyhow!("Invalid feature scope {}. The scope has to start with only: or except:.", src)),
+    };
+
+    let patterns = patterns.split(',')
+        .map(|pattern| pattern.trim().to_owned())
+        .filter(|pattern| !pattern.is_empty())
+        .collect::<Vec<_>>();
+
+    if patterns.is_empty() {
+        return Err(anyhow!("Invalid feature scope {}. No Packs provided.", src));
+    }
+
+    Ok(FeatureScope {
+        feature,
+        only,
+        patterns,
+    })
+}
+
+fn sql_script_parser(src: &str) -> Result<(PathBuf, Vec<String>)> {
+    let (path, params) = sql_params_parser(src)?;
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(anyhow!("Path {} doesn't belong to a valid file.", path.display()));
+    }
+
+    Ok((path, params))
+}
+
+fn multiplier_parser(src: &str) -> Result<f64> {
+    let multiplier = src.trim().parse::<f64>().map_err(|_| anyhow!("Invalid multiplier {}. It has to be a number.", src))?;
+    if !multiplier.is_finite() || multiplier <= 0.0 {
+        return Err(anyhow!("Invalid multiplier {}. It has to be greater than 0.", src));
+    }
+
+    Ok(multiplier)
+}
+
+fn garrison_multiplier_parser(src: &str) -> Result<f64> {
+    let multiplier = multiplier_parser(src)?;
+    if multiplier < 1.0 {
+        return Err(anyhow!("Invalid multiplier {}. It has to be 1 or greater.", src));
+    }
+
+    Ok(multiplier)
+}
+
+fn log_filter_parser(src: &str) -> Result<String> {
+    parse_log_filter(src)?;
+    Ok(src.to_owned())
+}
+
+fn sql_attach_parser(src: &str) -> Result<SqlAttach> {
+    let (alias, path) = src.split_once('=').ok_or_else(|| anyhow!("Invalid database {}. The format is ALIAS=DB_PATH.", src))?;
+    let alias = alias.trim().to_owned();
+    if alias.is_empty() || !alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') || alias.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(anyhow!("Invalid alias {}. Aliases can only contain letters, numbers and underscores, and cannot start with a number.", alias));
+    }
+
+    if ["main", "temp"].contains(&alias.to_lowercase().as_str()) {
+        return Err(anyhow!("Invalid alias {}. main and temp are reserved by SQLite.", alias));
+    }
+
+    let path = PathBuf::from(path.trim());
+    if !path.is_file() {
+        return Err(anyhow!("Path {} doesn't belong to a valid file.", path.display()));
+    }
+
+    Ok(SqlAttach {
+        alias,
+        path,
+    })
+}
+
+fn sql_preset_parser(src: &str) -> Result<(String, Vec<String>)> {
+    sql_params_parser(src)
+}
+
+/// This function splits a string with a value followed by its params, everything separated with ;.
+fn sql_params_parser(src: &str) -> Result<(String, Vec<String>)> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b';')
+        .quoting(true)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(src.as_bytes());
+
+    if let Some(Ok(record)) = reader.records().next() {
+        if record.is_empty() {
+            return Err(anyhow!("Incorrect CSV input."));
+        } else {
+            let params = if record.len() >= 2 {
+                (1..record.len()).map(|x| record[x].to_owned()).collect::<Vec<_>>()
+            } else {
+                vec![]
+            };
+
+            return Ok((record[0].to_owned(), params));
+        }
+    }
+
+    Err(anyhow!("Incorrect CSV input."))
+}
+
+//---------------------------------------------------------------------------//
+//                                  Tests
+//---------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn parse_patch_args(args: Vec<OsString>) -> PatchArgs {
+        let args = [OsString::from(env!("CARGO_PKG_NAME")), OsString::from("patch")].into_iter()
+            .chain(args)
+            .collect::<Vec<_>>();
+
+        match Cli::try_parse_from(args).unwrap().command {
+            Commands::Patch(args) => args,
+            _ => panic!("Not a patch command."),
+        }
+    }
+
+    #[test]
+    fn merge_precedence() {
+        let config = os_args(&["--unit-multiplier=2"]);
+        let profile = os_args(&["--unit-multiplier=3"]);
+        let manifest = os_args(&["--unit-multiplier=4"]);
+        let cli = os_args(&["-g", "warhammer_3", "--mods", "a.pack", "--unit-multiplier=5"]);
+
+        let args = parse_patch_args(merge_arg_lists(config.clone(), profile.clone(), manifest.clone(), cli));
+        assert_eq!(args.unit_multiplier, Some(5.0));
+
+        let cli = os_args(&["-g", "warhammer_3", "--mods", "a.pack"]);
+        let args = parse_patch_args(merge_arg_lists(config.clone(), profile.clone(), manifest, cli.clone()));
+        assert_eq!(args.unit_multiplier, Some(4.0));
+
+        let args = parse_patch_args(merge_arg_lists(config.clone(), profile, vec![], cli.clone()));
+        assert_eq!(args.unit_multiplier, Some(3.0));
+
+        let args = parse_patch_args(merge_arg_lists(config, vec![], vec![], cli));
+        assert_eq!(args.unit_multiplier, Some(2.0));
+    }
+
+    #[test]
+    fn disable_flags_removes_flags() {
+        let args = os_args(&["--skip-intro-videos", "--enable-logging"]);
+        let cli = os_args(&["-g", "warhammer_3", "--mods", "a.pack", "--no-skip-intro-videos"]);
+
+        let merged = disable_flags(args, cli);
+        assert!(!merged.contains(&OsString::from("--skip-intro-videos")));
+        assert!(!merged.contains(&OsString::from("--no-skip-intro-videos")));
+
+        let args = parse_patch_args(merged);
+        assert!(!args.skip_intro_videos);
+        assert!(args.enable_logging);
+    }
+
+    #[test]
+    fn disable_flags_keeps_no_flags() {
+
+        // --no-movie-scan is a flag by itself, so it has to reach clap as it is.
+        let cli = os_args(&["-g", "warhammer_3", "--mods", "a.pack", "--no-movie-scan"]);
+        let args = parse_patch_args(disable_flags(vec![], cli));
+        assert!(args.no_movie_scan);
+    }
+
+    #[test]
+    fn arg_value_last_wins() {
+        let args = os_args(&["-g", "warhammer_2", "--game=warhammer_3", "--profile", "test"]);
+        assert_eq!(arg_value(&args, &["-g", "--game"]), Some(OsString::from("warhammer_3")));
+        assert_eq!(arg_value(&args, &["--profile"]), Some(OsString::from("test")));
+        assert_eq!(arg_value(&args, &["--manifest"]), None);
+    }
+
+    #[test]
+    fn options_verbose_count() {
+        let options = json!({ "verbose": 2 });
+        let args = options_args(options.as_object().unwrap()).unwrap();
+        assert_eq!(args, vec!["--verbose", "--verbose"]);
+
+        let args = parse_patch_args(os_args(&["-g", "warhammer_3", "--mods", "a.pack"]).into_iter().chain(args.into_iter().map(OsString::from)).collect());
+        assert_eq!(args.verbose, 2);
+    }
+
+    #[test]
+    fn options_flags_and_values() {
+        let options = json!({
+            "skip_intro_videos": true,
+            "enable_logging": false,
+            "unit_multiplier": 1.5,
+            "mods": ["a.pack", "b.pack"],
+            "profile": "ignored",
+            "auto_install_updates": false,
+        });
+
+        let args = options_args(options.as_object().unwrap()).unwrap();
+        assert!(args.contains(&"--skip-intro-videos".to_owned()));
+        assert!(!args.contains(&"--enable-logging".to_owned()));
+        assert!(args.contains(&"--unit-multiplier=1.5".to_owned()));
+        assert!(args.contains(&"--mods=a.pack".to_owned()));
+        assert!(args.contains(&"--mods=b.pack".to_owned()));
+        assert!(args.contains(&"--check-updates-only".to_owned()));
+        assert!(!args.iter().any(|arg| arg.starts_with("--profile")));
+    }
+
+    #[test]
+    fn option_value_sql_script_object() {
+        let value = json!({ "path": "scripts/test.sql", "params": ["a", 2] });
+        assert_eq!(option_value("sql_script", &value).unwrap(), "scripts/test.sql;a;2");
+
+        let value = json!({ "name": "preset" });
+        assert_eq!(option_value("sql_preset", &value).unwrap(), "preset");
+
+        let value = json!({ "params": ["a"] });
+        assert!(option_value("sql_script", &value).is_err());
+
+        let options = json!({ "sql_script": [{ "path": "a.sql", "params": ["1"] }, { "path": "b.sql" }] });
+        let args = options_args(options.as_object().unwrap()).unwrap();
+        assert_eq!(args, vec!["--sql-script=a.sql;1", "--sql-script=b.sql"]);
+    }
+
+    #[test]
+    fn feature_scope() {
+        let scope = feature_scope_parser("unit-multiplier=only:my_mod*.pack, other.pack").unwrap();
+        assert_eq!(scope.feature, "unit_multiplier");
+        assert!(scope.only);
+        assert_eq!(scope.patterns, vec!["my_mod*.pack", "other.pack"]);
+        assert!(scope.contains("my_mod_units.pack"));
+        assert!(scope.contains("other.pack"));
+        assert!(!scope.contains("another.pack"));
+
+        let scope = feature_scope_parser("sql_script=except:broken.pack").unwrap();
+        assert!(!scope.only);
+        assert!(!scope.contains("broken.pack"));
+        assert!(scope.contains("working.pack"));
+    }
+
+    #[test]
+    fn feature_scope_invalid() {
+        assert!(feature_scope_parser("unit_multiplier").is_err());
+        assert!(feature_scope_parser("not_a_feature=only:a.pack").is_err());
+        assert!(feature_scope_parser("unit_multiplier=some:a.pack").is_err());
+        assert!(feature_scope_parser("unit_multiplier=only: , ").is_err());
+    }
 }
@@ -0,0 +1,173 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the report of conflicts between the mods of the load order.
+//!
+//! A conflict is either a file present in more than one Pack, or a row with the same key in tables of the same type from different Packs.
+//! In both cases, the first Pack in the load order (and for tables, the first table by name within it) is the one the game uses.
+
+use anyhow::Result;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rpfm_lib::files::{Container, DecodeableExtraData, FileType, pack::Pack, RFileDecoded};
+use rpfm_lib::games::GameInfo;
+use rpfm_lib::integrations::log::info;
+use rpfm_lib::schema::Schema;
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Report of the conflicts between the Packs of a load order.
+#[derive(Clone, Debug, Default)]
+pub struct ConflictsReport {
+
+    /// Path of the file -> Packs containing it, sorted by priority. Only files in more than one Pack are kept.
+    files: BTreeMap<String, Vec<String>>,
+
+    /// Table folder -> key of the row -> Packs containing it, sorted by priority. Only keys in more than one Pack are kept.
+    keys: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl ConflictsReport {
+
+    /// This function finds the conflicts between the Packs of the provided load order.
+    pub fn new(load_order: &[PathBuf], game: &GameInfo, schema: &Schema) -> Result<Self> {
+        let mut dec_extra_data = DecodeableExtraData::default();
+        dec_extra_data.set_schema(Some(schema));
+        let dec_extra_data = Some(dec_extra_data);
+
+        let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut keys: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+
+        for pack_path in load_order {
+            let pack_name = pack_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let mut pack = Pack::read_and_merge(&[pack_path.to_path_buf()], game, true, false, false)?;
+
+            let mut paths = pack.files().keys().cloned().collect::<Vec<_>>();
+            paths.sort();
+
+            for path in paths {
+                let packs = files.entry(path.to_owned()).or_default();
+                if !packs.contains(&pack_name) {
+                    packs.push(pack_name.to_owned());
+                }
+
+                if let Some(file) = pack.file_mut(&path, false) {
+                    if file.file_type() != FileType::DB {
+                        continue;
+                    }
+
+                    if let Ok(Some(RFileDecoded::DB(data))) = file.decode(&dec_extra_data, false, true) {
+                        let key_columns = data.definition().fields_processed()
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, field)| field.is_key(Some(data.patches())))
+                            .map(|(column, _)| column)
+                            .collect::<Vec<_>>();
+
+                        if key_columns.is_empty() {
+                            continue;
+                        }
+
+                        let table_folder = match path.rsplit_once('/') {
+                            Some((folder, _)) => folder.to_owned(),
+                            None => continue,
+                        };
+
+                        let table_keys = keys.entry(table_folder).or_default();
+                        for row in data.data().iter() {
+                            let key = key_columns.iter()
+                                .filter_map(|column| row.get(*column))
+                                .map(|cell| cell.data_to_string().to_string())
+                                .collect::<Vec<_>>()
+                                .join("|");
+
+                            let packs = table_keys.entry(key).or_default();
+                            if !packs.contains(&pack_name) {
+                                packs.push(pack_name.to_owned());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        files.retain(|_, packs| packs.len() > 1);
+        keys.values_mut().for_each(|table_keys| table_keys.retain(|_, packs| packs.len() > 1));
+        keys.retain(|_, table_keys| !table_keys.is_empty());
+
+        info!("Conflicts found: {} files and {} table keys.", files.len(), keys.values().map(|table_keys| table_keys.len()).sum::<usize>());
+
+        Ok(Self {
+            files,
+            keys,
+        })
+    }
+
+    /// This function saves the report to disk. If the path has an html extension, the report is saved as HTML. Otherwise, it's saved as Markdown.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let is_html = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm"));
+        let contents = if is_html { self.to_html() } else { self.to_markdown() };
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut contents = String::from("# Conflicts Report\n\n## Files\n\n| File | Winner | Overwritten |\n|---|---|---|\n");
+        for (path, packs) in &self.files {
+            contents.push_str(&format!("| {} | {} | {} |\n", path, packs[0], packs[1..].join(", ")));
+        }
+
+        contents.push_str("\n## Table Keys\n");
+        for (table_folder, table_keys) in &self.keys {
+            contents.push_str(&format!("\n### {}\n\n| Key | Winner | Overwritten |\n|---|---|---|\n", table_folder));
+            for (key, packs) in table_keys {
+                contents.push_str(&format!("| {} | {} | {} |\n", key.replace('|', "\\|"), packs[0], packs[1..].join(", ")));
+            }
+        }
+
+        contents
+    }
+
+    fn to_html(&self) -> String {
+        let mut contents = String::from("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Conflicts Report</title></head>\n<body>\n<h1>Conflicts Report</h1>\n<h2>Files</h2>\n");
+        contents.push_str("<table>\n<tr><th>File</th><th>Winner</th><th>Overwritten</th></tr>\n");
+        for (path, packs) in &self.files {
+            contents.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n", escape_html(path), escape_html(&packs[0]), escape_html(&packs[1..].join(", "))));
+        }
+        contents.push_str("</table>\n<h2>Table Keys</h2>\n");
+
+        for (table_folder, table_keys) in &self.keys {
+            contents.push_str(&format!("<h3>{}</h3>\n<table>\n<tr><th>Key</th><th>Winner</th><th>Overwritten</th></tr>\n", escape_html(table_folder)));
+            for (key, packs) in table_keys {
+                contents.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n", escape_html(key), escape_html(&packs[0]), escape_html(&packs[1..].join(", "))));
+            }
+            contents.push_str("</table>\n");
+        }
+
+        contents.push_str("</body>\n</html>\n");
+        contents
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -10,19 +10,53 @@
 
 use anyhow::Result;
 
+use rpfm_lib::schema::Schema;
 use rpfm_lib::files::{FileType, pack::Pack, RFile};
+use rpfm_lib::games::GameInfo;
 
-use super::EMPTY_CA_VP8;
+use super::{EMPTY_CA_VP8, inject_script_logging_wrapper, patch_kv_values, patch_load_order_tables, patch_numeric_columns};
+
+// Folders where the campaign entry scripts are.
+const SCRIPT_ENTRY_FOLDERS: [&str; 1] = [
+    "script/campaign/",
+];
 
 const INTRO_MOVIE_PATHS_BY_GAME: [&str; 2] = [
     "movies/intro.ca_vp8",
     "movies/sega_logo_sting_hd.ca_vp8",
 ];
 
+// Tables and columns defining how much damage attrition does to armies.
+const ATTRITION_DAMAGE_COLUMNS: [(&str, &str); 1] = [
+    ("db/campaign_attrition_types_tables/", "damage"),
+];
+
+// Keys in _kv_rules with the attrition damage applied by winter and climate.
+const ATTRITION_DAMAGE_KEYS: [&str; 2] = [
+    "winter_attrition_damage",
+    "climate_attrition_damage",
+];
+
 //-------------------------------------------------------------------------------//
 //                             Implementations
 //-------------------------------------------------------------------------------//
 
+pub fn prepare_attrition_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    for (table_folder, column) in ATTRITION_DAMAGE_COLUMNS {
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, table_folder, |data| {
+            patch_numeric_columns(data, &[column], |_| 0.0);
+        })?;
+    }
+
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/_kv_rules_tables/", |data| {
+        patch_kv_values(data, &ATTRITION_DAMAGE_KEYS, |value| *value = 0.0);
+    })
+}
+
+pub fn prepare_script_logging(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack) -> Result<()> {
+    inject_script_logging_wrapper(game, reserved_pack, vanilla_pack, modded_pack, &SCRIPT_ENTRY_FOLDERS)
+}
+
 pub fn prepare_skip_intro_videos(reserved_pack: &mut Pack) -> Result<()> {
     for path in INTRO_MOVIE_PATHS_BY_GAME {
         let file = RFile::new_from_vec(&EMPTY_CA_VP8, FileType::Video, 0, path);
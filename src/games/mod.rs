@@ -9,14 +9,22 @@
 //---------------------------------------------------------------------------//
 
 use anyhow::{anyhow, Result};
+use csv::WriterBuilder;
 
+use git2::Repository;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OpenFlags, params, types::ValueRef};
 use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 
-use std::collections::{HashMap, HashSet};
-use std::fs::DirBuilder;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{DirBuilder, read_to_string, remove_file};
+use std::io::{IsTerminal, stdin, stdout, Write};
 use std::path::{PathBuf, Path};
+use std::time::Instant;
 
 use common_utils::sql::SQLScript;
 
@@ -24,12 +32,18 @@ use rpfm_extensions::dependencies::Dependencies;
 use rpfm_extensions::optimizer::{Optimizable, OptimizerOptions};
 use rpfm_extensions::translator::*;
 
-use rpfm_lib::files::{Container, ContainerPath, DecodeableExtraData, EncodeableExtraData, FileType, loc::Loc, pack::Pack, RFile, RFileDecoded, table::DecodedData};
+use rpfm_lib::files::{Container, ContainerPath, db::DB, DecodeableExtraData, EncodeableExtraData, FileType, loc::Loc, pack::Pack, RFile, RFileDecoded, table::DecodedData};
 use rpfm_lib::games::{*, supported_games::*};
-use rpfm_lib::integrations::{git::GitIntegration, log::{error, info, warn}};
+use rpfm_lib::integrations::log::{debug, error, info, warn};
 use rpfm_lib::schema::Schema;
-
-use crate::app::Cli;
+use rpfm_lib::utils::files_from_subdir;
+
+use crate::app::{PatchArgs, UnitMultiplierRounding};
+use crate::logging::set_log_section;
+use crate::progress::Progress;
+use crate::machine_translation::MachineTranslator;
+use crate::patches::{lua::apply_lua_patch, TablePatchFile};
+use crate::report::{Report, TranslationReport, VanillaLocDiff};
 use crate::utils::*;
 
 const EMPTY_CA_VP8: [u8; 595] = [
@@ -105,13 +119,184 @@ pub const TRANSLATIONS_REPO: &str = "https://github.com/Frodo45127/total_war_tra
 pub const TRANSLATIONS_REMOTE: &str = "origin";
 pub const TRANSLATIONS_BRANCH: &str = "master";
 
+pub const SQL_PRESETS_REPO: &str = "https://github.com/Frodo45127/twpatcher_sql_presets";
+pub const SQL_PRESETS_REMOTE: &str = "origin";
+pub const SQL_PRESETS_BRANCH: &str = "master";
+
 pub const VANILLA_LOC_NAME: &str = "vanilla_english.tsv";
 pub const VANILLA_FIXES_NAME: &str = "vanilla_fixes_";
 
+// Languages whose texts cannot be shown with the default fonts of the game.
+const CJK_LANGUAGES: [&str; 4] = ["cn", "jp", "kr", "zh"];
+const FONTS_PATH: &str = "ui/fonts/";
+
+// Language codes used by the game, except english. Used to detect translated locs shipped within mods.
+const LOC_LANGUAGES: [&str; 13] = ["br", "cn", "cz", "de", "fr", "it", "jp", "kr", "pl", "ru", "sp", "tr", "zh"];
+
+// Lua wrapper prepended to the campaign scripts of games without a console logging switch.
+// It redirects everything passed to `out()` and any error thrown when loading a script to a log file in the game folder.
+const SCRIPT_LOGGING_WRAPPER_MARKER: &str = "-- TWPatcher script logging wrapper.";
+const SCRIPT_LOGGING_WRAPPER: &str = r#"-- TWPatcher script logging wrapper.
+local twpatcher_log_file = io.open("twpatcher_script_log.txt", "a");
+local twpatcher_out = out;
+local twpatcher_require = require;
+
+out = function(text)
+    if twpatcher_log_file then
+        twpatcher_log_file:write(tostring(text) .. "\n");
+        twpatcher_log_file:flush();
+    end
+
+    if twpatcher_out then
+        twpatcher_out(text);
+    end
+end
+
+require = function(name)
+    local ok, result = pcall(twpatcher_require, name);
+    if not ok then
+        out("SCRIPT ERROR loading " .. tostring(name) .. ": " .. tostring(result));
+        error(result);
+    end
+
+    return result;
+end
+-- End of TWPatcher script logging wrapper.
+
+"#;
+
+// Tables and columns limiting how many units of a type can be recruited in Troy and Pharaoh. -1 means no limit.
+const UNIT_CAP_COLUMNS: [(&str, &str); 1] = [
+    ("db/main_units_tables/", "campaign_cap"),
+];
+
+// Castes of the units that keep the Siege Attacker attribute when removing it.
+const SIEGE_CASTES: [&str; 1] = [
+    "warmachine",
+];
+
+// Same as above, for Troy, where siege units are artillery.
+const SIEGE_CASTES_TROY: [&str; 2] = [
+    "warmachine",
+    "artillery",
+];
+
+// Keys in _kv_rules controlling how long a battle can last before it ends, in the games since Warhammer.
+const BATTLE_TIMER_KEYS_WARHAMMER: [&str; 2] = [
+    "battle_time_limit",
+    "siege_battle_time_limit",
+];
+
+// Keys in _kv_rules controlling how long a battle can last before it ends, in Rome 2, Attila and Thrones of Britannia.
+const BATTLE_TIMER_KEYS_ROME_2: [&str; 1] = [
+    "battle_time_limit",
+];
+
+// Columns of the faction tables with the faction, and marking it as selectable in campaign.
+const FACTION_COLUMN: &str = "faction";
+const PLAYABLE_FACTION_COLUMN: &str = "is_playable";
+
+// Effect bundles the game applies to each army after the first one, increasing the upkeep of all of them.
+const SUPPLY_LINES_EFFECT_BUNDLES: [&str; 1] = [
+    "wh_main_bundle_force_additional_army_upkeep",
+];
+
+// Keys in _kv_winds_of_magic_params with amounts of winds of magic in battle: the reserves and how much each recharge gives.
+const WINDS_OF_MAGIC_AMOUNT_KEYS: [&str; 4] = [
+    "starting_reserve_power",
+    "max_reserve_power",
+    "max_active_power",
+    "recharge_amount",
+];
+
+// Keys in _kv_winds_of_magic_params with the time between recharges of the winds of magic in battle.
+const WINDS_OF_MAGIC_INTERVAL_KEYS: [&str; 1] = [
+    "recharge_interval",
+];
+
+// Keys in campaign_variables controlling the chance of characters dying (instead of being wounded) after battles or agent actions.
+const CHARACTER_DEATH_KEYS: [&str; 2] = [
+    "character_death_chance_after_battle",
+    "agent_death_chance_after_action",
+];
+
+// Table with the limits of the battle camera.
+const BATTLE_CAMERA_TABLE: &str = "db/battle_camera_settings_tables/";
+
+// Columns of the battle camera settings with the height (in meters) and pitch (in degrees) limits of the battle camera,
+// and the values we set them to, wide enough for cinematic shots. Warhammer 2, Warhammer 3 and Three Kingdoms.
+const BATTLE_CAMERA_LIMITS_WARHAMMER: [(&str, f64); 4] = [
+    ("min_camera_height", 1.0),
+    ("max_camera_height", 500.0),
+    ("min_camera_pitch", -89.0),
+    ("max_camera_pitch", 89.0),
+];
+
+// Same as above, for Attila.
+const BATTLE_CAMERA_LIMITS_ATTILA: [(&str, f64); 4] = [
+    ("min_height", 1.0),
+    ("max_height", 500.0),
+    ("min_pitch", -89.0),
+    ("max_pitch", 89.0),
+];
+
+// Table with the handicaps of each campaign difficulty, including the money each faction starts the campaign with.
+const STARTING_TREASURY_TABLE: &str = "db/campaign_difficulty_handicaps_tables/";
+
+// Column of the campaign difficulty handicaps with the starting money, in the games since Warhammer and in Rome 2, Attila and Thrones of Britannia.
+const STARTING_TREASURY_COLUMN_WARHAMMER: &str = "starting_treasury";
+const STARTING_TREASURY_COLUMN_ROME_2: &str = "treasury";
+
+// Effects buildings use to add money to a faction, in the different naming conventions used across games.
+const INCOME_EFFECTS: [&str; 12] = [
+
+    // Rome 2, Attila and Thrones of Britannia.
+    "rom_building_gdp_agriculture_farming",
+    "rom_building_gdp_agriculture_herding",
+    "rom_building_gdp_commerce_local",
+    "rom_building_gdp_commerce_trade",
+    "rom_building_gdp_culture",
+    "rom_building_gdp_industry",
+
+    // Warhammer, Warhammer 2 and Warhammer 3.
+    "wh_main_effect_economy_gdp_agriculture_farming",
+    "wh_main_effect_economy_gdp_agriculture_herding",
+    "wh_main_effect_economy_gdp_industry",
+    "wh_main_effect_economy_gdp_mining_gold",
+    "wh_main_effect_economy_gdp_mod_all",
+    "wh_main_effect_economy_gdp_trade_local",
+];
+
 const DB_EXTENSION: &str = ".db3";
 const DB_BAK_EXTENSION: &str = ".bak";
+const DB_MERGED_EXTENSION: &str = ".merged";
 const DB_FOLDER: &str = "dbs";
 
+// Pragmas to speed up the import of the tables into the database. Both databases can be rebuilt from the game files, so we trade crash safety for speed.
+const SQL_IMPORT_PRAGMAS: &str = "PRAGMA synchronous = OFF; PRAGMA journal_mode = MEMORY;";
+
+// Tables with the locs of the load order, and the loc files edited by the scripts. New rows without a file go to the default one.
+const SQL_LOCS_TABLE: &str = "locs";
+const SQL_LOCS_CHANGED_TABLE: &str = "locs_changed";
+const SQL_LOCS_DEFAULT_FILE: &str = "text/db/twpatcher_sql.loc";
+
+// Comment key used by the scripts to declare the loc files they create.
+const SQL_LOCS_CREATED_KEY: &str = "locs_created:";
+
+// Comment keys used by the scripts to declare their id, and the ids of the scripts they need to be executed after.
+const SQL_ID_KEY: &str = "id:";
+const SQL_DEPENDS_ON_KEY: &str = "depends_on:";
+
+// Table in the vanilla database with the checksums of the vanilla tables imported into it.
+const SQL_CHECKSUMS_TABLE: &str = "twpatcher_table_checksums";
+
+// Table with the metadata of the vanilla database, like the schema revision it was built with.
+const SQL_METADATA_TABLE: &str = "twpatcher_metadata";
+const SQL_SCHEMA_REVISION_KEY: &str = "schema_revision";
+
+// Table with the extra garrison entries added by the garrison multiplier.
+const GARRISON_MULTIPLIER_TABLE_NAME: &str = "twpatcher_garrison_multiplier";
+
 mod attila;
 mod empire;
 mod napoleon;
@@ -129,52 +314,361 @@ mod warhammer_3;
 //                             Implementations
 //-------------------------------------------------------------------------------//
 
-pub fn prepare_launch_options(cli: &Cli,
+/// Error returned when any of the SQL scripts fails, so it can be told apart from other errors.
+#[derive(Debug)]
+pub struct SqlScriptsError;
+
+impl Display for SqlScriptsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Something failed when processing the SQL scripts.")
+    }
+}
+
+impl Error for SqlScriptsError {}
+
+/// SQL data kept between patches by the server mode, so the database doesn't need to be rebuilt on each patch.
+#[derive(Default)]
+pub struct SqlCache {
+
+    /// Schema revision and checksums of the vanilla tables the cached vanilla tables were decoded from.
+    vanilla_key: Option<String>,
+
+    /// Vanilla tables, already decoded.
+    vanilla_tables: Vec<RFile>,
+
+    /// Checksum of the vanilla data, the modded tables and the locs in the copy of the database with all of them, before any script runs.
+    merged_key: Option<String>,
+}
+
+/// Unit multiplier to apply, with the rounding and the size cap chosen by the user.
+#[derive(Clone, Debug)]
+pub struct UnitMultiplier {
+    multiplier: f64,
+    rounding: UnitMultiplierRounding,
+    size_cap: Option<i32>,
+    excluded: Vec<String>,
+}
+
+impl UnitMultiplier {
+
+    pub fn new(multiplier: f64, rounding: UnitMultiplierRounding, size_cap: Option<i32>, excluded: Vec<String>) -> Self {
+        Self {
+            multiplier,
+            rounding,
+            size_cap,
+            excluded,
+        }
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    /// This function checks if the provided unit key is excluded from the multiplier.
+    pub fn is_excluded(&self, key: &str) -> bool {
+        !key.is_empty() && self.excluded.iter().any(|pattern| glob_match(pattern, key))
+    }
+
+    /// This function applies the multiplier to a value, rounding it as chosen by the user.
+    pub fn scale(&self, value: i32) -> i32 {
+        let value = value as f64 * self.multiplier;
+        match self.rounding {
+            UnitMultiplierRounding::Floor => value.floor() as i32,
+            UnitMultiplierRounding::Round => value.round() as i32,
+            UnitMultiplierRounding::Ceil => value.ceil() as i32,
+        }
+    }
+
+    /// This function applies the multiplier to the amount of men of a unit, without going over the size cap.
+    ///
+    /// Units that were already over the cap are left as they were, unless the multiplier makes them smaller.
+    pub fn scale_size(&self, size: i32) -> i32 {
+        let scaled = self.scale(size);
+        match self.size_cap {
+            Some(cap) if scaled > cap => cap.max(size.min(scaled)),
+            _ => scaled,
+        }
+    }
+
+    /// This function applies the multiplier to the amount of groups (engines, mounts,...) of a unit with the provided amount of men,
+    /// so the amount of men the unit ends up with doesn't go over the size cap.
+    pub fn scale_groups(&self, groups: i32, size: i32) -> i32 {
+        let scaled = self.scale(groups);
+        match self.size_cap {
+            Some(cap) if groups > 0 && size * scaled / groups > cap => (cap * groups / size).max(groups.min(scaled)),
+            _ => scaled,
+        }
+    }
+}
+
+pub fn prepare_launch_options(cli: &PatchArgs,
     game: &GameInfo,
     reserved_pack: &mut Pack,
     vanilla_pack: &mut Pack,
     modded_pack: &mut Pack,
     schema: &Schema,
+    schema_path: &Path,
     load_order: &[PathBuf],
-    game_path: &Path
+    game_path: &Path,
+    report: &mut Report,
+    sql_cache: Option<&mut SqlCache>,
 ) -> Result<()> {
 
     // Skip videos.
-    prepare_skip_intro_videos(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+    run_feature(cli, game, report, reserved_pack, "Skip Intro Videos", |reserved_pack| with_feature_scope(cli, &["skip_intro_videos"], game, load_order, modded_pack, |modded_pack, _| prepare_skip_intro_videos(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
 
     // Logging.
-    prepare_script_logging(cli, game, reserved_pack)?;
+    run_feature(cli, game, report, reserved_pack, "Enable Script Logging", |reserved_pack| with_feature_scope(cli, &["enable_logging"], game, load_order, modded_pack, |modded_pack, _| prepare_script_logging(cli, game, reserved_pack, vanilla_pack, modded_pack)))?;
 
     // Trait limit removal.
-    prepare_trait_limit_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+    run_feature(cli, game, report, reserved_pack, "Remove Trait Limit", |reserved_pack| with_feature_scope(cli, &["remove_trait_limit"], game, load_order, modded_pack, |modded_pack, _| prepare_trait_limit_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
 
     // Siege Attacker removal.
-    prepare_siege_attacker_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+    run_feature(cli, game, report, reserved_pack, "Remove Siege Attacker", |reserved_pack| with_feature_scope(cli, &["remove_siege_attacker"], game, load_order, modded_pack, |modded_pack, _| prepare_siege_attacker_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Battle timer removal.
+    run_feature(cli, game, report, reserved_pack, "Remove Battle Timer", |reserved_pack| with_feature_scope(cli, &["remove_battle_timer"], game, load_order, modded_pack, |modded_pack, _| prepare_battle_timer_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Unlock all factions.
+    run_feature(cli, game, report, reserved_pack, "Unlock All Factions", |reserved_pack| with_feature_scope(cli, &["unlock_all_factions"], game, load_order, modded_pack, |modded_pack, _| prepare_unlock_all_factions(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Agent caps removal.
+    run_feature(cli, game, report, reserved_pack, "Remove Agent Caps", |reserved_pack| with_feature_scope(cli, &["remove_agent_caps"], game, load_order, modded_pack, |modded_pack, _| prepare_agent_caps_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Unit caps removal.
+    run_feature(cli, game, report, reserved_pack, "Remove Unit Caps", |reserved_pack| with_feature_scope(cli, &["remove_unit_caps"], game, load_order, modded_pack, |modded_pack, _| prepare_unit_caps_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
 
     // Translations.
-    prepare_translations(cli, game, reserved_pack, load_order, game_path)?;
+    run_feature(cli, game, report, reserved_pack, "Translation Fixer", |reserved_pack| with_feature_scope(cli, &["translation_language"], game, load_order, modded_pack, |_, load_order| prepare_translations(cli, game, reserved_pack, vanilla_pack, load_order, game_path)))?;
 
     // Unit multiplier.
-    prepare_unit_multiplier(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+    run_feature(cli, game, report, reserved_pack, "Unit Multiplier", |reserved_pack| with_feature_scope(cli, &["unit_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_unit_multiplier(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Supply lines removal.
+    run_feature(cli, game, report, reserved_pack, "Remove Supply Lines", |reserved_pack| with_feature_scope(cli, &["remove_supply_lines"], game, load_order, modded_pack, |modded_pack, _| prepare_supply_lines_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Attrition removal.
+    run_feature(cli, game, report, reserved_pack, "No Attrition", |reserved_pack| with_feature_scope(cli, &["no_attrition"], game, load_order, modded_pack, |modded_pack, _| prepare_attrition_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Battle camera unlock.
+    run_feature(cli, game, report, reserved_pack, "Unlock Battle Camera", |reserved_pack| with_feature_scope(cli, &["unlock_battle_camera"], game, load_order, modded_pack, |modded_pack, _| prepare_battle_camera_unlock(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Immortal characters.
+    run_feature(cli, game, report, reserved_pack, "Immortal Characters", |reserved_pack| with_feature_scope(cli, &["immortal_characters"], game, load_order, modded_pack, |modded_pack, _| prepare_immortal_characters(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Endgame removal.
+    run_feature(cli, game, report, reserved_pack, "Disable Endgame", |reserved_pack| with_feature_scope(cli, &["disable_endgame"], game, load_order, modded_pack, |modded_pack, _| prepare_endgame_removal(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // AI difficulty normalization.
+    run_feature(cli, game, report, reserved_pack, "Normalize AI Difficulty", |reserved_pack| with_feature_scope(cli, &["normalize_ai_difficulty"], game, load_order, modded_pack, |modded_pack, _| prepare_ai_difficulty_normalization(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Garrison multiplier.
+    run_feature(cli, game, report, reserved_pack, "Garrison Multiplier", |reserved_pack| with_feature_scope(cli, &["garrison_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_garrison_multiplier(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Campaign movement multiplier.
+    run_feature(cli, game, report, reserved_pack, "Campaign Movement Multiplier", |reserved_pack| with_feature_scope(cli, &["campaign_movement_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_campaign_movement_multiplier(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Starting treasury.
+    run_feature(cli, game, report, reserved_pack, "Starting Treasury", |reserved_pack| with_feature_scope(cli, &["starting_treasury"], game, load_order, modded_pack, |modded_pack, _| prepare_starting_treasury(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Research rate and construction time multipliers.
+    run_feature(cli, game, report, reserved_pack, "Research Rate and Construction Time Multipliers", |reserved_pack| with_feature_scope(cli, &["research_rate_multiplier", "construction_time_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_research_and_construction_multipliers(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Upkeep and income multipliers.
+    run_feature(cli, game, report, reserved_pack, "Upkeep and Income Multipliers", |reserved_pack| with_feature_scope(cli, &["upkeep_multiplier", "income_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_upkeep_and_income_multipliers(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Public order and corruption.
+    run_feature(cli, game, report, reserved_pack, "Public Order Bonus and Corruption Multiplier", |reserved_pack| with_feature_scope(cli, &["public_order_bonus", "corruption_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_public_order_and_corruption(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Experience multiplier.
+    run_feature(cli, game, report, reserved_pack, "Experience Multiplier", |reserved_pack| with_feature_scope(cli, &["xp_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_xp_multiplier(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Winds of magic multiplier.
+    run_feature(cli, game, report, reserved_pack, "Winds of Magic Multiplier", |reserved_pack| with_feature_scope(cli, &["magic_multiplier"], game, load_order, modded_pack, |modded_pack, _| prepare_magic_multiplier(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Skill points per level.
+    run_feature(cli, game, report, reserved_pack, "Skill Points per Level", |reserved_pack| with_feature_scope(cli, &["skill_points_per_level"], game, load_order, modded_pack, |modded_pack, _| prepare_skill_points_per_level(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
 
     // Universal rebalancer.
-    prepare_universal_rebalancer(cli, game, reserved_pack, vanilla_pack, modded_pack, schema, load_order)?;
+    run_feature(cli, game, report, reserved_pack, "Universal Rebalancer", |reserved_pack| with_feature_scope(cli, &["universal_rebalancer"], game, load_order, modded_pack, |modded_pack, load_order| prepare_universal_rebalancer(cli, game, reserved_pack, vanilla_pack, modded_pack, schema, load_order)))?;
+
+    // Table patches.
+    run_feature(cli, game, report, reserved_pack, "Table Patches", |reserved_pack| with_feature_scope(cli, &["table_patch"], game, load_order, modded_pack, |modded_pack, _| prepare_table_patches(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
+
+    // Lua patches.
+    run_feature(cli, game, report, reserved_pack, "Lua Patches", |reserved_pack| with_feature_scope(cli, &["lua_patch"], game, load_order, modded_pack, |modded_pack, _| prepare_lua_patches(cli, game, reserved_pack, vanilla_pack, modded_pack, schema)))?;
 
     // Enable dev ui in all ui files.
-    prepare_dev_ui(cli, game, reserved_pack, vanilla_pack, modded_pack)?;
+    run_feature(cli, game, report, reserved_pack, "Enable Dev UI", |reserved_pack| with_feature_scope(cli, &["enable_dev_ui"], game, load_order, modded_pack, |modded_pack, _| prepare_dev_ui(cli, game, reserved_pack, vanilla_pack, modded_pack)))?;
 
     // SQL Queries.
-    prepare_sql_queries(cli, game, reserved_pack, vanilla_pack, modded_pack, schema, game_path)?;
+    run_feature(cli, game, report, reserved_pack, "SQL Scripts", |reserved_pack| with_feature_scope(cli, &["sql_script"], game, load_order, modded_pack, |modded_pack, _| prepare_sql_queries(cli, game, reserved_pack, vanilla_pack, modded_pack, schema, schema_path, game_path, sql_cache)))?;
 
     Ok(())
 }
 
+/// This function runs a feature, tagging the files it edits in the report, and recording if it failed.
+///
+/// With --keep-going, failures are logged, the changes the failed feature did to the reserved pack are undone, and the run continues.
+/// Otherwise, they're returned.
+fn run_feature<F>(cli: &PatchArgs, game: &GameInfo, report: &mut Report, reserved_pack: &mut Pack, feature: &str, run: F) -> Result<()>
+    where F: FnOnce(&mut Pack) -> Result<()> {
+
+    if cli.keep_going {
+        report.keep_snapshot(reserved_pack);
+    }
+
+    let result = run(reserved_pack);
+    if result.is_err() && cli.keep_going {
+        report.rollback(reserved_pack, game);
+    }
+
+    report.tag(reserved_pack, game, feature);
+    report.set_feature_status(feature, result.as_ref().err().map(|error| error.to_string()));
+
+    match result {
+        Err(error) if cli.keep_going => {
+            error!("{} failed: {}. Continuing with the rest of the features, as --keep-going is enabled.", feature, error);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// This function returns the paths of the game data the enabled features need, so we don't have to load the rest.
+///
+/// Returns None if all the game data is needed. An empty list means no game data is needed at all.
+pub fn required_data_paths(cli: &PatchArgs, game: &GameInfo) -> Option<Vec<ContainerPath>> {
+    let mut folders = vec![];
+
+    if cli.skip_intro_videos && matches!(game.key(), KEY_TROY | KEY_PHARAOH | KEY_PHARAOH_DYNASTIES) {
+        folders.push("db/");
+    }
+
+    if cli.enable_logging && matches!(game.key(), KEY_SHOGUN_2 | KEY_ROME_2 | KEY_ATTILA | KEY_THRONES_OF_BRITANNIA) {
+        folders.push("script/");
+        folders.push("campaigns/");
+    }
+
+    let needs_sql = cli.sql_script.is_some() ||
+        cli.sql_script_dir.is_some() ||
+        cli.sql_preset.is_some() ||
+        cli.sql_query.is_some() ||
+        cli.export_db.is_some();
+
+    let needs_tables = needs_sql ||
+        cli.remove_trait_limit ||
+        cli.remove_siege_attacker ||
+        cli.remove_battle_timer ||
+        cli.unlock_all_factions ||
+        cli.remove_agent_caps ||
+        cli.remove_unit_caps ||
+        cli.normalize_ai_difficulty ||
+        cli.remove_supply_lines ||
+        cli.no_attrition ||
+        cli.unlock_battle_camera ||
+        cli.immortal_characters ||
+        cli.disable_endgame ||
+        cli.unit_multiplier.is_some() ||
+        cli.garrison_multiplier.is_some() ||
+        cli.magic_multiplier.is_some() ||
+        cli.campaign_movement_multiplier.is_some() ||
+        cli.starting_treasury.is_some() ||
+        cli.research_rate_multiplier.is_some() ||
+        cli.construction_time_multiplier.is_some() ||
+        cli.upkeep_multiplier.is_some() ||
+        cli.income_multiplier.is_some() ||
+        cli.xp_multiplier.is_some() ||
+        cli.skill_points_per_level.is_some() ||
+        cli.public_order_bonus.is_some() ||
+        cli.corruption_multiplier.is_some() ||
+        cli.universal_rebalancer.is_some() ||
+        cli.table_patch.is_some();
+
+    if needs_tables {
+        folders.push("db/");
+    }
+
+    // SQL scripts can edit locs too.
+    if needs_sql {
+        folders.push("text/");
+        folders.push(TRANSLATED_PATH_OLD);
+    }
+
+    if cli.translation_language.is_some() {
+        folders.push("text/");
+        folders.push(TRANSLATED_PATH_OLD);
+
+        if cli.patch_fonts {
+            folders.push(FONTS_PATH);
+        }
+    }
+
+    if cli.enable_dev_ui {
+        folders.push("ui/");
+    }
+
+    // Lua patches can read any file, so they need everything.
+    if cli.lua_patch.is_some() {
+        return None;
+    }
+
+    folders.sort();
+    folders.dedup();
+    Some(folders.into_iter()
+        .map(|folder| if folder.ends_with('/') {
+            ContainerPath::Folder(folder.to_owned())
+        } else {
+            ContainerPath::File(folder.to_owned())
+        })
+        .collect())
+}
+
+/// This function creates a pool of SQLite connections, limited to the amount of threads we can use, if limited.
+fn sqlite_pool(cli: &PatchArgs, manager: SqliteConnectionManager) -> Result<Pool<SqliteConnectionManager>> {
+    match cli.threads {
+        Some(threads) => Pool::builder().max_size(threads).build(manager).map_err(From::from),
+        None => Pool::new(manager).map_err(From::from),
+    }
+}
+
+/// This function executes a feature over the load order, or over the part of it the feature has been scoped to through `--feature-scope`.
+fn with_feature_scope<F>(cli: &PatchArgs, features: &[&str], game: &GameInfo, load_order: &[PathBuf], modded_pack: &mut Pack, feature: F) -> Result<()>
+    where F: FnOnce(&mut Pack, &[PathBuf]) -> Result<()> {
+
+    set_log_section(features[0]);
+
+    let scopes = cli.feature_scope.iter()
+        .flatten()
+        .filter(|scope| features.contains(&scope.feature.as_str()))
+        .collect::<Vec<_>>();
+
+    if scopes.is_empty() {
+        return feature(modded_pack, load_order);
+    }
+
+    let scoped_load_order = load_order.iter()
+        .filter(|path| {
+            let pack_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            scopes.iter().all(|scope| scope.contains(&pack_name))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
 
-pub fn prepare_dev_ui(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack) -> Result<()> {
+    info!("- Scope of {}: {} of {} Packs.", features.join(", "), scoped_load_order.len(), load_order.len());
+
+    let mut scoped_modded_pack = init_modded_pack(game, &scoped_load_order)?;
+    feature(&mut scoped_modded_pack, &scoped_load_order)
+}
+
+pub fn prepare_dev_ui(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack) -> Result<()> {
     info!("- Enable Dev UI: {}.", cli.enable_dev_ui);
 
     if cli.enable_dev_ui {
 
+        // Some ui files break when their dev-only parts are enabled. Those need to be skipped.
+        let excluded_paths = cli.dev_ui_exclude.clone().unwrap_or_default();
+
         let mut files = vanilla_pack.files_by_type_and_paths(&[FileType::Text], &[ContainerPath::Folder("ui/".to_owned())], true)
             .into_iter()
             .cloned()
@@ -193,6 +687,16 @@ pub fn prepare_dev_ui(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vani
         // Sort them so file processing is done in the correct order.
         files.sort_by_key(|rfile| rfile.path_in_container_raw().to_string());
 
+        files.retain(|rfile| {
+            let path = rfile.path_in_container_raw();
+            let excluded = excluded_paths.iter().any(|pattern| glob_match(pattern, path));
+            if excluded {
+                debug!("  - Skipping excluded ui file: {}.", path);
+            }
+
+            !excluded
+        });
+
         let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
         let dec_extra_data = Some(DecodeableExtraData::default());
 
@@ -226,15 +730,53 @@ pub fn prepare_dev_ui(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vani
     Ok(())
 }
 
-pub fn prepare_sql_queries(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, game_path: &Path) -> Result<()> {
-    info!("- Apply SQL Scripts: {}.", cli.sql_script.is_some());
+pub fn prepare_table_patches(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Apply Table Patches: {}.", cli.table_patch.is_some());
+
+    if let Some(ref paths) = cli.table_patch {
+        for path in paths {
+            info!("  - Table Patch File: {}.", path.to_string_lossy().to_string().replace("\\", "/"));
+
+            let file = TablePatchFile::read(path)?;
+            file.apply(game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_lua_patches(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Apply Lua Patches: {}.", cli.lua_patch.is_some());
+
+    if let Some(ref paths) = cli.lua_patch {
+        for path in paths {
+            info!("  - Lua Patch File: {}.", path.to_string_lossy().to_string().replace("\\", "/"));
+
+            apply_lua_patch(path, game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_sql_queries(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, schema_path: &Path, game_path: &Path, mut sql_cache: Option<&mut SqlCache>) -> Result<()> {
+    let scripts = sql_scripts(cli, game)?;
+    info!("- Apply SQL Scripts: {}.", !scripts.is_empty());
+
+    if let Some(ref path) = cli.export_db {
+        info!("- Export SQL database to: {}.", path.display());
+    }
+
+    if let Some(ref queries) = cli.sql_query {
+        info!("- Apply SQL Queries: {}.", queries.len());
+    }
 
-    if let Some(ref scripts) = cli.sql_script {
+    if !scripts.is_empty() || cli.sql_query.is_some() || cli.export_db.is_some() {
         let mut script_failed = false;
 
         info!("  - SQL Scripts to apply:");
 
-        for (path, params) in scripts {
+        for (path, params) in &scripts {
             info!("    - Path: {}. Params: {}", path.to_string_lossy().to_string().replace("\\", "/"), params.join(","));
         }
 
@@ -275,111 +817,258 @@ pub fn prepare_sql_queries(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack,
 
         let db_path = config_path()?.join(format!("{}/{}{}", DB_FOLDER, game.key(), DB_EXTENSION));
         let db_path_bak = config_path()?.join(format!("{}/{}{}", DB_FOLDER, game.key(), DB_BAK_EXTENSION));
+        let db_path_merged = config_path()?.join(format!("{}/{}{}", DB_FOLDER, game.key(), DB_MERGED_EXTENSION));
+
+        // Schema updates can change the table definitions, so the database needs a rebuild if it was built with another schema.
+        let schema_revision = sql_schema_revision(schema_path);
+        let schema_changed = db_path_bak.is_file() && sql_stored_schema_revision(&db_path_bak).as_ref() != Some(&schema_revision);
 
         let exe_path = game.executable_path(game_path).unwrap_or_default();
-        if !db_path_bak.is_file() || exe_path.is_file() && exe_path.metadata()?.created()? > db_path_bak.metadata()?.modified()? {
-            info!("  - Recreating vanilla db, as either it didn't exist, or the game has been updated.");
-
-            // Make sure the database is clean before rebuilding it.
-            let manager = SqliteConnectionManager::file(&db_path_bak);
-            let pool = Pool::new(manager)?;
-            if let Err(error) = pool.get()?.execute_batch("
-                PRAGMA writable_schema = 1;
-                delete from sqlite_master where type in ('table', 'index', 'trigger');
-                PRAGMA writable_schema = 0;
-                VACUUM;
-                PRAGMA INTEGRITY_CHECK;
-            ") {
-                script_failed = true;
-                error!("  - Error reseting the database file: {}.", error);
+        let rebuild = !db_path_bak.is_file() || schema_changed || exe_path.is_file() && exe_path.metadata()?.created()? > db_path_bak.metadata()?.modified()?;
+        if rebuild {
+            info!("  - Updating vanilla db, as either it didn't exist, the game has been updated, or the schema has been updated.");
+
+            let manager = SqliteConnectionManager::file(&db_path_bak).with_init(|connection| connection.execute_batch(SQL_IMPORT_PRAGMAS));
+            let pool = sqlite_pool(cli, manager)?;
+
+            // Checksums of the vanilla tables, so we only need to reimport the ones that changed.
+            let checksums = tables.par_iter_mut()
+                .filter(|(_, is_vanilla)| *is_vanilla)
+                .map(|(table, _)| {
+                    let checksum = match table.load().ok().and_then(|_| table.cached().ok()) {
+                        Some(data) => content_hash(data),
+                        None => content_hash(&[]),
+                    };
+
+                    (table.path_in_container_raw().to_owned(), checksum)
+                })
+                .collect::<HashMap<_, _>>();
+
+            // If the schema changed, any table may have changed its columns, so we need a full rebuild.
+            let stored_checksums = if schema_changed {
+                HashMap::new()
+            } else {
+                sql_table_checksums(&pool).unwrap_or_default()
+            };
+
+            // Tables are reimported by table name, as all the files of a table share the same table in the database.
+            let changed_tables = checksums.iter()
+                .filter(|(path, checksum)| stored_checksums.get(*path) != Some(*checksum))
+                .map(|(path, _)| path)
+                .chain(stored_checksums.keys().filter(|path| !checksums.contains_key(*path)))
+                .filter_map(|path| path.split('/').nth(1))
+                .map(|table_name| table_name.to_owned())
+                .collect::<HashSet<_>>();
+
+            if stored_checksums.is_empty() {
+                info!("  - Building SQL database with vanilla data.");
+
+                // Make sure the database is clean before rebuilding it.
+                if let Err(error) = pool.get()?.execute_batch("
+                    PRAGMA writable_schema = 1;
+                    delete from sqlite_master where type in ('table', 'index', 'trigger');
+                    PRAGMA writable_schema = 0;
+                    VACUUM;
+                    PRAGMA INTEGRITY_CHECK;
+                ") {
+                    script_failed = true;
+                    error!("  - Error reseting the database file: {}.", error);
+                }
+            } else {
+                info!("  - Updating {} tables in the SQL database with vanilla data.", changed_tables.len());
+
+                if let Err(error) = drop_sql_tables(&pool, &changed_tables) {
+                    script_failed = true;
+                    error!("  - Error removing outdated tables from the database: {}.", error);
+                }
             }
 
-            info!("  - Building SQL database with vanilla data.");
+            // Decoding is the slow part, so it's done in parallel. SQLite only admits one writer, so the import is done in order.
+            tables.par_iter_mut()
+                .filter(|(_, is_vanilla)| *is_vanilla)
+                .for_each(|(table, _)| {
+                    let _ = table.decode(&dec_extra_data, true, false);
+                });
 
+            let progress = Progress::new("sql_vanilla_import", tables.iter().filter(|(_, is_vanilla)| *is_vanilla).count());
             for (table, is_vanilla) in &mut tables {
                 if *is_vanilla {
-                    if let Ok(Some(RFileDecoded::DB(data))) = table.decode(&dec_extra_data, true, true) {
+                    progress.inc();
+
+                    let table_name = table.path_in_container_raw().split('/').nth(1).unwrap_or_default().to_owned();
+                    if !stored_checksums.is_empty() && !changed_tables.contains(&table_name) {
+                        continue;
+                    }
+
+                    if let Ok(RFileDecoded::DB(data)) = table.decoded() {
                         let container_name = table.container_name().clone().unwrap();
                         let file_name = table.file_name().unwrap().to_owned();
 
+                        let start = Instant::now();
                         if let Err(error) = data.table().db_to_sql(&pool, &container_name, &file_name, *is_vanilla) {
                             warn!("  - Table {}_v{} failed to be populated in the database, with the following error: {}.", data.table_name(), data.definition().version(), error);
+                        } else {
+                            debug!("    - Table {} imported in {:.2?}.", table.path_in_container_raw(), start.elapsed());
                         }
                     }
                 }
             }
+
+            progress.finish();
+
+            if let Err(error) = save_sql_table_checksums(&pool, &checksums) {
+                warn!("  - Error saving the checksums of the vanilla tables. The next update will rebuild the whole database: {}.", error);
+            }
+
+            if let Err(error) = save_sql_schema_revision(&pool, &schema_revision) {
+                warn!("  - Error saving the schema revision of the database. The next run will rebuild the whole database: {}.", error);
+            }
         }
 
-        // In case we have a pre-existing valid db, we still need to decode in memory the tables.
-        // Otherwise, the sql_to_db functions won't work and data will not be moved back to the pack.
         else {
             info!("  - Found existing SQL database with vanilla data still valid. Using it.");
+        }
 
-            tables.par_iter_mut()
-                .filter(|(_, is_vanilla)| *is_vanilla)
-                .for_each(|(table, _)| {
-                    let _ = table.decode(&dec_extra_data, true, false);
-                });
+        // Even with a pre-existing valid db, we still need to decode in memory the tables.
+        // Otherwise, the sql_to_db functions won't work and data will not be moved back to the pack.
+        //
+        // The server mode keeps them decoded between patches, as long as the vanilla data and the schema don't change.
+        let vanilla_key = sql_vanilla_key(&db_path_bak, &schema_revision);
+        match sql_cache.as_deref_mut() {
+            Some(cache) if vanilla_key.is_some() && cache.vanilla_key == vanilla_key => {
+                info!("  - Using the cached vanilla tables.");
+
+                tables.retain(|(_, is_vanilla)| !*is_vanilla);
+                tables.extend(cache.vanilla_tables.iter().cloned().map(|table| (table, true)));
+                tables.sort_by_key(|(rfile, _)| rfile.path_in_container_raw().to_string());
+            }
+            cache => {
+                if !rebuild {
+                    tables.par_iter_mut()
+                        .filter(|(_, is_vanilla)| *is_vanilla)
+                        .for_each(|(table, _)| {
+                            let _ = table.decode(&dec_extra_data, true, false);
+                        });
+                }
+
+                if let Some(cache) = cache {
+                    cache.vanilla_key = vanilla_key.clone();
+                    cache.vanilla_tables = tables.iter()
+                        .filter(|(_, is_vanilla)| *is_vanilla)
+                        .map(|(table, _)| table.clone())
+                        .collect();
+                }
+            }
         }
 
-        std::fs::copy(db_path_bak, &db_path)?;
-        let manager = SqliteConnectionManager::file(db_path);
-        let pool = Pool::new(manager)?;
+        let reserved_pack_name = reserved_pack.disk_file_name();
+        let mut locs = vanilla_pack.files_by_type(&[FileType::Loc])
+            .into_iter()
+            .chain(modded_pack.files_by_type(&[FileType::Loc]))
+            .map(|file| (file.clone(), file.container_name().clone().unwrap_or_default()))
+            .chain(reserved_pack.files_by_type(&[FileType::Loc]).into_iter().map(|file| (file.clone(), reserved_pack_name.to_owned())))
+            .collect::<Vec<_>>();
 
-        info!("  - Building SQL database with modded data.");
-        for (table, is_vanilla) in &mut tables {
-            if !*is_vanilla {
-                if let Ok(Some(RFileDecoded::DB(data))) = table.decode(&dec_extra_data, true, true) {
-                    let container_name = table.container_name().clone().unwrap();
-                    let file_name = table.file_name().unwrap().to_owned();
+        // The server mode also keeps a copy of the database with the modded data, as long as the modded tables and locs don't change.
+        let merged_key = match (&sql_cache, &vanilla_key) {
+            (Some(_), Some(vanilla_key)) => sql_merged_key(vanilla_key, &mut tables, &mut locs, &enc_extra_data),
+            _ => None,
+        };
 
-                    if let Err(error) = data.table().db_to_sql(&pool, &container_name, &file_name, *is_vanilla) {
-                        warn!("  - Table {}_v{} failed to be populated in the database, with the following error: {}.", data.table_name(), data.definition().version(), error);
-                    }
-                }
+        let use_merged = db_path_merged.is_file() && merged_key.is_some() && sql_cache.as_ref().is_some_and(|cache| cache.merged_key == merged_key);
+        if use_merged {
+            std::fs::copy(&db_path_merged, &db_path)?;
+        } else {
+            std::fs::copy(&db_path_bak, &db_path)?;
+        }
+
+        // Attachments are per-connection, so they need to be done on each connection the pool opens.
+        let attachments = cli.sql_attach.clone().unwrap_or_default();
+        let manager = SqliteConnectionManager::file(&db_path).with_init(move |connection| {
+            connection.execute_batch(SQL_IMPORT_PRAGMAS)?;
+            for attachment in &attachments {
+                connection.execute("ATTACH DATABASE ?1 AS ?2", params![attachment.path.to_string_lossy(), attachment.alias])?;
+            }
+            Ok(())
+        });
+        let pool = sqlite_pool(cli, manager)?;
+
+        tables.par_iter_mut()
+            .filter(|(_, is_vanilla)| !*is_vanilla)
+            .for_each(|(table, _)| {
+                let _ = table.decode(&dec_extra_data, true, false);
+            });
+
+        if use_merged {
+            info!("  - Using the cached SQL database with modded data.");
+        } else {
+            sql_import_modded(&pool, &mut tables, &mut locs)?;
+
+            if let Some(cache) = sql_cache {
+                std::fs::copy(&db_path, &db_path_merged)?;
+                cache.merged_key = merged_key;
             }
         }
 
+        // Export the merged data before any script touches it.
+        if let Some(ref path) = cli.export_db {
+            export_sql_db(&pool, path, cli.export_db_tables.as_deref())?;
+        }
+
+        if scripts.is_empty() && cli.sql_query.is_none() {
+            return Ok(());
+        }
+
         info!("  - Executing scripts:");
 
         let mut edited_tables = vec![];
         let mut new_tables = vec![];
+        let mut new_locs = vec![];
 
         // Execute all the scripts in order.
-        for (path, params) in scripts {
+        for (path, params) in &scripts {
             let path_str = path.to_string_lossy().to_string().replace("\\", "/");
 
             info!("    - Executing script: {}", path_str);
 
             match SQLScript::from_path(path) {
                 Ok(script) => {
-                    edited_tables.extend_from_slice(&script.metadata()
-                        .tables_affected()
-                        .iter()
-                        .map(|x| format!("db/{}_tables/", x))
-                        .collect::<Vec<_>>());
+                    let param_values = match sql_script_param_values(&script, params) {
+                        Ok(param_values) => param_values,
+                        Err(error) => {
+                            script_failed = true;
+                            error!("    - Error in the params of the script: {}", error);
+                            continue;
+                        }
+                    };
 
-                    new_tables.extend_from_slice(&script.metadata()
-                        .tables_created()
-                        .iter()
-                        .map(|(table_name, file_name)| (format!("db/{table_name}_tables/{file_name}"), format!("{table_name}_tables")))
-                        .collect::<Vec<_>>());
+                    let query = script.prepare(param_values, &reserved_pack.disk_file_name());
 
-                    let mut param_values = HashMap::new();
+                    // Failed scripts are rolled back, so their tables are left untouched.
+                    let start = Instant::now();
+                    match execute_in_transaction(&pool, &query) {
+                        Ok(_) => {
+                            debug!("      - Executed in {:.2?}.", start.elapsed());
 
-                    for (index, param) in script.metadata().parameters().iter().enumerate() {
-                        match params.get(index) {
-                            Some(param_value) => param_values.insert(param.key().to_string(), param_value.to_string()),
-                            None => param_values.insert(param.key().to_string(), param.default_value().to_string()),
-                        };
-                    }
+                            new_locs.extend(sql_script_locs_created(path)?);
 
-                    let query = script.prepare(param_values, &reserved_pack.disk_file_name());
+                            edited_tables.extend_from_slice(&script.metadata()
+                                .tables_affected()
+                                .iter()
+                                .map(|x| format!("db/{}_tables/", x))
+                                .collect::<Vec<_>>());
 
-                    if let Err(error) = pool.get()?.execute_batch(&query) {
-                        script_failed = true;
-                        error!("  - SQL script failed to execute with the following error: {}.", error);
-                        error!("  - Contents of the SQL script that failed (in case the error message doesn't output the full script):\n {}.", &query);
+                            new_tables.extend_from_slice(&script.metadata()
+                                .tables_created()
+                                .iter()
+                                .map(|(table_name, file_name)| (format!("db/{table_name}_tables/{file_name}"), format!("{table_name}_tables")))
+                                .collect::<Vec<_>>());
+                        }
+                        Err(error) => {
+                            script_failed = true;
+                            error!("  - SQL script failed to execute with the following error: {}. Its changes have been rolled back.", error);
+                            error!("  - Contents of the SQL script that failed (in case the error message doesn't output the full script):\n {}.", &query);
+                        }
                     }
                 }
                 Err(error) => {
@@ -389,6 +1078,29 @@ pub fn prepare_sql_queries(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack,
             }
         }
 
+        // Queries have no metadata, so the tables they edit are taken from the query itself.
+        for query in cli.sql_query.iter().flatten() {
+            info!("    - Executing query: {}", query);
+
+            let tables = sql_query_tables(query);
+            if tables.is_empty() {
+                warn!("    - The query doesn't edit any table. Only INSERT, REPLACE, UPDATE and DELETE queries can change the generated Pack.");
+            }
+
+            let start = Instant::now();
+            match execute_in_transaction(&pool, query) {
+                Ok(_) => {
+                    debug!("      - Executed in {:.2?}.", start.elapsed());
+
+                    edited_tables.extend(tables);
+                }
+                Err(error) => {
+                    script_failed = true;
+                    error!("  - SQL query failed to execute with the following error: {}. Its changes have been rolled back.", error);
+                }
+            }
+        }
+
         info!("  - Rebuilding in-memory tables.");
 
         // If the script contains tables to create, make them empty so they're used in the retrieving pass.
@@ -424,6 +1136,7 @@ pub fn prepare_sql_queries(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack,
                     let container_name = table.container_name().clone().unwrap_or_else(|| reserved_pack.disk_file_name());
                     let file_name = table.file_name().unwrap().to_owned();
 
+                    let start = Instant::now();
                     if let Ok(RFileDecoded::DB(ref mut data)) = table.decoded_mut() {
                         data.sql_to_db(&pool, &container_name, &file_name)?;
                     } else {
@@ -432,37 +1145,626 @@ pub fn prepare_sql_queries(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack,
 
                     table.encode(&enc_extra_data, false, true, false)?;
                     reserved_pack.insert(table.clone())?;
+
+                    debug!("    - Table {} written back in {:.2?}.", table.path_in_container_raw(), start.elapsed());
                     break;
                 }
             }
         }
 
+        // Retrieve the locs edited by the scripts, if any.
+        match sql_to_locs(&pool, reserved_pack, &new_locs) {
+            Ok(0) => {},
+            Ok(count) => info!("  - {} loc files edited by the scripts added to the Pack.", count),
+            Err(error) => {
+                script_failed = true;
+                error!("  - Error retrieving the locs edited by the scripts: {}.", error);
+            }
+        }
+
         info!("  - SQL scripts processed.");
 
         if script_failed {
             error!("  - Something failed when processing the SQL scripts. Read this terminal for more info.");
-            return Err(anyhow!("Something failed when processing the SQL scripts."));
+            return Err(SqlScriptsError.into());
         }
     }
 
     Ok(())
 }
 
-pub fn prepare_script_logging(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack) -> Result<()> {
-    info!("- Enable script logging: {}.", cli.enable_logging);
+/// This function validates a SQL script: its metadata, the tables it uses, and its execution against the vanilla and modded data.
+///
+/// The data is loaded into a temporary in-memory database, so nothing on disk is changed. Returns if the script is valid.
+pub fn validate_sql_script(vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema, path: &Path, params: &[String]) -> Result<bool> {
+    let mut valid = true;
+    let script = SQLScript::from_path(path)?;
 
-    if cli.enable_logging {
-        match game.key() {
-            KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => pharaoh::prepare_script_logging(reserved_pack),
-            KEY_WARHAMMER_3 => warhammer_3::prepare_script_logging(reserved_pack),
-            KEY_TROY => troy::prepare_script_logging(reserved_pack),
-            KEY_THREE_KINGDOMS => Ok(()),
-            KEY_WARHAMMER_2 => warhammer_2::prepare_script_logging(reserved_pack),
-            KEY_WARHAMMER |
-            KEY_THRONES_OF_BRITANNIA |
-            KEY_ATTILA |
-            KEY_ROME_2 |
-            KEY_SHOGUN_2 |
+    let param_values = sql_script_param_values(&script, params)?;
+
+    info!("- Parameters:");
+    for param in script.metadata().parameters() {
+        info!("  - {}: {}.", param.key(), param_values.get(param.key()).map(|value| value.as_str()).unwrap_or_default());
+    }
+
+    // Tables we create need to exist in the schema too, as we copy their definition from an existing table.
+    let tables = script.metadata().tables_affected()
+        .iter()
+        .map(|table_name| (table_name.to_owned(), "affected"))
+        .chain(script.metadata().tables_created().iter().map(|(table_name, _)| (table_name.to_owned(), "created")))
+        .collect::<Vec<_>>();
+
+    let locs_created = sql_script_locs_created(path)?;
+    if !locs_created.is_empty() {
+        info!("- Loc files created:");
+        for loc_path in &locs_created {
+            info!("  - {}.", loc_path);
+        }
+    }
+
+    info!("- Tables:");
+    for (table_name, kind) in &tables {
+        if schema.definitions().contains_key(&format!("{}_tables", table_name)) {
+            info!("  - {} ({}).", table_name, kind);
+        } else {
+            error!("  - {} ({}): not found in the schema.", table_name, kind);
+            valid = false;
+        }
+    }
+
+    let dec_extra_data = {
+        let mut dec_extra_data = DecodeableExtraData::default();
+        dec_extra_data.set_schema(Some(schema));
+        Some(dec_extra_data)
+    };
+
+    // A single connection, as each connection gets its own in-memory database.
+    let manager = SqliteConnectionManager::memory();
+    let pool = Pool::builder().max_size(1).build(manager)?;
+
+    info!("- Building in-memory SQL database.");
+    let mut db_tables = vanilla_pack.files_by_type(&[FileType::DB])
+        .into_iter()
+        .cloned()
+        .map(|mut table| {
+            rename_file_name_to_low_priority(&mut table);
+            (table, true)
+        })
+        .chain(modded_pack.files_by_type(&[FileType::DB]).into_iter().cloned().map(|table| (table, false)))
+        .collect::<Vec<_>>();
+
+    db_tables.sort_by_key(|(rfile, _)| rfile.path_in_container_raw().to_string());
+
+    for (table, is_vanilla) in &mut db_tables {
+        if let Ok(Some(RFileDecoded::DB(data))) = table.decode(&dec_extra_data, false, true) {
+            let container_name = table.container_name().clone().unwrap_or_default();
+            let file_name = table.file_name().unwrap_or_default().to_owned();
+
+            if let Err(error) = data.table().db_to_sql(&pool, &container_name, &file_name, *is_vanilla) {
+                debug!("  - Table {}_v{} failed to be populated in the database, with the following error: {}.", data.table_name(), data.definition().version(), error);
+            }
+        }
+    }
+
+    info!("- Executing script.");
+    let query = script.prepare(param_values, RESERVED_PACK_NAME);
+    if let Err(error) = pool.get()?.execute_batch(&query) {
+        error!("  - SQL script failed to execute with the following error: {}.", error);
+
+        debug!("  - Contents of the SQL script that failed:\n {}.", &query);
+
+        valid = false;
+    }
+
+    Ok(valid)
+}
+
+/// This function exports the SQL database with the merged data.
+///
+/// If tables are provided, they're exported to CSV files in the provided folder, one per table. Otherwise, the whole database is exported to the provided path.
+fn export_sql_db(pool: &Pool<SqliteConnectionManager>, path: &Path, tables: Option<&[String]>) -> Result<()> {
+    let connection = pool.get()?;
+
+    match tables {
+        Some(tables) => {
+            DirBuilder::new().recursive(true).create(path)?;
+
+            let mut statement = connection.prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?;
+            let names = statement.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            for name in names.iter().filter(|name| tables.iter().any(|table| glob_match(table, name))) {
+                let mut statement = connection.prepare(&format!("SELECT * FROM \"{}\"", name))?;
+                let column_names = statement.column_names().iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+                let mut writer = WriterBuilder::new().from_path(path.join(format!("{}.csv", name)))?;
+                writer.write_record(&column_names)?;
+
+                let mut rows = statement.query([])?;
+                while let Some(row) = rows.next()? {
+                    let record = (0..column_names.len())
+                        .map(|index| match row.get_ref(index) {
+                            Ok(ValueRef::Null) | Err(_) => String::new(),
+                            Ok(ValueRef::Integer(value)) => value.to_string(),
+                            Ok(ValueRef::Real(value)) => value.to_string(),
+                            Ok(ValueRef::Text(value)) | Ok(ValueRef::Blob(value)) => String::from_utf8_lossy(value).to_string(),
+                        })
+                        .collect::<Vec<_>>();
+
+                    writer.write_record(&record)?;
+                }
+
+                writer.flush()?;
+                info!("  - Table {} exported.", name);
+            }
+        }
+
+        None => {
+            if path.is_file() {
+                remove_file(path)?;
+            }
+
+            connection.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+        }
+    }
+
+    info!("  - SQL database exported to: {}.", path.display());
+    Ok(())
+}
+
+/// This function returns the values of the params of a SQL script.
+///
+/// Params can be provided by name, as key=value, or by position. Params not provided use their default value, if they have one.
+/// Otherwise, they're asked for if we're in a terminal, or an error listing the script's params is returned if we're not.
+fn sql_script_param_values(script: &SQLScript, params: &[String]) -> Result<HashMap<String, String>> {
+    let script_params = script.metadata().parameters();
+    let mut param_values = HashMap::new();
+    let mut positional = vec![];
+
+    for param in params {
+        match param.split_once('=') {
+            Some((key, value)) if script_params.iter().any(|script_param| script_param.key() == key) => { param_values.insert(key.to_owned(), value.to_owned()); },
+            _ => positional.push(param.to_owned()),
+        }
+    }
+
+    // Positional params fill the params not provided by name, in order.
+    let mut positional = positional.into_iter();
+    for script_param in script_params.iter().filter(|script_param| !param_values.contains_key(script_param.key())) {
+        match positional.next() {
+            Some(value) => { param_values.insert(script_param.key().to_owned(), value); },
+            None => break,
+        }
+    }
+
+    if positional.next().is_some() {
+        warn!("    - More params provided than the ones the script has. The extra ones will be ignored.");
+    }
+
+    let param_list = script_params.iter()
+        .map(|script_param| if script_param.default_value().is_empty() {
+            script_param.key().to_owned()
+        } else {
+            format!("{} (default: {})", script_param.key(), script_param.default_value())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    for script_param in script_params {
+        if param_values.contains_key(script_param.key()) {
+            continue;
+        }
+
+        if !script_param.default_value().is_empty() {
+            param_values.insert(script_param.key().to_owned(), script_param.default_value().to_owned());
+        } else if stdin().is_terminal() {
+            print!("Value for the param {}: ", script_param.key());
+            stdout().flush()?;
+
+            let mut value = String::new();
+            stdin().read_line(&mut value)?;
+            param_values.insert(script_param.key().to_owned(), value.trim().to_owned());
+        } else {
+            return Err(anyhow!("Param {} has no value and no default value. The params of the script are: {}.", script_param.key(), param_list));
+        }
+    }
+
+    Ok(param_values)
+}
+
+/// This function imports the loc files into the locs table of the database, and sets up the tracking of the files edited by the scripts.
+fn locs_to_sql(pool: &Pool<SqliteConnectionManager>, locs: &mut [(RFile, String)]) -> Result<()> {
+    let mut connection = pool.get()?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(&format!("
+        DROP TABLE IF EXISTS {0};
+        DROP TABLE IF EXISTS {1};
+        CREATE TABLE {0} (key TEXT NOT NULL, value TEXT NOT NULL, tooltip INTEGER NOT NULL DEFAULT 0, pack TEXT NOT NULL DEFAULT '', file TEXT NOT NULL DEFAULT '{2}');
+        CREATE TABLE {1} (file TEXT NOT NULL);
+        CREATE TRIGGER {0}_insert AFTER INSERT ON {0} BEGIN INSERT INTO {1} VALUES (new.file); END;
+        CREATE TRIGGER {0}_update AFTER UPDATE ON {0} BEGIN INSERT INTO {1} VALUES (old.file); INSERT INTO {1} VALUES (new.file); END;
+        CREATE TRIGGER {0}_delete AFTER DELETE ON {0} BEGIN INSERT INTO {1} VALUES (old.file); END;
+    ", SQL_LOCS_TABLE, SQL_LOCS_CHANGED_TABLE, SQL_LOCS_DEFAULT_FILE))?;
+
+    {
+        let mut statement = transaction.prepare(&format!("INSERT INTO {} (key, value, tooltip, pack, file) VALUES (?1, ?2, ?3, ?4, ?5)", SQL_LOCS_TABLE))?;
+        for (file, pack_name) in locs.iter_mut() {
+            let path = file.path_in_container_raw().to_owned();
+
+            if let Ok(Some(RFileDecoded::Loc(loc))) = file.decode(&None, false, true) {
+                for row in loc.data().iter() {
+                    let tooltip = matches!(row[2], DecodedData::Boolean(true));
+                    statement.execute(params![row[0].data_to_string(), row[1].data_to_string(), tooltip, pack_name, path])?;
+                }
+            }
+        }
+    }
+
+    // The import itself shouldn't count as an edit.
+    transaction.execute_batch(&format!("DELETE FROM {};", SQL_LOCS_CHANGED_TABLE))?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// This function adds the loc files edited or created by the scripts to the reserved pack, returning how many were added.
+///
+/// Created loc files are added even if no script added rows to them.
+fn sql_to_locs(pool: &Pool<SqliteConnectionManager>, reserved_pack: &mut Pack, new_locs: &[String]) -> Result<usize> {
+    let connection = pool.get()?;
+    let mut statement = connection.prepare(&format!("SELECT DISTINCT file FROM {}", SQL_LOCS_CHANGED_TABLE))?;
+    let mut paths = statement.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    for path in new_locs {
+        if !paths.contains(path) {
+            paths.push(path.to_owned());
+        }
+    }
+
+    let mut statement = connection.prepare(&format!("SELECT key, value, tooltip FROM {} WHERE file = ?1 ORDER BY rowid", SQL_LOCS_TABLE))?;
+    for path in &paths {
+        let data = statement.query_map([path], |row| Ok(vec![
+            DecodedData::StringU16(row.get(0)?),
+            DecodedData::StringU16(row.get(1)?),
+            DecodedData::Boolean(row.get(2)?),
+        ]))?.collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut loc = Loc::new();
+        loc.set_data(&data)?;
+
+        let file = RFile::new_from_decoded(&RFileDecoded::Loc(loc), 0, path);
+        reserved_pack.files_mut().insert(path.to_owned(), file);
+    }
+
+    Ok(paths.len())
+}
+
+/// This function returns the loc files a SQL script creates.
+///
+/// They're declared in the script with a comment line like `-- locs_created: my_texts, text/db/other_texts.loc`.
+/// Names without a folder are created in text/db/.
+fn sql_script_locs_created(path: &Path) -> Result<Vec<String>> {
+    let mut locs = vec![];
+    for name in sql_script_comment_values(path, SQL_LOCS_CREATED_KEY)? {
+        let loc_path = if name.contains('/') {
+            name
+        } else {
+            format!("text/db/{}.loc", name.strip_suffix(".loc").unwrap_or(&name))
+        };
+
+        if !loc_path.starts_with("text/") || !loc_path.ends_with(".loc") {
+            return Err(anyhow!("Invalid loc file {} in {}. Loc files need to be in text/ and end in .loc.", loc_path, path.display()));
+        }
+
+        locs.push(loc_path);
+    }
+
+    Ok(locs)
+}
+
+/// This function returns the comma-separated values of the comment lines of a SQL script starting with the provided key.
+fn sql_script_comment_values(path: &Path, key: &str) -> Result<Vec<String>> {
+    Ok(read_to_string(path)?
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("--").and_then(|line| line.trim().strip_prefix(key)))
+        .flat_map(|values| values.split(','))
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+        .collect())
+}
+
+/// This function sorts the SQL scripts so each one is executed after the ones it depends on.
+///
+/// Scripts declare their id with a comment line like `-- id: my_script` (if not declared, the file name without extension is used),
+/// and their dependencies with `-- depends_on: other_script, another_script`. Scripts without dependencies between them keep their order.
+fn sort_sql_scripts(scripts: Vec<(PathBuf, Vec<String>)>) -> Result<Vec<(PathBuf, Vec<String>)>> {
+    let mut ids = Vec::with_capacity(scripts.len());
+    let mut dependencies = Vec::with_capacity(scripts.len());
+    for (path, _) in &scripts {
+        let id = match sql_script_comment_values(path, SQL_ID_KEY)?.first() {
+            Some(id) => id.to_owned(),
+            None => path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default(),
+        };
+
+        ids.push(id);
+        dependencies.push(sql_script_comment_values(path, SQL_DEPENDS_ON_KEY)?);
+    }
+
+    // Resolve the dependencies to indexes, so we can sort them.
+    let mut pending = Vec::with_capacity(scripts.len());
+    for (index, script_dependencies) in dependencies.iter().enumerate() {
+        let mut indexes = HashSet::new();
+        for dependency in script_dependencies {
+            let dependency_indexes = ids.iter()
+                .enumerate()
+                .filter(|(_, id)| *id == dependency)
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            if dependency_indexes.is_empty() {
+                return Err(anyhow!("SQL script {} depends on script {}, which has not been provided.", ids[index], dependency));
+            }
+
+            indexes.extend(dependency_indexes);
+        }
+
+        pending.push(indexes);
+    }
+
+    let mut sorted = Vec::with_capacity(scripts.len());
+    let mut done = vec![false; scripts.len()];
+    while sorted.len() < scripts.len() {
+        match (0..scripts.len()).find(|index| !done[*index] && pending[*index].iter().all(|dependency| done[*dependency])) {
+            Some(index) => {
+                done[index] = true;
+                sorted.push(index);
+            }
+            None => {
+                let cycle = (0..scripts.len())
+                    .filter(|index| !done[*index])
+                    .map(|index| ids[index].to_owned())
+                    .collect::<Vec<_>>();
+
+                return Err(anyhow!("Cyclic dependency found between the following SQL scripts: {}.", cycle.join(", ")));
+            }
+        }
+    }
+
+    let mut scripts = scripts.into_iter().map(Some).collect::<Vec<_>>();
+    Ok(sorted.into_iter().filter_map(|index| scripts[index].take()).collect())
+}
+
+/// This function returns the checksums of the vanilla tables imported into the database, by path.
+fn sql_table_checksums(pool: &Pool<SqliteConnectionManager>) -> Result<HashMap<String, String>> {
+    let connection = pool.get()?;
+    let mut statement = connection.prepare(&format!("SELECT path, checksum FROM {}", SQL_CHECKSUMS_TABLE))?;
+    let checksums = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+    Ok(checksums)
+}
+
+/// This function imports the modded tables and the locs into the database, on top of the vanilla data.
+fn sql_import_modded(pool: &Pool<SqliteConnectionManager>, tables: &mut [(RFile, bool)], locs: &mut [(RFile, String)]) -> Result<()> {
+    info!("  - Building SQL database with modded data.");
+
+    let progress = Progress::new("sql_modded_import", tables.iter().filter(|(_, is_vanilla)| !*is_vanilla).count());
+    for (table, is_vanilla) in tables.iter_mut() {
+        if !*is_vanilla {
+            progress.inc();
+
+            if let Ok(RFileDecoded::DB(data)) = table.decoded() {
+                let container_name = table.container_name().clone().unwrap();
+                let file_name = table.file_name().unwrap().to_owned();
+
+                let start = Instant::now();
+                if let Err(error) = data.table().db_to_sql(pool, &container_name, &file_name, *is_vanilla) {
+                    warn!("  - Table {}_v{} failed to be populated in the database, with the following error: {}.", data.table_name(), data.definition().version(), error);
+                } else {
+                    debug!("    - Table {} imported in {:.2?}.", table.path_in_container_raw(), start.elapsed());
+                }
+            }
+        }
+    }
+
+    progress.finish();
+
+    info!("  - Building SQL database with loc data.");
+
+    let start = Instant::now();
+    if let Err(error) = locs_to_sql(pool, locs) {
+        warn!("  - Locs failed to be populated in the database, with the following error: {}.", error);
+    } else {
+        debug!("    - {} loc files imported in {:.2?}.", locs.len(), start.elapsed());
+    }
+
+    Ok(())
+}
+
+/// This function returns a key identifying the vanilla data of the provided database: its schema revision and the checksums of its vanilla tables.
+fn sql_vanilla_key(db_path: &Path, schema_revision: &str) -> Option<String> {
+    let connection = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+    let mut statement = connection.prepare(&format!("SELECT path, checksum FROM {} ORDER BY path", SQL_CHECKSUMS_TABLE)).ok()?;
+    let checksums = statement.query_map([], |row| Ok(format!("{}:{}", row.get::<_, String>(0)?, row.get::<_, String>(1)?))).ok()?
+        .collect::<std::result::Result<Vec<_>, _>>().ok()?;
+
+    Some(content_hash(format!("{}\n{}", schema_revision, checksums.join("\n")).as_bytes()))
+}
+
+/// This function returns a key identifying the data imported into the database on top of the vanilla data: the modded tables and all the locs.
+///
+/// Returns None if any of the files cannot be read, as then we cannot tell if the data changed.
+fn sql_merged_key(vanilla_key: &str, tables: &mut [(RFile, bool)], locs: &mut [(RFile, String)], extra_data: &Option<EncodeableExtraData>) -> Option<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(vanilla_key.as_bytes());
+
+    for (table, _) in tables.iter_mut().filter(|(_, is_vanilla)| !*is_vanilla) {
+        let container_name = table.container_name().clone().unwrap_or_default();
+        hasher.update(format!("\n{}:{}:{}", container_name, table.path_in_container_raw(), file_checksum(table, extra_data)?).as_bytes());
+    }
+
+    for (loc, container_name) in locs.iter_mut() {
+        hasher.update(format!("\n{}:{}:{}", container_name, loc.path_in_container_raw(), file_checksum(loc, extra_data)?).as_bytes());
+    }
+
+    Some(hash_to_string(hasher.finalize().as_slice()))
+}
+
+/// This function returns the checksum of the data of a file, encoding it first if it has been edited in memory.
+fn file_checksum(file: &mut RFile, extra_data: &Option<EncodeableExtraData>) -> Option<String> {
+    if file.load().is_ok() {
+        if let Ok(data) = file.cached() {
+            return Some(content_hash(data));
+        }
+    }
+
+    file.encode(extra_data, false, false, true).ok().flatten().map(|data| content_hash(&data))
+}
+
+/// This function replaces the checksums of the vanilla tables imported into the database.
+fn save_sql_table_checksums(pool: &Pool<SqliteConnectionManager>, checksums: &HashMap<String, String>) -> Result<()> {
+    let mut connection = pool.get()?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(&format!("
+        CREATE TABLE IF NOT EXISTS {0} (path TEXT PRIMARY KEY, checksum TEXT NOT NULL);
+        DELETE FROM {0};
+    ", SQL_CHECKSUMS_TABLE))?;
+
+    {
+        let mut statement = transaction.prepare(&format!("INSERT INTO {} (path, checksum) VALUES (?1, ?2)", SQL_CHECKSUMS_TABLE))?;
+        for (path, checksum) in checksums {
+            statement.execute([path, checksum])?;
+        }
+    }
+
+    transaction.commit()?;
+    Ok(())
+}
+
+/// This function returns the revision of the schema loaded from the provided path, as a hash of its file.
+fn sql_schema_revision(schema_path: &Path) -> String {
+    content_hash(&std::fs::read(schema_path).unwrap_or_default())
+}
+
+/// This function returns the schema revision the provided database was built with, if any.
+fn sql_stored_schema_revision(db_path: &Path) -> Option<String> {
+    let connection = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+    connection.query_row(&format!("SELECT value FROM {} WHERE key = ?1", SQL_METADATA_TABLE), [SQL_SCHEMA_REVISION_KEY], |row| row.get(0)).ok()
+}
+
+/// This function saves the schema revision the database has been built with.
+fn save_sql_schema_revision(pool: &Pool<SqliteConnectionManager>, revision: &str) -> Result<()> {
+    let connection = pool.get()?;
+    connection.execute_batch(&format!("CREATE TABLE IF NOT EXISTS {} (key TEXT PRIMARY KEY, value TEXT NOT NULL);", SQL_METADATA_TABLE))?;
+    connection.execute(&format!("INSERT OR REPLACE INTO {} (key, value) VALUES (?1, ?2)", SQL_METADATA_TABLE), [SQL_SCHEMA_REVISION_KEY, revision])?;
+    Ok(())
+}
+
+/// This function removes from the database the tables with the provided names (like land_units_tables), in all their versions.
+fn drop_sql_tables(pool: &Pool<SqliteConnectionManager>, table_names: &HashSet<String>) -> Result<()> {
+    let connection = pool.get()?;
+    let mut statement = connection.prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?;
+    let names = statement.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    for name in names {
+        let is_match = table_names.iter().any(|table_name| {
+            let short_name = table_name.strip_suffix("_tables").unwrap_or(table_name);
+            [table_name.as_str(), short_name].iter().any(|table_name| match name.strip_prefix(table_name) {
+                Some("") => true,
+                Some(version) => version.strip_prefix("_v").is_some_and(|version| !version.is_empty() && version.chars().all(|character| character.is_ascii_digit())),
+                None => false,
+            })
+        });
+
+        if is_match {
+            connection.execute_batch(&format!("DROP TABLE IF EXISTS \"{}\"", name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// This function executes a batch of SQL statements within a transaction, rolling it back if any of them fails.
+fn execute_in_transaction(pool: &Pool<SqliteConnectionManager>, query: &str) -> Result<()> {
+    let mut connection = pool.get()?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(query)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// This function returns the folders of the tables edited by a SQL query, by looking at the INSERT, REPLACE, UPDATE and DELETE statements in it.
+fn sql_query_tables(query: &str) -> Vec<String> {
+    let words = query.split(|character: char| character.is_whitespace() || character == ';' || character == '(')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut tables = vec![];
+    for (index, word) in words.iter().enumerate() {
+        let table_index = if word.eq_ignore_ascii_case("update") {
+            Some(index + 1)
+        } else if word.eq_ignore_ascii_case("into") || (word.eq_ignore_ascii_case("from") && index > 0 && words[index - 1].eq_ignore_ascii_case("delete")) {
+            Some(index + 1)
+        } else {
+            None
+        };
+
+        if let Some(table_name) = table_index.and_then(|index| words.get(index)) {
+            let table_name = table_name.trim_matches(|character| character == '"' || character == '`' || character == '[' || character == ']');
+            let table_name = table_name.strip_suffix("_tables").unwrap_or(table_name);
+            let folder = format!("db/{}_tables/", table_name);
+            if !tables.contains(&folder) {
+                tables.push(folder);
+            }
+        }
+    }
+
+    tables
+}
+
+/// This function returns the SQL scripts to execute, with their params: first the ones provided one by one, then the ones in the scripts folder, if any.
+fn sql_scripts(cli: &PatchArgs, game: &GameInfo) -> Result<Vec<(PathBuf, Vec<String>)>> {
+    let mut scripts = cli.sql_script.clone().unwrap_or_default();
+    if let Some(ref path) = cli.sql_script_dir {
+        scripts.append(&mut sql_scripts_from_dir(path)?);
+    }
+
+    if let Some(ref presets) = cli.sql_preset {
+
+        // Download the presets. Ignore failure here, as it may fail due to network issues.
+        let local_path = sql_presets_path()?;
+        if !cli.offline {
+            info!("Checking and downloading SQL presets...");
+
+            let _ = update_repo(&local_path, cli.sql_presets_repo.as_deref().unwrap_or(SQL_PRESETS_REPO), SQL_PRESETS_BRANCH, SQL_PRESETS_REMOTE, cli.proxy.as_deref());
+
+            info!("Checking and downloading SQL presets done.");
+        }
+
+        for (name, params) in presets {
+            scripts.push((sql_preset_path(game, name)?, params.to_vec()));
+        }
+    }
+
+    sort_sql_scripts(scripts)
+}
+
+pub fn prepare_script_logging(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack) -> Result<()> {
+    info!("- Enable script logging: {}.", cli.enable_logging);
+
+    if cli.enable_logging {
+        match game.key() {
+            KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => pharaoh::prepare_script_logging(reserved_pack),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_script_logging(reserved_pack),
+            KEY_TROY => troy::prepare_script_logging(reserved_pack),
+            KEY_THREE_KINGDOMS => three_kingdoms::prepare_script_logging(reserved_pack),
+            KEY_WARHAMMER_2 => warhammer_2::prepare_script_logging(reserved_pack),
+            KEY_WARHAMMER => Ok(()),
+            KEY_THRONES_OF_BRITANNIA => thrones::prepare_script_logging(game, reserved_pack, vanilla_pack, modded_pack),
+            KEY_ATTILA => attila::prepare_script_logging(game, reserved_pack, vanilla_pack, modded_pack),
+            KEY_ROME_2 => rome_2::prepare_script_logging(game, reserved_pack, vanilla_pack, modded_pack),
+            KEY_SHOGUN_2 => shogun_2::prepare_script_logging(game, reserved_pack, vanilla_pack, modded_pack),
             KEY_NAPOLEON |
             KEY_EMPIRE => Ok(()),
             _ => Ok(())
@@ -472,7 +1774,7 @@ pub fn prepare_script_logging(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pa
     }
 }
 
-pub fn prepare_skip_intro_videos(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+pub fn prepare_skip_intro_videos(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
     info!("- Skip intro videos: {}.", cli.skip_intro_videos);
 
     if cli.skip_intro_videos {
@@ -496,7 +1798,7 @@ pub fn prepare_skip_intro_videos(cli: &Cli, game: &GameInfo, reserved_pack: &mut
     }
 }
 
-pub fn prepare_trait_limit_removal(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+pub fn prepare_trait_limit_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
     info!("- Remove trait limit: {}.", cli.remove_trait_limit);
 
     if cli.remove_trait_limit {
@@ -520,16 +1822,16 @@ pub fn prepare_trait_limit_removal(cli: &Cli, game: &GameInfo, reserved_pack: &m
     }
 }
 
-pub fn prepare_siege_attacker_removal(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+pub fn prepare_siege_attacker_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
     info!("- Remove Siege Attacker attribute: {}.", cli.remove_siege_attacker);
 
     if cli.remove_siege_attacker {
         match game.key() {
             KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => Ok(()),
-            KEY_WARHAMMER_3 => warhammer_3::prepare_siege_attacker_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
-            KEY_TROY |
-            KEY_THREE_KINGDOMS |
-            KEY_WARHAMMER_2 |
+            KEY_WARHAMMER_3 => remove_siege_attacker(game, reserved_pack, vanilla_pack, modded_pack, schema, &SIEGE_CASTES),
+            KEY_TROY => remove_siege_attacker(game, reserved_pack, vanilla_pack, modded_pack, schema, &SIEGE_CASTES_TROY),
+            KEY_THREE_KINGDOMS => Ok(()),
+            KEY_WARHAMMER_2 => remove_siege_attacker(game, reserved_pack, vanilla_pack, modded_pack, schema, &SIEGE_CASTES),
             KEY_WARHAMMER |
             KEY_THRONES_OF_BRITANNIA |
             KEY_ATTILA |
@@ -544,63 +1846,395 @@ pub fn prepare_siege_attacker_removal(cli: &Cli, game: &GameInfo, reserved_pack:
     }
 }
 
-/// All total war games use the same translation system.
-///
-/// The only particularity is that all games before warhammer 1 need to merge all translations into a localisation.loc file.
-pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, load_order: &[PathBuf], game_path: &Path) -> Result<()> {
-    match &cli.translation_language {
-        Some(language) => info!("- Apply translations fixes and mod translations for language: {}.", language),
-        None => info!("- Do not apply translation fixes and mod translations."),
-    }
+/// This function removes the Siege Attacker attribute from all the units except the ones with the provided castes.
+fn remove_siege_attacker(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, siege_castes: &[&str]) -> Result<()> {
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/main_units_tables/", |data| {
+        let caste_column = data.definition().column_position_by_name("caste");
+        let can_siege_column = data.definition().column_position_by_name("can_siege");
+
+        if let (Some(caste_column), Some(can_siege_column)) = (caste_column, can_siege_column) {
+            for row in data.data_mut() {
+                if let Some(DecodedData::StringU8(caste)) = row.get(caste_column).cloned() {
+                    if !siege_castes.contains(&&*caste) {
+                        if let Some(DecodedData::Boolean(ref mut value)) = row.get_mut(can_siege_column) {
+                            *value = false;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
 
-    // Translation process:
-    // - Pull new translations from the repo.
-    // - Get language from UI.
-    // - Get all the paths for available translations.
-    // - Get all the packs we need to translate, in z-a order, so the last one has priority.
-    // - Make an empty loc to put the translations into.
-    // - Apply the fixes file, if found.
-    // - For each Pack:
-    //   - Check for translations in the local folder.
-    //   - If not found, check for translations in the remote folder.
-    //   - If found in any folder, apply them, or use the english value if there's no translation.
-    //   - If none are found, just add the loc to the end of the translated loc.
-    //
-    // - Pass the translated loc through the optimizer to remove lines that didn't need to be there.
-    //   - If it's an old game, append the vanilla localisation.loc file to the translated file.
-    //   - If it's not an old game, check what lines got optimized and re-add them, but from the vanilla translation, so they overwrite any mod using them.
+pub fn prepare_battle_timer_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Remove battle timer: {}.", cli.remove_battle_timer);
 
-    // TODO: Troy has a weird translation system. Check that it works, and check pharaoh too.
-    if let Some(language) = &cli.translation_language {
+    if cli.remove_battle_timer {
+        let keys: &[&str] = match game.key() {
+            KEY_PHARAOH_DYNASTIES |
+            KEY_PHARAOH |
+            KEY_WARHAMMER_3 |
+            KEY_TROY |
+            KEY_THREE_KINGDOMS |
+            KEY_WARHAMMER_2 |
+            KEY_WARHAMMER => &BATTLE_TIMER_KEYS_WARHAMMER,
+            KEY_THRONES_OF_BRITANNIA |
+            KEY_ATTILA |
+            KEY_ROME_2 => &BATTLE_TIMER_KEYS_ROME_2,
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => return Ok(()),
+            _ => return Ok(()),
+        };
 
-        // Make sure the translations folders exist.
-        DirBuilder::new().recursive(true).create(translations_local_path()?)?;
-        DirBuilder::new().recursive(true).create(translations_remote_path()?)?;
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/_kv_rules_tables/", |data| {
 
-        // Download the translations. Ignore failure here, as it may fail due to network issues.
-        if let Ok(local_path) = translations_remote_path() {
-            info!("Checking and downloading community translations...");
+            // -1 means no time limit.
+            patch_kv_values(data, keys, |value| *value = -1_f32);
+        })?;
+    }
 
-            let git_integration = GitIntegration::new(&local_path, TRANSLATIONS_REPO, TRANSLATIONS_BRANCH, TRANSLATIONS_REMOTE);
-            let _ = git_integration.update_repo();
+    Ok(())
+}
 
-            info!("Checking and downloading community translations done.");
-        }
+pub fn prepare_unlock_all_factions(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Unlock all factions: {}.", cli.unlock_all_factions);
+
+    if cli.unlock_all_factions {
+        let playable_column = match game.key() {
+            KEY_WARHAMMER_3 |
+            KEY_WARHAMMER_2 => PLAYABLE_FACTION_COLUMN,
+            KEY_ROME_2 => rome_2::PLAYABLE_FACTION_COLUMN,
+            _ => return Ok(()),
+        };
+
+        // Rebels break the campaign if played, so they're left as they are.
+        let rebel_factions = rebel_factions(reserved_pack, vanilla_pack, modded_pack, schema)?;
+
+        // Besides the factions table, the factions selectable in each campaign are set in junction tables, which we find through the schema.
+        let mut junction_tables = schema.definitions()
+            .iter()
+            .filter(|(table_name, definitions)| table_name.as_str() != "factions_tables" && definitions.iter().any(|definition| {
+                let fields = definition.fields_processed();
+                fields.iter().any(|field| field.name() == FACTION_COLUMN) && fields.iter().any(|field| field.name() == playable_column)
+            }))
+            .map(|(table_name, _)| format!("db/{}/", table_name))
+            .collect::<Vec<_>>();
+        junction_tables.sort();
 
-        // Get the paths. Local has priority over remote, so it goes first.
-        let mut paths = vec![];
-        if let Ok(path) = translations_local_path() {
-            paths.push(path);
-        }
+        let tables = [("db/factions_tables/".to_owned(), "key")].into_iter()
+            .chain(junction_tables.into_iter().map(|table_folder| (table_folder, FACTION_COLUMN)));
 
-        if let Ok(path) = translations_remote_path() {
-            paths.push(path);
+        for (table_folder, faction_column) in tables {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, &table_folder, |data| {
+                let faction_column = data.definition().column_position_by_name(faction_column);
+                let playable_column = data.definition().column_position_by_name(playable_column);
+
+                if let (Some(faction_column), Some(playable_column)) = (faction_column, playable_column) {
+                    for row in data.data_mut() {
+                        let is_rebel = match row.get(faction_column) {
+                            Some(DecodedData::StringU8(faction)) => rebel_factions.contains(faction),
+                            _ => true,
+                        };
+
+                        if !is_rebel {
+                            if let Some(DecodedData::Boolean(ref mut value)) = row.get_mut(playable_column) {
+                                *value = true;
+                            }
+                        }
+                    }
+                }
+            })?;
         }
+    }
 
-        if !paths.is_empty() {
+    Ok(())
+}
 
-            // If we need to merge the localisation.loc file if found to the translations.
-            let use_old_multilanguage_logic = matches!(game.key(),
+pub fn prepare_agent_caps_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Remove agent caps: {}.", cli.remove_agent_caps);
+
+    if cli.remove_agent_caps {
+        match game.key() {
+            KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => Ok(()),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_agent_caps_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
+            KEY_TROY => Ok(()),
+            KEY_THREE_KINGDOMS => three_kingdoms::prepare_agent_caps_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
+            KEY_WARHAMMER_2 |
+            KEY_WARHAMMER |
+            KEY_THRONES_OF_BRITANNIA |
+            KEY_ATTILA |
+            KEY_ROME_2 |
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => Ok(()),
+            _ => Ok(())
+        }
+    } else {
+        Ok(())
+    }
+}
+
+pub fn prepare_unit_caps_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Remove unit caps: {}.", cli.remove_unit_caps);
+
+    if cli.remove_unit_caps {
+        let columns: &[(&str, &str)] = match game.key() {
+            KEY_WARHAMMER_3 => &warhammer_3::UNIT_CAP_COLUMNS,
+            KEY_PHARAOH_DYNASTIES |
+            KEY_PHARAOH |
+            KEY_TROY => &UNIT_CAP_COLUMNS,
+            _ => &[],
+        };
+
+        for &(table_folder, column) in columns {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, table_folder, |data| {
+                patch_numeric_columns(data, &[column], |_| -1.0);
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_ai_difficulty_normalization(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Normalize AI difficulty: {}.", cli.normalize_ai_difficulty);
+
+    if cli.normalize_ai_difficulty && matches!(game.key(), KEY_WARHAMMER_3 | KEY_THREE_KINGDOMS | KEY_WARHAMMER_2) {
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_difficulty_handicap_effects_tables/", |data| {
+            let human_column = data.definition().column_position_by_name("human");
+            let value_column = data.definition().column_position_by_name("value");
+
+            if let (Some(human_column), Some(value_column)) = (human_column, value_column) {
+                for row in data.data_mut() {
+
+                    // Only touch the effects for the AI. The player ones are what the player chose when picking the difficulty.
+                    if let Some(DecodedData::Boolean(false)) = row.get(human_column) {
+                        if let Some(DecodedData::F32(value)) = row.get_mut(value_column) {
+                            *value = 0.0;
+                        }
+                    }
+                }
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn prepare_supply_lines_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Remove supply lines: {}.", cli.remove_supply_lines);
+
+    if cli.remove_supply_lines && matches!(game.key(), KEY_WARHAMMER_3 | KEY_WARHAMMER_2) {
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/effect_bundles_to_effects_junctions_tables/", |data| {
+            let effect_bundle_column = data.definition().column_position_by_name("effect_bundle_key");
+            let value_column = data.definition().column_position_by_name("value");
+
+            if let (Some(effect_bundle_column), Some(value_column)) = (effect_bundle_column, value_column) {
+                for row in data.data_mut() {
+                    if let Some(DecodedData::StringU8(effect_bundle)) = row.get(effect_bundle_column).cloned() {
+                        if SUPPLY_LINES_EFFECT_BUNDLES.contains(&&*effect_bundle) {
+                            if let Some(DecodedData::F32(value)) = row.get_mut(value_column) {
+                                *value = 0.0;
+                            }
+                        }
+                    }
+                }
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn prepare_attrition_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Remove campaign attrition: {}.", cli.no_attrition);
+
+    if cli.no_attrition {
+        match game.key() {
+            KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => Ok(()),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_attrition_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
+            KEY_TROY |
+            KEY_THREE_KINGDOMS |
+            KEY_WARHAMMER_2 |
+            KEY_WARHAMMER |
+            KEY_THRONES_OF_BRITANNIA => Ok(()),
+            KEY_ATTILA => attila::prepare_attrition_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
+            KEY_ROME_2 |
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => Ok(()),
+            _ => Ok(())
+        }
+    } else {
+        Ok(())
+    }
+}
+
+pub fn prepare_battle_camera_unlock(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Unlock battle camera: {}.", cli.unlock_battle_camera);
+
+    if cli.unlock_battle_camera {
+        let limits: &[(&str, f64)] = match game.key() {
+            KEY_WARHAMMER_3 |
+            KEY_THREE_KINGDOMS |
+            KEY_WARHAMMER_2 => &BATTLE_CAMERA_LIMITS_WARHAMMER,
+            KEY_ATTILA => &BATTLE_CAMERA_LIMITS_ATTILA,
+            _ => return Ok(()),
+        };
+
+        let mut patched = false;
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, BATTLE_CAMERA_TABLE, |data| {
+            for (column, limit) in limits {
+                if data.definition().column_position_by_name(column).is_some() {
+                    patch_numeric_columns(data, &[column], |_| *limit);
+                    patched = true;
+                }
+            }
+        })?;
+
+        if !patched {
+            warn!("  - No {} table with camera limits found in the load order. The battle camera has not been unlocked.", BATTLE_CAMERA_TABLE);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_immortal_characters(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Immortal characters: {}.", cli.immortal_characters);
+
+    if cli.immortal_characters {
+        let (death_keys, death_age_keys): (&[&str], &[&str]) = match game.key() {
+            KEY_THREE_KINGDOMS => (&three_kingdoms::CHARACTER_DEATH_KEYS, &three_kingdoms::CHARACTER_DEATH_AGE_KEYS),
+            KEY_WARHAMMER_3 |
+            KEY_WARHAMMER_2 |
+            KEY_WARHAMMER => (&CHARACTER_DEATH_KEYS, &[]),
+            _ => return Ok(()),
+        };
+
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_variables_tables/", |data| {
+            patch_kv_values(data, death_keys, |value| *value = 0_f32);
+
+            // Push the aging thresholds so far away no character ever reaches them.
+            patch_kv_values(data, death_age_keys, |value| *value = 999_f32);
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn prepare_endgame_removal(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    info!("- Disable endgame: {}.", cli.disable_endgame);
+
+    if cli.disable_endgame {
+        match game.key() {
+            KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => Ok(()),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_endgame_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
+            KEY_TROY |
+            KEY_THREE_KINGDOMS => Ok(()),
+            KEY_WARHAMMER_2 => warhammer_2::prepare_endgame_removal(game, reserved_pack, vanilla_pack, modded_pack, schema),
+            KEY_WARHAMMER |
+            KEY_THRONES_OF_BRITANNIA |
+            KEY_ATTILA |
+            KEY_ROME_2 |
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => Ok(()),
+            _ => Ok(())
+        }
+    } else {
+        Ok(())
+    }
+}
+
+/// All total war games use the same translation system.
+///
+/// The only particularity is that all games before warhammer 1 need to merge all translations into a localisation.loc file.
+pub fn prepare_translations(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, load_order: &[PathBuf], game_path: &Path) -> Result<()> {
+    match &cli.translation_language {
+        Some(language) => info!("- Apply translations fixes and mod translations for language: {}.", language),
+        None => info!("- Do not apply translation fixes and mod translations."),
+    }
+
+    // Translation process:
+    // - Pull new translations from the repo.
+    // - Get language from UI.
+    // - Get all the paths for available translations.
+    // - Get all the packs we need to translate, in z-a order, so the last one has priority.
+    // - Make an empty loc to put the translations into.
+    // - Apply the fixes file, if found.
+    // - For each Pack:
+    //   - Check for translations in the local folder.
+    //   - If not found, check for translations in the remote folder.
+    //   - If found in any folder, apply them, or use the english value if there's no translation.
+    //   - If none are found, just add the loc to the end of the translated loc.
+    //
+    // - Pass the translated loc through the optimizer to remove lines that didn't need to be there.
+    //   - If it's an old game, append the vanilla localisation.loc file to the translated file.
+    //   - If it's not an old game, check what lines got optimized and re-add them, but from the vanilla translation, so they overwrite any mod using them.
+
+    // TODO: Troy has a weird translation system. Check that it works, and check pharaoh too.
+    if let Some(languages) = &cli.translation_language {
+
+        // Multiple languages can be provided as a fallback chain. The first one is the one the game uses.
+        let languages = languages.split(',')
+            .map(|language| language.trim())
+            .filter(|language| !language.is_empty())
+            .collect::<Vec<_>>();
+
+        let language = match languages.first() {
+            Some(language) => *language,
+            None => return Err(anyhow!("No translation language provided.")),
+        };
+
+        if cli.patch_fonts {
+            prepare_font_patches(cli, reserved_pack, vanilla_pack, language)?;
+        }
+
+        // Make sure the translations folders exist.
+        DirBuilder::new().recursive(true).create(translations_local_path()?)?;
+        DirBuilder::new().recursive(true).create(translations_remote_path()?)?;
+
+        // Download the translations. Ignore failure here, as it may fail due to network issues.
+        if let Ok(local_path) = translations_remote_path() {
+            if !cli.offline {
+                info!("Checking and downloading community translations...");
+
+                let _ = update_repo(&local_path, cli.translations_repo.as_deref().unwrap_or(TRANSLATIONS_REPO), TRANSLATIONS_BRANCH, TRANSLATIONS_REMOTE, cli.proxy.as_deref());
+
+                info!("Checking and downloading community translations done.");
+            } else if !local_path.join(game.key()).is_dir() {
+                warn!("Community translations not found in {}, and they cannot be downloaded in offline mode. Only local translations will be used.", local_path.display());
+            }
+        }
+
+        // Get the paths. Custom folders have priority over local, and local has priority over remote, so they go first.
+        let mut paths = cli.translation_dir.clone().unwrap_or_default();
+        if let Ok(path) = translations_local_path() {
+            paths.push(path);
+        }
+
+        if let Ok(path) = translations_remote_path() {
+            paths.push(path);
+        }
+
+        if !paths.is_empty() {
+
+            // If nothing changed since the last run, reuse the translated loc from it. The report needs the full process, so it disables the cache.
+            let cache_key = translation_cache_key(cli, game, game_path, load_order, &paths);
+            if cli.translation_report.is_none() {
+                if let Some(cache) = TranslationCache::load(game, &cache_key) {
+                    info!("Nothing changed since the last translation. Using the cached translation.");
+
+                    let file = cache.to_rfile()?;
+                    reserved_pack.files_mut().insert(cache.path_in_container().to_owned(), file);
+                    return Ok(());
+                }
+            }
+
+            // If we need to merge the localisation.loc file if found to the translations.
+            let use_old_multilanguage_logic = matches!(game.key(),
                 KEY_THRONES_OF_BRITANNIA |
                 KEY_ATTILA |
                 KEY_ROME_2 |
@@ -617,6 +2251,25 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
             let mut base_local_fixes = HashMap::new();
             let mut vanilla_english_loc = None;
 
+            // Malformed fixes files are ignored completely, so they're not half-applied.
+            let fixes_rows = match paths.last() {
+                Some(remote_path) => {
+                    let fixes_loc_path = remote_path.join(format!("{}/{}{}.tsv", game.key(), VANILLA_FIXES_NAME, language));
+                    if fixes_loc_path.is_file() {
+                        match vanilla_fixes_rows(&fixes_loc_path) {
+                            Ok(rows) => rows,
+                            Err(error) => {
+                                warn!("Vanilla fixes file {} ignored, as it's not valid: {}", fixes_loc_path.display(), error);
+                                vec![]
+                            }
+                        }
+                    } else {
+                        vec![]
+                    }
+                }
+                None => vec![],
+            };
+
             if let Some(remote_path) = paths.last() {
                 let vanilla_loc_path = remote_path.join(format!("{}/{}", game.key(), VANILLA_LOC_NAME));
                 if let Ok(mut vanilla_loc) = RFile::tsv_import_from_path(&vanilla_loc_path, &None) {
@@ -624,20 +2277,12 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
                     if let Ok(RFileDecoded::Loc(vloc)) = vanilla_loc.decoded() {
 
                         // If we have a fixes file for the vanilla translation, apply it before everything else.
-                        let fixes_loc_path = remote_path.join(format!("{}/{}{}.tsv", game.key(), VANILLA_FIXES_NAME, language));
-                        if let Ok(mut fixes_loc) = RFile::tsv_import_from_path(&fixes_loc_path, &None) {
-                            let _ = fixes_loc.guess_file_type();
-
-                            if let Ok(RFileDecoded::Loc(fixes_loc)) = fixes_loc.decoded() {
-                                base_local_fixes.extend(
-                                    fixes_loc
-                                        .data()
-                                        .iter()
-                                        .map(|x| (x[0].data_to_string().to_string(), x[1].data_to_string().to_string()))
-                                        .collect::<Vec<_>>(),
-                                );
-                            }
-                        }
+                        base_local_fixes.extend(
+                            fixes_rows
+                                .iter()
+                                .map(|x| (x[0].data_to_string().to_string(), x[1].data_to_string().to_string()))
+                                .collect::<Vec<_>>(),
+                        );
 
                         base_english.extend(
                             vloc.data()
@@ -650,6 +2295,12 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
                 }
             }
 
+            let mut translation_report = cli.translation_report.as_ref().map(|_| TranslationReport::default());
+            let mut machine_translator = match cli.machine_translate {
+                Some(provider) => Some(MachineTranslator::new(provider, language, cli.offline, cli.proxy.as_deref())?),
+                None => None,
+            };
+
             // Workaround: We do not need a whole dependencies for this, just one file with the entire english loc combined.
             // So we initialize an empty dependencies, the manually insert that loc.
             let mut dependencies = Dependencies::default();
@@ -657,8 +2308,23 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
                 dependencies.insert_loc_as_vanilla_loc(vloc.clone());
             }
 
+            // Rows of loc_data added by each Pack, to know where each text came from.
+            let mut pack_ranges = vec![];
+
+            let progress = Progress::new("translation_merge", load_order.len());
             for pack_path in load_order {
+                progress.inc();
+
                 if let Some(ref pack_name) = pack_path.file_name().map(|name| name.to_string_lossy().to_string()) {
+                    let pack_start = loc_data.len();
+
+                    // Packs excluded from translation keep their own locs as they are.
+                    if cli.no_translate_pack.iter().flatten().any(|pattern| glob_match(pattern, pack_name)) {
+                        debug!("  - Pack excluded from translation: {}.", pack_name);
+
+                        continue;
+                    }
+
                     let mut translation_found = false;
 
                     // Use new instead of load. This should update the translation on-the-fly.
@@ -670,41 +2336,105 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
                         });
                     }
 
-                    if let Ok(tr) = PackTranslation::new(&paths, &pack, game.key(), &language, &dependencies, &base_english, &base_local_fixes) {
-                        for tr in tr.translations().values() {
+                    // Locs for our languages shipped within the Pack, like text/db/my_mod_sp.loc, take priority over any other translation.
+                    let mut keys_done = HashSet::new();
+                    {
+                        let mut locs = pack.files_by_type_mut(&[FileType::Loc]);
+                        locs.sort_by(|a, b| a.path_in_container_raw().cmp(b.path_in_container_raw()));
+
+                        for language in &languages {
+                            for file in locs.iter_mut().filter(|loc| embedded_loc_language(loc.path_in_container_raw()) == Some(*language)) {
+                                if let Ok(Some(RFileDecoded::Loc(embedded_loc))) = file.decode(&None, false, true) {
+                                    for row in embedded_loc.data().iter() {
+                                        if keys_done.insert(row[0].data_to_string().to_string()) {
+                                            if let Some(ref mut translation_report) = translation_report {
+                                                translation_report.track(pack_name, &row[0].data_to_string());
+                                            }
+
+                                            loc_data.push(row.clone());
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    if !keys_done.is_empty() {
+                        debug!("  - Embedded translation found for Pack: {}.", pack_name);
+                    }
+
+                    // Load the translations for all the languages in the chain. For each key, the first language with an up to date translation wins.
+                    let translations = languages.iter()
+                        .filter_map(|language| PackTranslation::new(&paths, &pack, game.key(), language, &dependencies, &base_english, &base_local_fixes).ok())
+                        .collect::<Vec<_>>();
+
+                    if !translations.is_empty() {
+                        let mut machine_rows = vec![];
+                        for tr in translations.iter().flat_map(|translation| translation.translations().values()) {
+                            if !keys_done.insert(tr.key().to_owned()) {
+                                continue;
+                            }
+
+                            let translated = translations.iter()
+                                .filter_map(|translation| translation.translations().get(tr.key()))
+                                .find(|tr| !tr.value_translated().is_empty() && !*tr.needs_retranslation());
 
                             // Only add entries for values we actually have translated and up to date.
-                            if !tr.value_translated().is_empty() && !*tr.needs_retranslation() {
+                            if let Some(translated) = translated {
                                 loc_data.push(vec![
-                                    DecodedData::StringU16(tr.key().to_owned()),
-                                    DecodedData::StringU16(tr.value_translated().to_owned()),
+                                    DecodedData::StringU16(translated.key().to_owned()),
+                                    DecodedData::StringU16(translated.value_translated().to_owned()),
                                     DecodedData::Boolean(false),
                                 ]);
+
+                                if let Some(ref mut translation_report) = translation_report {
+                                    translation_report.track(pack_name, tr.key());
+                                }
                             }
 
-                            // If we're in a game with the old logic and there is no translation, add the text in english directly.
-                            else if use_old_multilanguage_logic && !tr.value_original().is_empty() {
-                                loc_data.push(vec![
-                                    DecodedData::StringU16(tr.key().to_owned()),
-                                    DecodedData::StringU16(tr.value_original().to_owned()),
-                                    DecodedData::Boolean(false),
-                                ]);
+                            else {
+                                if let Some(ref mut translation_report) = translation_report {
+                                    translation_report.add(pack_name, tr.key(), "english_fallback", tr.value_original());
+                                }
+
+                                // If we're machine-translating, queue the text so all the Pack's texts go in as few requests as possible.
+                                if machine_translator.is_some() && !tr.value_original().is_empty() {
+                                    machine_rows.push(vec![
+                                        DecodedData::StringU16(tr.key().to_owned()),
+                                        DecodedData::StringU16(tr.value_original().to_owned()),
+                                        DecodedData::Boolean(false),
+                                    ]);
+                                }
+
+                                // If we're in a game with the old logic and there is no translation, add the text in english directly.
+                                else if use_old_multilanguage_logic && !tr.value_original().is_empty() {
+                                    loc_data.push(vec![
+                                        DecodedData::StringU16(tr.key().to_owned()),
+                                        DecodedData::StringU16(tr.value_original().to_owned()),
+                                        DecodedData::Boolean(false),
+                                    ]);
+                                }
                             }
                         }
 
+                        if let Some(ref mut machine_translator) = machine_translator {
+                            machine_translate_rows(machine_translator, &mut machine_rows);
+                            loc_data.append(&mut machine_rows);
+                        }
+
                         translation_found = true;
                     }
 
-                    if cli.verbose && translation_found {
-                        info!("  - Translation found for Pack: {}.", pack_name);
+                    if translation_found {
+                        debug!("  - Translation found for Pack: {}.", pack_name);
                     }
 
                     if !translation_found {
                         let mut locs = pack.files_by_type_mut(&[FileType::Loc]);
 
                         // Some people (SCM Team) decided it was a good idea to put a loc wiping entries outside the text folder.
-                        // This filters out anything not in text/.
-                        locs.retain(|loc| loc.path_in_container_raw().starts_with("text/"));
+                        // This filters out anything not in text/. Locs for other languages are left out too.
+                        locs.retain(|loc| loc.path_in_container_raw().starts_with("text/") && embedded_loc_language(loc.path_in_container_raw()).is_none());
                         locs.sort_by(|a, b| a.path_in_container_raw().cmp(b.path_in_container_raw()));
 
                         let locs_split = locs.iter_mut()
@@ -718,20 +2448,58 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
                         let locs_split_ref = locs_split.iter().collect::<Vec<_>>();
 
                         let mut merged_loc = Loc::merge(&locs_split_ref)?;
+                        merged_loc.data_mut().retain(|row| !keys_done.contains(&*row[0].data_to_string()));
+
+                        if let Some(ref mut translation_report) = translation_report {
+                            for row in merged_loc.data().iter() {
+                                translation_report.add(pack_name, &row[0].data_to_string(), "no_translation", &row[1].data_to_string());
+                            }
+                        }
+
+                        if let Some(ref mut machine_translator) = machine_translator {
+                            machine_translate_rows(machine_translator, merged_loc.data_mut());
+                        }
+
                         loc_data.append(merged_loc.data_mut());
                     }
+
+                    pack_ranges.push((pack_name.to_owned(), pack_start..loc_data.len()));
+                }
+            }
+
+            progress.finish();
+
+            if let Some(ref machine_translator) = machine_translator {
+                if let Err(error) = machine_translator.save() {
+                    warn!("Failed to save the machine translations cache: {}", error);
                 }
             }
 
             // If we have a fixes file for the vanilla translation, apply it before everything else.
-            if let Some(remote_path) = paths.last() {
-                let fixes_loc_path = remote_path.join(format!("{}/{}{}.tsv", game.key(), VANILLA_FIXES_NAME, language));
-                if let Ok(mut fixes_loc) = RFile::tsv_import_from_path(&fixes_loc_path, &None) {
-                    fixes_loc.guess_file_type()?;
-                    if let Ok(Some(RFileDecoded::Loc(fixes_loc))) = fixes_loc.decode(&None, false, true) {
-                        loc_data.append(&mut fixes_loc.data().to_vec());
+            //
+            // As the mod translations go first, they take priority over the fixes. Report it, so it's clear which text the game ends up using.
+            if !fixes_rows.is_empty() {
+                let mut mod_keys = HashMap::new();
+                for (pack_name, range) in &pack_ranges {
+                    for row in &loc_data[range.clone()] {
+                        mod_keys.entry(row[0].data_to_string().to_string()).or_insert(pack_name);
+                    }
+                }
+
+                let mut conflicts = 0;
+                for row in &fixes_rows {
+                    if let Some(pack_name) = mod_keys.get(&*row[0].data_to_string()) {
+                        conflicts += 1;
+
+                        debug!("  - Vanilla fix for key {} overridden by the text from Pack: {}.", row[0].data_to_string(), pack_name);
                     }
                 }
+
+                if conflicts > 0 {
+                    warn!("{} vanilla fixes overridden by texts from mods. Use -v to see them.", conflicts);
+                }
+
+                loc_data.extend(fixes_rows);
             }
 
             // Only needed for modern games.
@@ -744,18 +2512,29 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
             };
 
             // Perform the optimisation BEFORE appending the vanilla loc, if we're appending it. Otherwise we'll lose valid entries.
-            if !loc_data.is_empty() {
+            if !loc_data.is_empty() && !cli.no_translation_optimize {
                 loc.set_data(&loc_data)?;
 
                 let options = OptimizerOptions::default();
                 let _ = !loc.optimize(&mut dependencies, None, &options);
+
+                if let Some(ref mut translation_report) = translation_report {
+                    let keys_post_opt = loc.data()
+                        .iter()
+                        .map(|row| row[0].data_to_string().to_string())
+                        .collect::<HashSet<_>>();
+
+                    for row in loc_data.iter().filter(|row| !keys_post_opt.contains(&*row[0].data_to_string())) {
+                        translation_report.add_dropped(&row[0].data_to_string(), &row[1].data_to_string());
+                    }
+                }
+
                 loc_data = loc.data().to_vec();
             }
 
             // If the game uses the old multilanguage logic, we need to get the most updated version of localisation.loc from the game and append it to our loc.
             if use_old_multilanguage_logic {
-                let mut pack = Pack::read_and_merge_ca_packs(game, game_path)?;
-                if let Some(vanilla_loc) = pack.file_mut(TRANSLATED_PATH_OLD, false) {
+                if let Some(vanilla_loc) = vanilla_pack.file_mut(TRANSLATED_PATH_OLD, false) {
                     if let Ok(Some(RFileDecoded::Loc(mut loc))) = vanilla_loc.decode(&None, false, true) {
                         loc_data.append(loc.data_mut());
                     }
@@ -763,9 +2542,8 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
             }
 
             // If the game is not using the old logic, we need to restore the optimized lines, but from the translated loc, not the english one.
-            else {
-                let mut pack = Pack::read_and_merge_ca_packs(game, game_path)?;
-                let mut vanilla_locs = pack.files_by_type_mut(&[FileType::Loc]);
+            else if !cli.no_translation_optimize {
+                let mut vanilla_locs = vanilla_pack.files_by_type_mut(&[FileType::Loc]);
                 let vanilla_loc_data = vanilla_locs.par_iter_mut()
                     .filter_map(|rfile| {
                         if let Ok(Some(RFileDecoded::Loc(loc))) = rfile.decode(&None, false, true) {
@@ -853,26 +2631,273 @@ pub fn prepare_translations(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack
                     TRANSLATED_PATH.to_string()
                 };
 
+                if let Err(error) = TranslationCache::new(&cache_key, &path, loc.data()).save(game) {
+                    warn!("Failed to save the translation cache: {}", error);
+                }
+
                 let file = RFile::new_from_decoded(&RFileDecoded::Loc(loc), 0, &path);
                 reserved_pack.files_mut().insert(path, file);
             }
+
+            if let (Some(translation_report), Some(report_path)) = (translation_report, &cli.translation_report) {
+                if let Err(error) = translation_report.save(report_path) {
+                    warn!("Failed to save the translation report to {}: {}", report_path.display(), error);
+                }
+            }
         }
     }
 
     Ok(())
 }
 
-pub fn prepare_unit_multiplier(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
-    if let Some(multiplier) = cli.unit_multiplier {
+/// This function exports the texts of each Pack in the load order to a TSV file, so they can be used as a base for new translations.
+///
+/// Only the english texts are exported. Locs for other languages within the Packs are ignored. Returns the amount of Packs exported.
+pub fn export_translations(game: &GameInfo, load_order: &[PathBuf], schema: &Schema, output_path: &Path) -> Result<usize> {
+    let folder = output_path.join(game.key());
+    DirBuilder::new().recursive(true).create(&folder)?;
+
+    let mut exported = 0;
+    for pack_path in load_order {
+        let pack_name = match pack_path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        let mut pack = Pack::read_and_merge(&[pack_path.to_path_buf()], game, true, false, true)?;
+        let mut locs = pack.files_by_type_mut(&[FileType::Loc]);
+        locs.retain(|loc| loc.path_in_container_raw().starts_with("text/") && embedded_loc_language(loc.path_in_container_raw()).is_none());
+        locs.sort_by(|a, b| a.path_in_container_raw().cmp(b.path_in_container_raw()));
+
+        let locs_split = locs.iter_mut()
+            .filter_map(|loc| if let Ok(Some(RFileDecoded::Loc(loc))) = loc.decode(&None, false, true) {
+                Some(loc)
+            } else {
+                None
+            })
+            .collect::<Vec<_>>();
 
-        info!("- Apply unit multiplier (if the game supports it) of: {}.", multiplier);
+        let locs_split_ref = locs_split.iter().collect::<Vec<_>>();
+        let merged_loc = Loc::merge(&locs_split_ref)?;
+        if merged_loc.data().is_empty() {
+            continue;
+        }
 
-        match game.key() {
+        debug!("  - Exporting {} texts from Pack: {}.", merged_loc.data().len(), pack_name);
+
+        let path = format!("text/db/{}.loc", pack_name.strip_suffix(".pack").unwrap_or(&pack_name));
+        let mut file = RFile::new_from_decoded(&RFileDecoded::Loc(merged_loc), 0, &path);
+        file.tsv_export_to_path(&folder.join(format!("{}.tsv", pack_name)), schema, false)?;
+        exported += 1;
+    }
+
+    Ok(exported)
+}
+
+/// This function compares the english texts of the game against the ones the community translations are based on.
+///
+/// The game needs to be in english for this to work, as the vanilla locs are the ones of the language the game is in.
+pub fn vanilla_english_diff(game: &GameInfo, vanilla_pack: &mut Pack, translations_path: &Path) -> Result<VanillaLocDiff> {
+    let vanilla_loc_path = translations_path.join(format!("{}/{}", game.key(), VANILLA_LOC_NAME));
+    let mut hub_loc = RFile::tsv_import_from_path(&vanilla_loc_path, &None)?;
+    hub_loc.guess_file_type()?;
+
+    let old = match hub_loc.decode(&None, false, true)? {
+        Some(RFileDecoded::Loc(hub_loc)) => hub_loc.data()
+            .iter()
+            .map(|row| (row[0].data_to_string().to_string(), row[1].data_to_string().to_string()))
+            .collect::<HashMap<_, _>>(),
+        _ => return Err(anyhow!("{} is not a loc file.", vanilla_loc_path.display())),
+    };
+
+    let mut vanilla_locs = vanilla_pack.files_by_type_mut(&[FileType::Loc]);
+    let new = vanilla_locs.par_iter_mut()
+        .filter_map(|rfile| {
+            if let Ok(Some(RFileDecoded::Loc(loc))) = rfile.decode(&None, false, true) {
+                Some(loc)
+            } else {
+                None
+            }
+        })
+        .map(|loc| loc.data().to_vec())
+        .flatten()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .rev()
+        .map(|row| (row[0].data_to_string().to_string(), row[1].data_to_string().to_string()))
+        .collect::<HashMap<_, _>>();
+
+    Ok(VanillaLocDiff::new(&old, &new))
+}
+
+/// This function reads the rows of a vanilla fixes file, making sure it's a valid loc file without empty or duplicated keys.
+fn vanilla_fixes_rows(path: &Path) -> Result<Vec<Vec<DecodedData>>> {
+    let mut fixes_loc = RFile::tsv_import_from_path(path, &None)?;
+    fixes_loc.guess_file_type()?;
+
+    let rows = match fixes_loc.decode(&None, false, true)? {
+        Some(RFileDecoded::Loc(fixes_loc)) => fixes_loc.data().to_vec(),
+        _ => return Err(anyhow!("The file is not a loc file.")),
+    };
+
+    let mut keys = HashSet::new();
+    for (index, row) in rows.iter().enumerate() {
+        let key = row[0].data_to_string();
+        if key.is_empty() {
+            return Err(anyhow!("Row {} has an empty key.", index + 1));
+        }
+
+        if !keys.insert(key.to_string()) {
+            return Err(anyhow!("Row {} has a duplicated key: {}.", index + 1, key));
+        }
+    }
+
+    Ok(rows)
+}
+
+/// This function returns the language of a loc file, if its name ends with the code of one of the game's languages other than english, like `text/db/my_mod_sp.loc`.
+fn embedded_loc_language(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next()?.strip_suffix(".loc")?;
+    let (_, language) = name.rsplit_once('_')?;
+    LOC_LANGUAGES.iter().find(|code| code.eq_ignore_ascii_case(language)).copied()
+}
+
+/// This function calculates the key of the translation cache, from everything that can change the translated loc.
+fn translation_cache_key(cli: &PatchArgs, game: &GameInfo, game_path: &Path, load_order: &[PathBuf], translation_paths: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    let mut add = |value: &str| {
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    };
+
+    add(game.key());
+    add(&format!("{:?}", game.executable_path(game_path).and_then(|path| modified_time(&path))));
+
+    for pack_path in load_order {
+        add(&pack_path.to_string_lossy());
+        add(&format!("{:?}", modified_time(pack_path)));
+    }
+
+    add(&format!("{:?}", cli.translation_language));
+    add(&format!("{:?}", cli.no_translate_pack));
+    add(&format!("{:?}", cli.no_translation_optimize));
+    add(&format!("{:?}", cli.machine_translate));
+    add(&format!("{:?}", cli.offline));
+
+    // For the translation folders, use the revision of the repo if they're one, and the modification times of the game's files.
+    for path in translation_paths {
+        add(&path.to_string_lossy());
+
+        if let Ok(repo) = Repository::open(path) {
+            add(&format!("{:?}", repo.head().ok().and_then(|head| head.target()).map(|oid| oid.to_string())));
+        }
+
+        let mut files = files_from_subdir(&path.join(game.key()), true).unwrap_or_default();
+        files.sort();
+        for file in &files {
+            add(&file.to_string_lossy());
+            add(&format!("{:?}", modified_time(file)));
+        }
+    }
+
+    hash_to_string(hasher.finalize().as_slice())
+}
+
+/// This function replaces the default fonts of the game with the ones for the provided language, so languages like chinese or korean are shown properly.
+///
+/// The fonts are taken from the game's own data, from files in ui/fonts/ either within a folder named after the language (ui/fonts/cn/x.cuf)
+/// or ending with its code (ui/fonts/x_cn.cuf).
+fn prepare_font_patches(cli: &PatchArgs, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, language: &str) -> Result<()> {
+    if !CJK_LANGUAGES.contains(&language) {
+        info!("- Fonts not patched, as {} doesn't need special fonts.", language);
+        return Ok(());
+    }
+
+    let replacements = vanilla_pack.files()
+        .keys()
+        .filter(|path| path.starts_with(FONTS_PATH))
+        .filter_map(|path| {
+            let (folder, name) = path.rsplit_once('/')?;
+            let default_path = match folder.strip_suffix(&format!("/{}", language)) {
+                Some(folder) => format!("{}/{}", folder, name),
+                None => {
+                    let (stem, extension) = name.rsplit_once('.')?;
+                    let stem = stem.strip_suffix(&format!("_{}", language))?;
+                    format!("{}/{}.{}", folder, stem, extension)
+                }
+            };
+
+            if vanilla_pack.files().contains_key(&default_path) {
+                Some((path.to_owned(), default_path))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if replacements.is_empty() {
+        warn!("Fonts not patched, as no fonts for {} were found in the game data. Make sure the game has the files for that language installed.", language);
+        return Ok(());
+    }
+
+    for (path, default_path) in &replacements {
+        if let Some(file) = vanilla_pack.files_mut().get_mut(path) {
+            if let Some(data) = file.encode(&None, false, false, true)? {
+                debug!("  - Replacing font {} with {}.", default_path, path);
+
+                let file = RFile::new_from_vec(&data, FileType::Unknown, 0, default_path);
+                reserved_pack.files_mut().insert(default_path.to_owned(), file);
+            }
+        }
+    }
+
+    info!("- Fonts patched for language: {}.", language);
+    Ok(())
+}
+
+/// This function machine translates the text column of the provided loc rows. On failure, the rows are left in english.
+fn machine_translate_rows(machine_translator: &mut MachineTranslator, rows: &mut [Vec<DecodedData>]) {
+    let texts = rows.iter()
+        .map(|row| row[1].data_to_string().to_string())
+        .collect::<Vec<_>>();
+
+    match machine_translator.translate(&texts) {
+        Ok(translated) => rows.iter_mut()
+            .zip(translated)
+            .for_each(|(row, text)| row[1] = DecodedData::StringU16(text)),
+        Err(error) => warn!("Failed to machine translate texts, using them in english: {}", error),
+    }
+}
+
+pub fn prepare_unit_multiplier(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(multiplier) = cli.unit_multiplier {
+
+        info!("- Apply unit multiplier (if the game supports it) of: {}.", multiplier);
+
+        let excluded = match cli.unit_multiplier_exclude {
+            Some(ref path) => {
+                let excluded = read_to_string(path)
+                    .map_err(|error| anyhow!("Failed to read the units to exclude from {}: {}", path.display(), error))?
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.to_owned())
+                    .collect::<Vec<_>>();
+
+                info!("- Excluding {} units (or patterns) from the unit multiplier.", excluded.len());
+                excluded
+            }
+            None => vec![],
+        };
+
+        let multiplier = UnitMultiplier::new(multiplier, cli.unit_multiplier_rounding, cli.unit_size_cap, excluded);
+
+        match game.key() {
             KEY_PHARAOH_DYNASTIES |
             KEY_PHARAOH => Ok(()),
-            KEY_WARHAMMER_3 => warhammer_3::prepare_unit_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, multiplier),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_unit_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, &multiplier),
             KEY_TROY => Ok(()),
-            KEY_THREE_KINGDOMS => three_kingdoms::prepare_unit_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, multiplier),
+            KEY_THREE_KINGDOMS => three_kingdoms::prepare_unit_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, &multiplier),
             KEY_WARHAMMER_2 |
             KEY_WARHAMMER |
             KEY_THRONES_OF_BRITANNIA |
@@ -890,7 +2915,317 @@ pub fn prepare_unit_multiplier(cli: &Cli, game: &GameInfo, reserved_pack: &mut P
     }
 }
 
-pub fn prepare_universal_rebalancer(cli: &Cli, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, mod_paths: &[PathBuf]) -> Result<()> {
+/// Unlike the unit multiplier, this changes the amount of units in each garrison, not the size of the units.
+pub fn prepare_garrison_multiplier(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(multiplier) = cli.garrison_multiplier {
+
+        info!("- Apply garrison multiplier (if the game supports it) of: {}.", multiplier);
+
+        if !matches!(game.key(), KEY_SHOGUN_2 | KEY_NAPOLEON | KEY_EMPIRE) {
+            let table_folder = "db/building_level_armed_citizenry_junctions_tables/";
+
+            // New entries need ids not used by any table of the load order, not only by the one being patched.
+            let mut next_id = load_order_max_i32(reserved_pack, vanilla_pack, modded_pack, schema, table_folder, "id")? + 1;
+
+            let mut dec_extra_data = DecodeableExtraData::default();
+            dec_extra_data.set_schema(Some(schema));
+            let dec_extra_data = Some(dec_extra_data);
+
+            // Merge the entries of the load order by id, so each id has the entry the game actually uses. Lower priority tables go first,
+            // so their entries get overwritten by the higher priority ones. Entries from tables with an outdated definition are ignored.
+            let mut template: Option<DB> = None;
+            let mut entries: BTreeMap<i32, Vec<DecodedData>> = BTreeMap::new();
+            for table in load_order_tables(reserved_pack, vanilla_pack, modded_pack, table_folder).iter_mut().rev() {
+                if let Some(RFileDecoded::DB(data)) = table.decode(&dec_extra_data, false, true)? {
+                    if template.as_ref().is_some_and(|template| template.definition().version() != data.definition().version()) {
+                        continue;
+                    }
+
+                    if let Some(id_column) = data.definition().column_position_by_name("id") {
+                        for row in data.data() {
+                            if let Some(DecodedData::I32(id)) = row.get(id_column) {
+                                entries.insert(*id, row.clone());
+                            }
+                        }
+                    }
+
+                    if template.is_none() {
+                        template = Some(data);
+                    }
+                }
+            }
+
+            if let Some(mut template) = template {
+                let building_column = template.definition().column_position_by_name("building_level");
+                let id_column = template.definition().column_position_by_name("id");
+
+                if let (Some(building_column), Some(id_column)) = (building_column, id_column) {
+
+                    // Group the garrison entries by building. Entries without a building are not touched.
+                    let mut groups: BTreeMap<String, Vec<&Vec<DecodedData>>> = BTreeMap::new();
+                    for row in entries.values() {
+                        if let Some(DecodedData::StringU8(building)) = row.get(building_column) {
+                            groups.entry(building.to_owned()).or_default().push(row);
+                        }
+                    }
+
+                    // Then repeat the entries of each building until we get the amount we want. Only the extra entries go to the new table,
+                    // so the existing ones keep coming from wherever they come from.
+                    let mut new_rows = vec![];
+                    for rows in groups.values() {
+                        let target = (rows.len() as f64 * multiplier).round() as usize;
+                        for row in rows.iter().cycle().take(target).skip(rows.len()) {
+                            let mut row = (*row).clone();
+                            row[id_column] = DecodedData::I32(next_id);
+                            next_id += 1;
+
+                            new_rows.push(row);
+                        }
+                    }
+
+                    if !new_rows.is_empty() {
+                        *template.data_mut() = new_rows;
+
+                        let path = format!("{}{}", table_folder, GARRISON_MULTIPLIER_TABLE_NAME);
+                        let mut file = RFile::new_from_decoded(&RFileDecoded::DB(template), 0, &path);
+                        file.encode(&Some(EncodeableExtraData::new_from_game_info(game)), false, true, false)?;
+                        reserved_pack.insert(file)?;
+                    }
+                }
+            }
+        }
+    } else {
+        info!("- Do not apply garrison multiplier.");
+    }
+
+    Ok(())
+}
+
+pub fn prepare_campaign_movement_multiplier(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(multiplier) = cli.campaign_movement_multiplier {
+
+        info!("- Apply campaign movement multiplier (if the game supports it) of: {}.", multiplier);
+
+        // Armies move as far as their general's land unit allows, and agents as far as their own land unit allows.
+        if matches!(game.key(), KEY_WARHAMMER_3 | KEY_THREE_KINGDOMS | KEY_WARHAMMER_2) {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/land_units_tables/", |data| {
+                patch_numeric_columns(data, &["campaign_action_points"], |value| value * multiplier);
+            })?;
+        }
+    } else {
+        info!("- Do not apply campaign movement multiplier.");
+    }
+
+    Ok(())
+}
+
+pub fn prepare_research_and_construction_multipliers(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    let is_supported = !matches!(game.key(), KEY_SHOGUN_2 | KEY_NAPOLEON | KEY_EMPIRE);
+
+    match cli.research_rate_multiplier {
+        Some(multiplier) => {
+            info!("- Apply research rate multiplier (if the game supports it) of: {}.", multiplier);
+
+            // A higher rate means less research points needed per technology.
+            if is_supported {
+                patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/technologies_tables/", |data| {
+                    patch_numeric_columns(data, &["research_points_required"], |value| (value / multiplier).max(1.0));
+                })?;
+            }
+        }
+        None => info!("- Do not apply research rate multiplier."),
+    }
+
+    match cli.construction_time_multiplier {
+        Some(multiplier) => {
+            info!("- Apply construction time multiplier (if the game supports it) of: {}.", multiplier);
+
+            // Buildings need to take at least one turn to build, or the game gets confused.
+            if is_supported {
+                patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/building_levels_tables/", |data| {
+                    patch_numeric_columns(data, &["turns_to_build"], |value| (value * multiplier).round().max(1.0));
+                })?;
+            }
+        }
+        None => info!("- Do not apply construction time multiplier."),
+    }
+
+    Ok(())
+}
+
+pub fn prepare_starting_treasury(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(treasury) = cli.starting_treasury {
+
+        info!("- Set starting treasury (if the game supports it) to: {}.", treasury);
+
+        let column = match game.key() {
+            KEY_PHARAOH_DYNASTIES |
+            KEY_PHARAOH |
+            KEY_WARHAMMER_3 |
+            KEY_TROY |
+            KEY_THREE_KINGDOMS |
+            KEY_WARHAMMER_2 |
+            KEY_WARHAMMER => STARTING_TREASURY_COLUMN_WARHAMMER,
+            KEY_THRONES_OF_BRITANNIA |
+            KEY_ATTILA |
+            KEY_ROME_2 => STARTING_TREASURY_COLUMN_ROME_2,
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => return Ok(()),
+            _ => return Ok(()),
+        };
+
+        // Each difficulty has its own row, and we override all of them so the amount doesn't depend on the difficulty picked.
+        let mut patched = false;
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, STARTING_TREASURY_TABLE, |data| {
+            if data.definition().column_position_by_name(column).is_some() {
+                patch_numeric_columns(data, &[column], |_| treasury as f64);
+                patched = true;
+            }
+        })?;
+
+        if !patched {
+            warn!("  - No {} table with a {} column found in the load order. The starting treasury has not been changed.", STARTING_TREASURY_TABLE, column);
+        }
+    } else {
+        info!("- Do not change starting treasury.");
+    }
+
+    Ok(())
+}
+
+pub fn prepare_upkeep_and_income_multipliers(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    let is_supported = !matches!(game.key(), KEY_SHOGUN_2 | KEY_NAPOLEON | KEY_EMPIRE);
+
+    match cli.upkeep_multiplier {
+        Some(multiplier) => {
+            info!("- Apply upkeep multiplier (if the game supports it) of: {}.", multiplier);
+
+            if is_supported {
+                patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/main_units_tables/", |data| {
+                    patch_numeric_columns(data, &["upkeep_cost"], |value| value * multiplier);
+                })?;
+            }
+        }
+        None => info!("- Do not apply upkeep multiplier."),
+    }
+
+    match cli.income_multiplier {
+        Some(multiplier) => {
+            info!("- Apply income multiplier (if the game supports it) of: {}.", multiplier);
+
+            // Building income is defined as effects. We only touch the ones related to income.
+            if is_supported {
+                patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/building_effects_junction_tables/", |data| {
+                    patch_effect_values(data, &INCOME_EFFECTS, |value| *value *= multiplier as f32);
+                })?;
+            }
+        }
+        None => info!("- Do not apply income multiplier."),
+    }
+
+    Ok(())
+}
+
+pub fn prepare_public_order_and_corruption(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    match cli.public_order_bonus {
+        Some(bonus) => info!("- Apply public order bonus (if the game supports it) of: {}.", bonus),
+        None => info!("- Do not apply public order bonus."),
+    }
+
+    match cli.corruption_multiplier {
+        Some(multiplier) => info!("- Apply corruption multiplier (if the game supports it) of: {}.", multiplier),
+        None => info!("- Do not apply corruption multiplier."),
+    }
+
+    if cli.public_order_bonus.is_some() || cli.corruption_multiplier.is_some() {
+        match game.key() {
+            KEY_PHARAOH_DYNASTIES |
+            KEY_PHARAOH => Ok(()),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_public_order_and_corruption(game, reserved_pack, vanilla_pack, modded_pack, schema, cli.public_order_bonus, cli.corruption_multiplier),
+            KEY_TROY |
+            KEY_THREE_KINGDOMS => Ok(()),
+            KEY_WARHAMMER_2 => warhammer_2::prepare_public_order_and_corruption(game, reserved_pack, vanilla_pack, modded_pack, schema, cli.public_order_bonus, cli.corruption_multiplier),
+            KEY_WARHAMMER |
+            KEY_THRONES_OF_BRITANNIA |
+            KEY_ATTILA |
+            KEY_ROME_2 |
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => Ok(()),
+            _ => Ok(())
+        }
+    } else {
+        Ok(())
+    }
+}
+
+pub fn prepare_xp_multiplier(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(multiplier) = cli.xp_multiplier {
+
+        info!("- Apply experience multiplier (if the game supports it) of: {}.", multiplier);
+
+        match game.key() {
+            KEY_PHARAOH_DYNASTIES |
+            KEY_PHARAOH => Ok(()),
+            KEY_WARHAMMER_3 => warhammer_3::prepare_xp_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, multiplier),
+            KEY_TROY => Ok(()),
+            KEY_THREE_KINGDOMS => three_kingdoms::prepare_xp_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, multiplier),
+            KEY_WARHAMMER_2 => warhammer_2::prepare_xp_multiplier(game, reserved_pack, vanilla_pack, modded_pack, schema, multiplier),
+            KEY_WARHAMMER |
+            KEY_THRONES_OF_BRITANNIA |
+            KEY_ATTILA |
+            KEY_ROME_2 |
+            KEY_SHOGUN_2 |
+            KEY_NAPOLEON |
+            KEY_EMPIRE => Ok(()),
+            _ => Ok(())
+        }
+    } else {
+
+        info!("- Do not apply experience multiplier.");
+        Ok(())
+    }
+}
+
+pub fn prepare_magic_multiplier(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(multiplier) = cli.magic_multiplier {
+
+        info!("- Apply winds of magic multiplier (if the game supports it) of: {}.", multiplier);
+
+        if matches!(game.key(), KEY_WARHAMMER_3 | KEY_WARHAMMER_2 | KEY_WARHAMMER) {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/_kv_winds_of_magic_params_tables/", |data| {
+                patch_kv_values(data, &WINDS_OF_MAGIC_AMOUNT_KEYS, |value| *value *= multiplier as f32);
+
+                // More magic means recharging more often, so intervals go the other way.
+                patch_kv_values(data, &WINDS_OF_MAGIC_INTERVAL_KEYS, |value| *value /= multiplier as f32);
+            })?;
+        }
+    } else {
+        info!("- Do not apply winds of magic multiplier.");
+    }
+
+    Ok(())
+}
+
+pub fn prepare_skill_points_per_level(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    if let Some(skill_points) = cli.skill_points_per_level {
+
+        info!("- Set skill points per level (if the game supports it) to: {}.", skill_points);
+
+        if matches!(game.key(), KEY_PHARAOH_DYNASTIES | KEY_PHARAOH | KEY_WARHAMMER_3 | KEY_TROY | KEY_THREE_KINGDOMS | KEY_WARHAMMER_2) {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/character_experience_skill_tiers_tables/", |data| {
+                patch_numeric_columns(data, &["skill_points"], |_| skill_points as f64);
+            })?;
+        }
+    } else {
+        info!("- Do not change skill points per level.");
+    }
+
+    Ok(())
+}
+
+pub fn prepare_universal_rebalancer(cli: &PatchArgs, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, mod_paths: &[PathBuf]) -> Result<()> {
     if let Some(mod_name) = &cli.universal_rebalancer {
         info!("- Perform a universal rebalancing using the mod {} as base mod.", mod_name);
 
@@ -898,8 +3233,8 @@ pub fn prepare_universal_rebalancer(cli: &Cli, game: &GameInfo, reserved_pack: &
             match game.key() {
                 KEY_PHARAOH | KEY_PHARAOH_DYNASTIES => Ok(()),
                 KEY_WARHAMMER_3 => warhammer_3::prepare_universal_rebalancer(game, reserved_pack, vanilla_pack, modded_pack, schema, mod_path, mod_paths),
-                KEY_TROY |
-                KEY_THREE_KINGDOMS |
+                KEY_TROY => Ok(()),
+                KEY_THREE_KINGDOMS => three_kingdoms::prepare_universal_rebalancer(game, reserved_pack, vanilla_pack, modded_pack, schema, mod_path, mod_paths),
                 KEY_WARHAMMER_2 |
                 KEY_WARHAMMER |
                 KEY_THRONES_OF_BRITANNIA |
@@ -920,6 +3255,285 @@ pub fn prepare_universal_rebalancer(cli: &Cli, game: &GameInfo, reserved_pack: &
     }
 }
 
+/// This function prepends the script logging wrapper to all the campaign entry scripts (scripting.lua) found in the provided folders.
+///
+/// Used by games that do not have a console logging switch.
+pub fn inject_script_logging_wrapper(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, entry_folders: &[&str]) -> Result<()> {
+    let folders = entry_folders.iter().map(|folder| ContainerPath::Folder(folder.to_string())).collect::<Vec<_>>();
+
+    let mut files = vanilla_pack.files_by_type_and_paths(&[FileType::Text], &folders, true)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    files.append(&mut modded_pack.files_by_type_and_paths(&[FileType::Text], &folders, true)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>());
+
+    files.append(&mut reserved_pack.files_by_type_and_paths(&[FileType::Text], &folders, true)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>());
+
+    // Only the entry scripts need the wrapper. Everything else is loaded from them.
+    files.retain(|rfile| rfile.path_in_container_raw().ends_with("/scripting.lua"));
+
+    // Sort them so file processing is done in the correct order.
+    files.sort_by_key(|rfile| rfile.path_in_container_raw().to_string());
+
+    if files.is_empty() {
+        warn!("  - No campaign scripts found to inject the logging wrapper into. Script logging will not be enabled.");
+        return Ok(());
+    }
+
+    let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
+    let dec_extra_data = Some(DecodeableExtraData::default());
+
+    for file in &mut files {
+        if let Ok(Some(RFileDecoded::Text(mut data))) = file.decode(&dec_extra_data, false, true) {
+
+            // Do not wrap twice files another step already wrapped.
+            if !data.contents().starts_with(SCRIPT_LOGGING_WRAPPER_MARKER) {
+                data.set_contents(SCRIPT_LOGGING_WRAPPER.to_owned() + data.contents());
+
+                file.set_decoded(RFileDecoded::Text(data))?;
+                file.encode(&enc_extra_data, false, true, false)?;
+                reserved_pack.insert(file.clone())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// This function returns all the tables within the provided folder from the vanilla, modded and reserved packs, sorted so the
+/// ones processed last are the ones that take priority in the load order.
+pub fn load_order_tables(reserved_pack: &Pack, vanilla_pack: &Pack, modded_pack: &Pack, table_folder: &str) -> Vec<RFile> {
+    let mut tables = vanilla_pack.files_by_path(&ContainerPath::Folder(table_folder.to_string()), true)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // Give the daracores extreme low priority so they don't overwrite other mods tables.
+    tables.iter_mut().for_each(rename_file_name_to_low_priority);
+
+    tables.append(&mut modded_pack.files_by_path(&ContainerPath::Folder(table_folder.to_string()), true)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>());
+
+    // Just in case another step of the launch process adds this table.
+    tables.append(&mut reserved_pack.files_by_path(&ContainerPath::Folder(table_folder.to_string()), true)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>());
+
+    // Sort them so file processing is done in the correct order.
+    tables.sort_by_key(|rfile| rfile.path_in_container_raw().to_string());
+    tables
+}
+
+/// This function decodes all the tables within the provided folder across the load order, passes them through the provided
+/// edit function, then re-encodes them and adds them to the reserved pack. Tables the edit function doesn't change are not added.
+pub fn patch_load_order_tables<F>(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema, table_folder: &str, mut edit: F) -> Result<()>
+    where F: FnMut(&mut DB) {
+
+    let mut tables = load_order_tables(reserved_pack, vanilla_pack, modded_pack, table_folder);
+
+    let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
+    let mut dec_extra_data = DecodeableExtraData::default();
+    dec_extra_data.set_schema(Some(schema));
+    let dec_extra_data = Some(dec_extra_data);
+
+    for table in &mut tables {
+        if let Some(RFileDecoded::DB(mut data)) = table.decode(&dec_extra_data, false, true)? {
+            let original_data = data.data().to_vec();
+            edit(&mut data);
+
+            // Do not override tables we didn't change, so they don't take priority over other tables for no reason.
+            if *data.data() == original_data {
+                continue;
+            }
+
+            table.set_decoded(RFileDecoded::DB(data))?;
+            table.encode(&enc_extra_data, false, true, false)?;
+            reserved_pack.insert(table.clone())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// This function returns the highest value of an integer column across all the tables within the provided folder in the load order.
+pub fn load_order_max_i32(reserved_pack: &Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema, table_folder: &str, column_name: &str) -> Result<i32> {
+    let mut dec_extra_data = DecodeableExtraData::default();
+    dec_extra_data.set_schema(Some(schema));
+    let dec_extra_data = Some(dec_extra_data);
+
+    let mut max = 0;
+    for table in &mut load_order_tables(reserved_pack, vanilla_pack, modded_pack, table_folder) {
+        if let Some(RFileDecoded::DB(data)) = table.decode(&dec_extra_data, false, true)? {
+            if let Some(column) = data.definition().column_position_by_name(column_name) {
+                for row in data.data().iter() {
+                    if let Some(DecodedData::I32(value)) = row.get(column) {
+                        max = max.max(*value);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(max)
+}
+
+/// This function returns the keys of the factions marked as rebels in the factions tables of the load order.
+fn rebel_factions(reserved_pack: &Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema) -> Result<HashSet<String>> {
+    let mut dec_extra_data = DecodeableExtraData::default();
+    dec_extra_data.set_schema(Some(schema));
+    let dec_extra_data = Some(dec_extra_data);
+
+    // Tables are sorted from higher to lower priority, so we go through them backwards to let the higher ones override the rest.
+    let mut rebel_factions = HashSet::new();
+    for table in load_order_tables(reserved_pack, vanilla_pack, modded_pack, "db/factions_tables/").iter_mut().rev() {
+        if let Some(RFileDecoded::DB(data)) = table.decode(&dec_extra_data, false, true)? {
+            let key_column = data.definition().column_position_by_name("key");
+            let is_rebel_column = data.definition().column_position_by_name("is_rebel");
+
+            if let (Some(key_column), Some(is_rebel_column)) = (key_column, is_rebel_column) {
+                for row in data.data().iter() {
+                    if let Some(DecodedData::StringU8(key)) = row.get(key_column) {
+                        if matches!(row.get(is_rebel_column), Some(DecodedData::Boolean(true))) {
+                            rebel_factions.insert(key.to_owned());
+                        } else {
+                            rebel_factions.remove(key);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(rebel_factions)
+}
+
+/// This function edits the value of all the rows of a key-value table with keys matching any of the provided patterns.
+pub fn patch_kv_values<F>(data: &mut DB, key_patterns: &[&str], mut edit: F)
+    where F: FnMut(&mut f32) {
+
+    for row in data.data_mut() {
+        if let Some(DecodedData::StringU8(key)) = row.first().cloned() {
+            if key_patterns.iter().any(|pattern| glob_match(pattern, &key)) {
+                if let Some(DecodedData::F32(value)) = row.get_mut(1) {
+                    edit(value);
+                }
+            }
+        }
+    }
+}
+
+/// This function edits the values of all the rows of an effects table with effects matching any of the provided patterns.
+///
+/// It works both with building effects (`effect` column) and effect bundles (`effect_key` column).
+pub fn patch_effect_values<F>(data: &mut DB, effect_patterns: &[&str], mut edit: F)
+    where F: FnMut(&mut f32) {
+
+    let effect_column = data.definition().column_position_by_name("effect")
+        .or_else(|| data.definition().column_position_by_name("effect_key"));
+    let value_columns = ["value", "value_damaged", "value_ruined"].iter()
+        .filter_map(|column| data.definition().column_position_by_name(column))
+        .collect::<Vec<_>>();
+
+    if let Some(effect_column) = effect_column {
+        for row in data.data_mut() {
+            if let Some(DecodedData::StringU8(effect)) = row.get(effect_column).cloned() {
+                if effect_patterns.iter().any(|pattern| glob_match(pattern, &effect)) {
+                    for column in &value_columns {
+                        if let Some(DecodedData::F32(value)) = row.get_mut(*column) {
+                            edit(value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// This function adds the provided effect to the player on all campaign difficulties of a campaign_difficulty_handicap_effects table.
+///
+/// If the player already has the effect on a difficulty, the value is added to the existing one.
+pub fn add_player_handicap_effect(data: &mut DB, effect: &str, value: f32) {
+    let handicap_column = data.definition().column_position_by_name("campaign_difficulty_handicap");
+    let effect_column = data.definition().column_position_by_name("effect");
+    let human_column = data.definition().column_position_by_name("human");
+    let value_column = data.definition().column_position_by_name("value");
+
+    if let (Some(handicap_column), Some(effect_column), Some(human_column), Some(value_column)) = (handicap_column, effect_column, human_column, value_column) {
+        let mut patched_handicaps = HashSet::new();
+
+        // First, add the value to the difficulties that already have the effect.
+        for row in data.data_mut() {
+            if let Some(DecodedData::Boolean(true)) = row.get(human_column) {
+                if let Some(DecodedData::StringU8(row_effect)) = row.get(effect_column) {
+                    if row_effect == effect {
+                        if let Some(DecodedData::StringU8(handicap)) = row.get(handicap_column).cloned() {
+                            if let Some(DecodedData::F32(row_value)) = row.get_mut(value_column) {
+                                *row_value += value;
+                                patched_handicaps.insert(handicap);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Then, clone a player row of each remaining difficulty to add the effect to it.
+        let mut new_rows = vec![];
+        for row in data.data() {
+            if let Some(DecodedData::Boolean(true)) = row.get(human_column) {
+                if let Some(DecodedData::StringU8(handicap)) = row.get(handicap_column) {
+                    if patched_handicaps.insert(handicap.to_owned()) {
+                        let mut new_row = row.clone();
+                        new_row[effect_column] = DecodedData::StringU8(effect.to_owned());
+                        new_row[value_column] = DecodedData::F32(value);
+                        new_rows.push(new_row);
+                    }
+                }
+            }
+        }
+
+        data.data_mut().extend(new_rows);
+    }
+}
+
+/// This function edits the value of all numeric columns of a table with names matching any of the provided patterns.
+///
+/// Integer columns are rounded after the edit.
+pub fn patch_numeric_columns<F>(data: &mut DB, column_patterns: &[&str], mut edit: F)
+    where F: FnMut(f64) -> f64 {
+
+    let columns = data.definition()
+        .fields_processed()
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| column_patterns.iter().any(|pattern| glob_match(pattern, field.name())))
+        .map(|(column, _)| column)
+        .collect::<Vec<_>>();
+
+    for row in data.data_mut() {
+        for column in &columns {
+            match row.get_mut(*column) {
+                Some(DecodedData::I16(value)) => *value = edit(*value as f64).round() as i16,
+                Some(DecodedData::I32(value)) => *value = edit(*value as f64).round() as i32,
+                Some(DecodedData::I64(value)) => *value = edit(*value as f64).round() as i64,
+                Some(DecodedData::F32(value)) => *value = edit(*value as f64) as f32,
+                Some(DecodedData::F64(value)) => *value = edit(*value),
+                _ => {}
+            }
+        }
+    }
+}
+
 pub fn rename_file_name_to_low_priority(file: &mut RFile) {
     let mut path = file.path_in_container_raw().split('/').map(|x| x.to_owned()).collect::<Vec<_>>();
 
@@ -929,3 +3543,70 @@ pub fn rename_file_name_to_low_priority(file: &mut RFile) {
 
     file.set_path_in_container_raw(&path.join("/"));
 }
+
+//---------------------------------------------------------------------------//
+//                                  Tests
+//---------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    /// This function writes the provided scripts to a temp folder, returning them in the format used by the SQL scripts argument.
+    fn sql_scripts(folder: &str, scripts: &[(&str, &str)]) -> Vec<(PathBuf, Vec<String>)> {
+        let folder = std::env::temp_dir().join("twpatcher_tests").join(folder);
+        DirBuilder::new().recursive(true).create(&folder).unwrap();
+
+        scripts.iter()
+            .map(|(name, contents)| {
+                let path = folder.join(name);
+                write(&path, contents).unwrap();
+                (path, vec![])
+            })
+            .collect()
+    }
+
+    fn sorted_names(scripts: Vec<(PathBuf, Vec<String>)>) -> Vec<String> {
+        scripts.iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn sort_sql_scripts_by_dependencies() {
+        let scripts = sql_scripts("sort_sql_scripts_by_dependencies", &[
+            ("c.sql", "-- depends_on: b_script\nSELECT 1;"),
+            ("b.sql", "-- id: b_script\n-- depends_on: a\nSELECT 1;"),
+            ("a.sql", "SELECT 1;"),
+            ("d.sql", "SELECT 1;"),
+        ]);
+
+        assert_eq!(sorted_names(sort_sql_scripts(scripts).unwrap()), vec!["a.sql", "b.sql", "c.sql", "d.sql"]);
+    }
+
+    #[test]
+    fn sort_sql_scripts_keeps_order() {
+        let scripts = sql_scripts("sort_sql_scripts_keeps_order", &[
+            ("b.sql", "SELECT 1;"),
+            ("a.sql", "SELECT 1;"),
+        ]);
+
+        assert_eq!(sorted_names(sort_sql_scripts(scripts).unwrap()), vec!["b.sql", "a.sql"]);
+    }
+
+    #[test]
+    fn sort_sql_scripts_errors() {
+        let scripts = sql_scripts("sort_sql_scripts_missing", &[
+            ("a.sql", "-- depends_on: missing\nSELECT 1;"),
+        ]);
+        assert!(sort_sql_scripts(scripts).is_err());
+
+        let scripts = sql_scripts("sort_sql_scripts_cycle", &[
+            ("a.sql", "-- depends_on: b\nSELECT 1;"),
+            ("b.sql", "-- depends_on: a\nSELECT 1;"),
+        ]);
+        assert!(sort_sql_scripts(scripts).is_err());
+    }
+}
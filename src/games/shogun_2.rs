@@ -11,8 +11,15 @@
 use anyhow::Result;
 
 use rpfm_lib::files::{FileType, pack::Pack, RFile};
+use rpfm_lib::games::GameInfo;
 
-use super::EMPTY_BIK;
+use super::{EMPTY_BIK, inject_script_logging_wrapper};
+
+// Folders where the campaign entry scripts are.
+const SCRIPT_ENTRY_FOLDERS: [&str; 2] = [
+    "campaigns/",
+    "script/campaign/",
+];
 
 const INTRO_MOVIE_PATHS_BY_GAME: [&str; 3] = [
     "movies/intel_logo_english.bik",
@@ -24,6 +31,10 @@ const INTRO_MOVIE_PATHS_BY_GAME: [&str; 3] = [
 //                             Implementations
 //-------------------------------------------------------------------------------//
 
+pub fn prepare_script_logging(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack) -> Result<()> {
+    inject_script_logging_wrapper(game, reserved_pack, vanilla_pack, modded_pack, &SCRIPT_ENTRY_FOLDERS)
+}
+
 pub fn prepare_skip_intro_videos(reserved_pack: &mut Pack) -> Result<()> {
     for path in INTRO_MOVIE_PATHS_BY_GAME {
         let file = RFile::new_from_vec(&EMPTY_BIK, FileType::Video, 0, path);
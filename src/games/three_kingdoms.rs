@@ -12,23 +12,68 @@ use anyhow::Result;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use rpfm_lib::schema::Schema;
 use rpfm_lib::files::{Container, ContainerPath, DecodeableExtraData, EncodeableExtraData, FileType, pack::Pack, RFile, RFileDecoded, table::DecodedData};
 use rpfm_lib::games::GameInfo;
 
-use super::{EMPTY_CA_VP8, rename_file_name_to_low_priority};
+use super::{EMPTY_CA_VP8, patch_kv_values, patch_load_order_tables, patch_numeric_columns, rename_file_name_to_low_priority, UnitMultiplier, warhammer_3};
+
+const SCRIPT_DEBUG_ACTIVATOR_PATH: &str = "script/enable_console_logging";
 
 const INTRO_MOVIE_PATHS_BY_GAME: [&str; 2] = [
     "movies/startup_movie_01.ca_vp8",
     "movies/startup_movie_02.ca_vp8",
 ];
 
+// Keys in campaign_variables controlling how many characters a faction can have recruited and deployed.
+const AGENT_CAP_KEYS: [&str; 2] = [
+    "max_characters_recruited",
+    "max_characters_deployed",
+];
+
+// Columns of character_actions with the turns a character has to wait before repeating an action.
+const CHARACTER_ACTION_COOLDOWN_COLUMNS: [&str; 1] = [
+    "cooldown",
+];
+
+// Keys in campaign_variables controlling the chance of characters dying of old age or after battles.
+pub const CHARACTER_DEATH_KEYS: [&str; 2] = [
+    "character_death_chance_after_battle",
+    "character_natural_death_chance",
+];
+
+// Keys in campaign_variables controlling the age at which characters start to die of old age.
+pub const CHARACTER_DEATH_AGE_KEYS: [&str; 1] = [
+    "character_natural_death_min_age",
+];
+
 //-------------------------------------------------------------------------------//
 //                             Implementations
 //-------------------------------------------------------------------------------//
 
-pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, unit_multiplier: f64) -> Result<()> {
+pub fn prepare_xp_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, xp_multiplier: f64) -> Result<()> {
+
+    // Ranks here are tied to a lot of things, so instead of touching the thresholds we increase the experience gained on each event.
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/experience_triggers_tables/", |data| {
+        patch_numeric_columns(data, &["experience"], |value| value * xp_multiplier);
+    })
+}
+
+pub fn prepare_agent_caps_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_variables_tables/", |data| {
+        patch_kv_values(data, &AGENT_CAP_KEYS, |value| *value = 999_f32);
+    })?;
+
+    // Action cooldowns.
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/character_actions_tables/", |data| {
+        patch_numeric_columns(data, &CHARACTER_ACTION_COOLDOWN_COLUMNS, |_| 0.0);
+    })
+}
+
+pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, multiplier: &UnitMultiplier) -> Result<()> {
+    let unit_multiplier = multiplier.multiplier();
 
     let mut kv_key_buildings = vanilla_pack.files_by_path(&ContainerPath::Folder("db/_kv_key_buildings_tables/".to_string()), true)
         .into_iter()
@@ -204,9 +249,25 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
             let num_composed_entities_column = data.definition().column_position_by_name("num_composed_entities");
             let hp_pool_column = data.definition().column_position_by_name("hp_pool");
 
+            // Count the men of each unit first, so the size cap applies to the whole unit, not to each of its entities.
             let mut key_count: HashMap<String, i32> = HashMap::new();
+            if let (Some(land_unit_column), Some(num_composed_entities_column)) = (land_unit_column, num_composed_entities_column) {
+                for row in data.data() {
+                    if let (Some(DecodedData::StringU8(land_unit)), Some(DecodedData::I32(value))) = (row.get(land_unit_column), row.get(num_composed_entities_column)) {
+                        *key_count.entry(land_unit.to_owned()).or_default() += *value;
+                    }
+                }
+            }
+
             for row in data.data_mut() {
 
+                // Skip the units the user doesn't want scaled.
+                if let Some(DecodedData::StringU8(land_unit)) = land_unit_column.and_then(|column| row.get(column)) {
+                    if multiplier.is_excluded(land_unit) {
+                        continue;
+                    }
+                }
+
                 if let Some(land_unit_column) = land_unit_column {
                     if let Some(DecodedData::StringU8(land_unit)) = row.get(land_unit_column).cloned() {
 
@@ -214,15 +275,10 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                         if let Some(column) = num_composed_entities_column {
                             if let Some(DecodedData::I32(value)) = row.get_mut(column) {
 
-                                // Add them to the list.
-                                match key_count.get_mut(&land_unit) {
-                                    Some(data) => *data += *value,
-                                    None => { key_count.insert(land_unit.to_string(), *value); }
-                                }
-
                                 // Ignore single units (heroes and unit captains).
                                 if *value > 1 {
-                                    *value = (*value as f64 * unit_multiplier).round() as i32;
+                                    let count = key_count.get(&land_unit).copied().unwrap_or(*value);
+                                    *value = multiplier.scale_size(count) * *value / count;
                                 }
                             }
                         }
@@ -232,7 +288,7 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                 // HP pool of the whole unit.
                 if let Some(column) = hp_pool_column {
                     if let Some(DecodedData::I32(value)) = row.get_mut(column) {
-                        *value = (*value as f64 * unit_multiplier).round() as i32;
+                        *value = multiplier.scale(*value);
                     }
                 }
             }
@@ -262,10 +318,10 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                 if let Some(key_column) = key_column {
                     if let Some(DecodedData::StringU8(key_value)) = row.get(key_column).cloned() {
 
-                        if !single_entity_units.contains(&key_value) {
+                        if !single_entity_units.contains(&key_value) && !multiplier.is_excluded(&key_value) {
                             if let Some(column) = rank_depth_column {
                                 if let Some(DecodedData::I32(value)) = row.get_mut(column) {
-                                    *value = (*value as f64 * unit_multiplier).round() as i32;
+                                    *value = multiplier.scale(*value);
                                 }
                             }
                         }
@@ -283,6 +339,13 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
     Ok(())
 }
 
+pub fn prepare_script_logging(reserved_pack: &mut Pack) -> Result<()> {
+    let file = RFile::new_from_vec("why not working?!!".as_bytes(), FileType::Text, 0, SCRIPT_DEBUG_ACTIVATOR_PATH);
+    reserved_pack.files_mut().insert(SCRIPT_DEBUG_ACTIVATOR_PATH.to_string(), file);
+
+    Ok(())
+}
+
 pub fn prepare_skip_intro_videos(reserved_pack: &mut Pack) -> Result<()> {
     for path in INTRO_MOVIE_PATHS_BY_GAME {
         let file = RFile::new_from_vec(&EMPTY_CA_VP8, FileType::Video, 0, path);
@@ -291,3 +354,9 @@ pub fn prepare_skip_intro_videos(reserved_pack: &mut Pack) -> Result<()> {
 
     Ok(())
 }
+
+/// Three Kingdoms land units share the stat columns the Warhammer 3 rebalancer works with, and units are tied to
+/// cultures through the same faction/subculture tables, so we reuse its logic. Columns missing in this game are just skipped.
+pub fn prepare_universal_rebalancer(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, base_mod: &Path, mod_paths: &[PathBuf]) -> Result<()> {
+    warhammer_3::prepare_universal_rebalancer(game, reserved_pack, vanilla_pack, modded_pack, schema, base_mod, mod_paths)
+}
@@ -10,9 +10,11 @@
 
 use anyhow::Result;
 
+use rpfm_lib::schema::Schema;
 use rpfm_lib::files::{FileType, pack::Pack, RFile};
+use rpfm_lib::games::GameInfo;
 
-use super::EMPTY_CA_VP8;
+use super::{EMPTY_CA_VP8, add_player_handicap_effect, patch_effect_values, patch_kv_values, patch_load_order_tables, patch_numeric_columns};
 
 const SCRIPT_DEBUG_ACTIVATOR_PATH: &str = "script/enable_console_logging";
 
@@ -22,10 +24,68 @@ const INTRO_MOVIE_PATHS_BY_GAME: [&str; 3] = [
     "movies/startup_movie_03.ca_vp8",
 ];
 
+// Keys in campaign_variables with the earliest turns the chaos invasion and the endgame events can be triggered on.
+// Only the trigger turns are touched: the rest of the invasion variables (army counts, strength, etc.) are left as they are.
+const ENDGAME_TRIGGER_KEYS: [&str; 4] = [
+    "chaos_invasion_start_turn_min",
+    "chaos_invasion_start_turn_max",
+    "end_game_start_turn_min",
+    "end_game_start_turn_max",
+];
+
+// Effect granting public order to all the provinces of a faction.
+const PUBLIC_ORDER_EFFECT: &str = "wh_main_effect_public_order_global";
+
+// Effects adding corruption of each type to the provinces around buildings and characters. Effects reducing corruption are not touched.
+const CORRUPTION_EFFECTS: [&str; 6] = [
+    "wh_main_effect_corruption_chaos_building_base",
+    "wh_main_effect_corruption_chaos_characters",
+    "wh_main_effect_corruption_vampiric_building_base",
+    "wh_main_effect_corruption_vampiric_characters",
+    "wh2_main_effect_corruption_skaven_building_base",
+    "wh2_main_effect_corruption_skaven_characters",
+];
+
 //-------------------------------------------------------------------------------//
 //                             Implementations
 //-------------------------------------------------------------------------------//
 
+pub fn prepare_public_order_and_corruption(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, public_order_bonus: Option<i32>, corruption_multiplier: Option<f64>) -> Result<()> {
+
+    // Public order is given to the player as an extra effect on every difficulty.
+    if let Some(bonus) = public_order_bonus {
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_difficulty_handicap_effects_tables/", |data| {
+            add_player_handicap_effect(data, PUBLIC_ORDER_EFFECT, bonus as f32);
+        })?;
+    }
+
+    if let Some(multiplier) = corruption_multiplier {
+        for table_folder in ["db/building_effects_junction_tables/", "db/effect_bundles_to_effects_junctions_tables/"] {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, table_folder, |data| {
+                patch_effect_values(data, &CORRUPTION_EFFECTS, |value| *value *= multiplier as f32);
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_endgame_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_variables_tables/", |data| {
+
+        // Push the triggers to a turn no campaign ever reaches.
+        patch_kv_values(data, &ENDGAME_TRIGGER_KEYS, |value| *value = 9999_f32);
+    })
+}
+
+pub fn prepare_xp_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, xp_multiplier: f64) -> Result<()> {
+
+    // Here we lower the experience each rank needs, so a higher multiplier means faster levelling.
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/character_experience_skill_tiers_tables/", |data| {
+        patch_numeric_columns(data, &["experience"], |value| value / xp_multiplier);
+    })
+}
+
 pub fn prepare_script_logging(reserved_pack: &mut Pack) -> Result<()> {
     let file = RFile::new_from_vec("why not working?!!".as_bytes(), FileType::Text, 0, SCRIPT_DEBUG_ACTIVATOR_PATH);
     reserved_pack.files_mut().insert(SCRIPT_DEBUG_ACTIVATOR_PATH.to_string(), file);
@@ -19,7 +19,7 @@ use rpfm_lib::schema::Schema;
 use rpfm_lib::files::{Container, ContainerPath, db::DB, DecodeableExtraData, EncodeableExtraData, FileType, pack::Pack, RFile, RFileDecoded, table::DecodedData};
 use rpfm_lib::games::GameInfo;
 
-use super::{EMPTY_CA_VP8, rename_file_name_to_low_priority};
+use super::{EMPTY_CA_VP8, add_player_handicap_effect, patch_effect_values, patch_kv_values, patch_load_order_tables, patch_numeric_columns, rename_file_name_to_low_priority, UnitMultiplier};
 
 const SCRIPT_DEBUG_ACTIVATOR_PATH: &str = "script/enable_console_logging";
 
@@ -45,6 +45,61 @@ const INTRO_MOVIE_PATHS_BY_GAME: [&str; 19] = [
     "movies/startup_movie_05.ca_vp8",
 ];
 
+// Keys in campaign_variables controlling how many agents of each type a faction can have.
+const AGENT_CAP_KEYS: [&str; 1] = [
+    "max_agents_per_type",
+];
+
+// Columns of agent_actions with the turns an agent has to wait before repeating an action.
+const AGENT_ACTION_COOLDOWN_COLUMNS: [&str; 1] = [
+    "cooldown",
+];
+
+// Tables and columns limiting how many units of a type can be recruited. -1 means no limit.
+pub const UNIT_CAP_COLUMNS: [(&str, &str); 2] = [
+    ("db/main_units_tables/", "campaign_cap"),
+    ("db/units_to_groupings_military_permissions_tables/", "military_group_cap"),
+];
+
+// Tables and columns defining how much damage attrition does to armies.
+const ATTRITION_DAMAGE_COLUMNS: [(&str, &str); 1] = [
+    ("db/attrition_types_tables/", "damage"),
+];
+
+// Table controlling when the endgame scenarios are triggered, and which one gets picked.
+const ENDGAME_SCENARIOS_TABLE: &str = "db/campaign_endgame_scenarios_tables/";
+
+// Columns of the endgame scenarios table with the earliest turn a scenario can be triggered on.
+const ENDGAME_TURN_COLUMNS: [&str; 1] = [
+    "min_turn",
+];
+
+// Columns of the endgame scenarios table with the chance of a scenario being picked.
+const ENDGAME_WEIGHT_COLUMNS: [&str; 1] = [
+    "weight",
+];
+
+// Effect granting public order to all the provinces of a faction.
+const PUBLIC_ORDER_EFFECT: &str = "wh_main_effect_public_order_global";
+
+// Effects adding corruption of each type to the provinces around buildings and characters. Effects reducing corruption are not touched.
+const CORRUPTION_EFFECTS: [&str; 14] = [
+    "wh_main_effect_corruption_chaos_building_base",
+    "wh_main_effect_corruption_chaos_characters",
+    "wh_main_effect_corruption_vampiric_building_base",
+    "wh_main_effect_corruption_vampiric_characters",
+    "wh2_main_effect_corruption_skaven_building_base",
+    "wh2_main_effect_corruption_skaven_characters",
+    "wh3_main_effect_corruption_khorne_building_base",
+    "wh3_main_effect_corruption_khorne_characters",
+    "wh3_main_effect_corruption_nurgle_building_base",
+    "wh3_main_effect_corruption_nurgle_characters",
+    "wh3_main_effect_corruption_slaanesh_building_base",
+    "wh3_main_effect_corruption_slaanesh_characters",
+    "wh3_main_effect_corruption_tzeentch_building_base",
+    "wh3_main_effect_corruption_tzeentch_characters",
+];
+
 //-------------------------------------------------------------------------------//
 //                              Enums & Structs
 //-------------------------------------------------------------------------------//
@@ -98,6 +153,64 @@ pub struct UniversalRebalancerLandUnit {
 //                             Implementations
 //-------------------------------------------------------------------------------//
 
+pub fn prepare_public_order_and_corruption(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, public_order_bonus: Option<i32>, corruption_multiplier: Option<f64>) -> Result<()> {
+
+    // Public order is given to the player as an extra effect on every difficulty.
+    if let Some(bonus) = public_order_bonus {
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_difficulty_handicap_effects_tables/", |data| {
+            add_player_handicap_effect(data, PUBLIC_ORDER_EFFECT, bonus as f32);
+        })?;
+    }
+
+    if let Some(multiplier) = corruption_multiplier {
+        for table_folder in ["db/building_effects_junction_tables/", "db/effect_bundles_to_effects_junctions_tables/"] {
+            patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, table_folder, |data| {
+                patch_effect_values(data, &CORRUPTION_EFFECTS, |value| *value *= multiplier as f32);
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_endgame_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, ENDGAME_SCENARIOS_TABLE, |data| {
+
+        // Push the triggers to a turn no campaign ever reaches, and make sure no scenario is ever picked.
+        patch_numeric_columns(data, &ENDGAME_TURN_COLUMNS, |_| 9999.0);
+        patch_numeric_columns(data, &ENDGAME_WEIGHT_COLUMNS, |_| 0.0);
+    })
+}
+
+pub fn prepare_attrition_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    for (table_folder, column) in ATTRITION_DAMAGE_COLUMNS {
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, table_folder, |data| {
+            patch_numeric_columns(data, &[column], |_| 0.0);
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn prepare_xp_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, xp_multiplier: f64) -> Result<()> {
+
+    // Here we lower the experience each rank needs, so a higher multiplier means faster levelling.
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/character_experience_skill_tiers_tables/", |data| {
+        patch_numeric_columns(data, &["experience"], |value| value / xp_multiplier);
+    })
+}
+
+pub fn prepare_agent_caps_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/campaign_variables_tables/", |data| {
+        patch_kv_values(data, &AGENT_CAP_KEYS, |value| *value = 999_f32);
+    })?;
+
+    // Action cooldowns.
+    patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, "db/agent_actions_tables/", |data| {
+        patch_numeric_columns(data, &AGENT_ACTION_COOLDOWN_COLUMNS, |_| 0.0);
+    })
+}
+
 
 pub fn prepare_trait_limit_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
     let mut campaign_variables = vanilla_pack.files_by_path(&ContainerPath::Folder("db/campaign_variables_tables/".to_string()), true)
@@ -149,63 +262,8 @@ pub fn prepare_trait_limit_removal(game: &GameInfo, reserved_pack: &mut Pack, va
     Ok(())
 }
 
-pub fn prepare_siege_attacker_removal(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema) -> Result<()> {
-    let mut main_units = vanilla_pack.files_by_path(&ContainerPath::Folder("db/main_units_tables/".to_string()), true)
-        .into_iter()
-        .cloned()
-        .collect::<Vec<_>>();
-
-    // Give the daracores extreme low priority so they don't overwrite other mods tables.
-    main_units.iter_mut().for_each(rename_file_name_to_low_priority);
-
-    main_units.append(&mut modded_pack.files_by_path(&ContainerPath::Folder("db/main_units_tables/".to_string()), true)
-        .into_iter()
-        .cloned()
-        .collect::<Vec<_>>());
-
-    // Just in case another step of the launch process adds this table.
-    main_units.append(&mut reserved_pack.files_by_path(&ContainerPath::Folder("db/main_units_tables/".to_string()), true)
-        .into_iter()
-        .cloned()
-        .collect::<Vec<_>>());
-
-    // Sort them so file processing is done in the correct order.
-    main_units.sort_by_key(|rfile| rfile.path_in_container_raw().to_string());
-
-    let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
-    let mut dec_extra_data = DecodeableExtraData::default();
-    dec_extra_data.set_schema(Some(schema));
-    let dec_extra_data = Some(dec_extra_data);
-
-    for table in &mut main_units {
-        if let Some(RFileDecoded::DB(mut data)) = table.decode(&dec_extra_data, false, true)? {
-            let caste_column = data.definition().column_position_by_name("caste");
-            let can_siege_column = data.definition().column_position_by_name("can_siege");
-            if let Some(caste_column) = caste_column {
-                if let Some(can_siege_column) = can_siege_column {
-                    for row in data.data_mut() {
-
-                        if let Some(DecodedData::StringU8(caste)) = row.get(caste_column).cloned() {
-                            if caste != "warmachine" {
-                                if let Some(DecodedData::Boolean(ref mut value)) = row.get_mut(can_siege_column) {
-                                    *value = false;
-                                }
-                            }
-                        }
-                    }
-
-                    table.set_decoded(RFileDecoded::DB(data))?;
-                    table.encode(&enc_extra_data, false, true, false)?;
-                    reserved_pack.insert(table.clone())?;
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
-
-pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, unit_multiplier: f64) -> Result<()> {
+pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &mut Pack, modded_pack: &mut Pack, schema: &Schema, multiplier: &UnitMultiplier) -> Result<()> {
+    let unit_multiplier = multiplier.multiplier();
 
     let mut kv_rules = vanilla_pack.files_by_path(&ContainerPath::Folder("db/_kv_rules_tables/".to_string()), true)
         .into_iter()
@@ -423,11 +481,15 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
         }
     }
 
-    // Note: we need to process this before land_units to get the single entity units.
+    // Note: we need to process this before land_units to get the single entity units, and the new amount of engines and mounts.
     let mut single_entity_units = HashSet::new();
     let mut processed_units = HashSet::new();
+    let mut new_engine_amounts = HashMap::new();
+    let mut new_mount_amounts = HashMap::new();
+    let mut excluded_units = HashSet::new();
     for table in &mut main_units {
         if let Some(RFileDecoded::DB(mut data)) = table.decode(&dec_extra_data, false, true)? {
+            let unit_column = data.definition().column_position_by_name("unit");
             let caste_column = data.definition().column_position_by_name("caste");
             let num_men_column = data.definition().column_position_by_name("num_men");
             let land_unit_column = data.definition().column_position_by_name("land_unit");
@@ -435,6 +497,22 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
 
             for row in data.data_mut() {
 
+                // Skip the units the user doesn't want scaled, remembering their land units so they're skipped too.
+                let unit_value = match unit_column.and_then(|column| row.get(column)) {
+                    Some(DecodedData::StringU8(unit_value)) => unit_value.to_owned(),
+                    _ => String::new(),
+                };
+
+                let land_unit_value = match land_unit_column.and_then(|column| row.get(column)) {
+                    Some(DecodedData::StringU8(land_unit_value)) => land_unit_value.to_owned(),
+                    _ => String::new(),
+                };
+
+                if multiplier.is_excluded(&unit_value) || multiplier.is_excluded(&land_unit_value) {
+                    excluded_units.insert(land_unit_value);
+                    continue;
+                }
+
                 // General unit size.
                 if let Some(num_men_column) = num_men_column {
                     if let Some(caste_column) = caste_column {
@@ -457,21 +535,23 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
 
                                                 // If we have engines, we need to calculate the engine-men ratio to avoid ghost engines.
                                                 else if let Some(engine_amount) = engine_amount.get(&land_unit_value) {
-                                                    let new_engine_amount = (*engine_amount as f64 * unit_multiplier).round() as i32;
+                                                    let new_engine_amount = *new_engine_amounts.entry(land_unit_value.to_owned())
+                                                        .or_insert_with(|| multiplier.scale_groups(*engine_amount, *num_men_value));
                                                     *num_men_value = (*num_men_value * new_engine_amount) / *engine_amount;
                                                     processed_units.insert(land_unit_value.to_owned());
                                                 }
 
                                                 // Same with some weird mounts.
                                                 else if let Some(mount_amount) = mount_amount.get(&land_unit_value) {
-                                                    let new_mount_amount = (*mount_amount as f64 * unit_multiplier).round() as i32;
+                                                    let new_mount_amount = *new_mount_amounts.entry(land_unit_value.to_owned())
+                                                        .or_insert_with(|| multiplier.scale_groups(*mount_amount, *num_men_value));
                                                     *num_men_value = (*num_men_value * new_mount_amount) / *mount_amount;
                                                     processed_units.insert(land_unit_value.to_owned());
                                                 }
 
                                                 // If it's not a single entity, apply the multiplier.
                                                 else {
-                                                    *num_men_value = (*num_men_value as f64 * unit_multiplier).round() as i32;
+                                                    *num_men_value = multiplier.scale_size(*num_men_value);
                                                     processed_units.insert(land_unit_value.to_owned());
                                                 }
                                             }
@@ -504,6 +584,10 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                 // For engines with mounts (chariots) the calculatuion is different. We only need to increase engines, as mounts is mounts per-engine.
                 if let Some(key_column) = key_column {
                     if let Some(DecodedData::StringU8(key_value)) = row.get(key_column).cloned() {
+                        if excluded_units.contains(&key_value) || multiplier.is_excluded(&key_value) {
+                            continue;
+                        }
+
                         let is_single_entity = single_entity_units.contains(&key_value);
                         let mut is_engine = false;
 
@@ -516,7 +600,11 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                                         is_engine = true;
                                     }
 
-                                    *value = (*value as f64 * unit_multiplier).round() as i32;
+                                    // Use the same amount used for the men of the unit, so no engine ends up empty.
+                                    *value = match new_engine_amounts.get(&key_value) {
+                                        Some(new_engine_amount) => *new_engine_amount,
+                                        None => multiplier.scale(*value),
+                                    };
                                 }
                             }
                         }
@@ -525,7 +613,10 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                         if let Some(column) = num_mounts_column {
                             if let Some(DecodedData::I32(value)) = row.get_mut(column) {
                                 if !is_single_entity && !is_engine {
-                                    *value = (*value as f64 * unit_multiplier).round() as i32;
+                                    *value = match new_mount_amounts.get(&key_value) {
+                                        Some(new_mount_amount) => *new_mount_amount,
+                                        None => multiplier.scale(*value),
+                                    };
                                 }
                             }
                         }
@@ -534,7 +625,7 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                         if let Some(column) = rank_depth_column {
                             if let Some(DecodedData::I32(value)) = row.get_mut(column) {
                                 if !is_single_entity {
-                                    *value = (*value as f64 * unit_multiplier).round() as i32;
+                                    *value = multiplier.scale(*value);
                                 }
                             }
                         }
@@ -542,7 +633,7 @@ pub fn prepare_unit_multiplier(game: &GameInfo, reserved_pack: &mut Pack, vanill
                         if is_single_entity {
                             if let Some(bonus_hit_points) = bonus_hit_points_column {
                                 if let Some(DecodedData::I32(value)) = row.get_mut(bonus_hit_points) {
-                                    *value = (*value as f64 * unit_multiplier).round() as i32;
+                                    *value = multiplier.scale(*value);
                                 }
                             }
                         }
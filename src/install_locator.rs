@@ -0,0 +1,161 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module to find where games are installed, including the stores the autodetection of RPFM doesn't support.
+//!
+//! Steam installs are found by RPFM. If that fails, we check the Epic Games Store manifests and the Microsoft Store/Game Pass folders,
+//! and use the first folder containing the executable of the game. In Linux, we check the Steam libraries instead, for games running through Proton.
+//!
+//! Games running through Proton also have their config folder inside the Proton prefix of the game, not in the user's config folder.
+
+use serde_json::Value;
+
+use std::env;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+use rpfm_lib::games::GameInfo;
+use rpfm_lib::integrations::log::info;
+
+use crate::utils::steam_app_id;
+
+// Folder with the manifests of the installed games of the Epic Games Store, within the ProgramData folder.
+const EGS_MANIFESTS_FOLDER: &str = "Epic/EpicGamesLauncher/Data/Manifests";
+const EGS_MANIFEST_EXTENSION: &str = "item";
+
+// Folders where the Microsoft Store and the Xbox app install games, in the root of each drive.
+const MS_STORE_FOLDERS: [&str; 2] = ["XboxGames", "Program Files/ModifiableWindowsApps"];
+
+// Folder inside the game folder with the game files, in Microsoft Store installs.
+const MS_STORE_CONTENT_FOLDER: &str = "Content";
+
+// Folders where Steam can be installed in Linux, within the home folder. The last one is for the Flatpak version.
+const STEAM_LINUX_FOLDERS: [&str; 3] = [".steam/steam", ".local/share/Steam", ".var/app/com.valvesoftware.Steam/.local/share/Steam"];
+
+// File with the list of Steam libraries, within the Steam folder.
+const STEAM_LIBRARIES_FILE: &str = "steamapps/libraryfolders.vdf";
+
+// AppData folder of the user, within the Proton prefix of a game.
+const PROTON_APPDATA_FOLDER: &str = "pfx/drive_c/users/steamuser/AppData/Roaming";
+const CA_CONFIG_FOLDER: &str = "The Creative Assembly";
+
+/// This function returns the folder where the provided game is installed, if found.
+pub fn find_game_install_location(game: &GameInfo) -> Option<PathBuf> {
+    if let Ok(Some(game_path)) = game.find_game_install_location() {
+        return Some(game_path);
+    }
+
+    if cfg!(target_os = "linux") {
+        let game_path = steam_linux_install_location(game);
+        if let Some(ref game_path) = game_path {
+            info!("Game found in a Steam library: {}.", game_path.display());
+        }
+
+        return game_path;
+    }
+
+    if !cfg!(target_os = "windows") {
+        return None;
+    }
+
+    if let Some(game_path) = egs_install_location(game) {
+        info!("Game found in the Epic Games Store install folder: {}.", game_path.display());
+        return Some(game_path);
+    }
+
+    if let Some(game_path) = ms_store_install_location(game) {
+        info!("Game found in the Microsoft Store install folder: {}.", game_path.display());
+        return Some(game_path);
+    }
+
+    None
+}
+
+/// This function returns the config folder of the provided game, where the older games have their user script.
+///
+/// In Linux, if the game runs through Proton, the one inside its prefix is returned.
+pub fn game_config_path(game: &GameInfo, game_path: &Path) -> Option<PathBuf> {
+    let config_path = game.config_path(game_path)?;
+    if cfg!(target_os = "linux") {
+        if let Some(proton_config_path) = proton_config_path(&config_path, game_path) {
+            return Some(proton_config_path);
+        }
+    }
+
+    Some(config_path)
+}
+
+/// This function looks for the game in the install locations of the Epic Games Store manifests.
+fn egs_install_location(game: &GameInfo) -> Option<PathBuf> {
+    let manifests_path = PathBuf::from(env::var("PROGRAMDATA").ok()?).join(EGS_MANIFESTS_FOLDER);
+    read_dir(manifests_path).ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == EGS_MANIFEST_EXTENSION))
+        .filter_map(|path| serde_json::from_str::<Value>(&read_to_string(path).ok()?).ok())
+        .filter_map(|manifest| manifest.get("InstallLocation")?.as_str().map(PathBuf::from))
+        .find(|path| is_game_folder(game, path))
+}
+
+/// This function looks for the game in the folders the Microsoft Store and the Xbox app use in each drive.
+fn ms_store_install_location(game: &GameInfo) -> Option<PathBuf> {
+    ('A'..='Z')
+        .map(|drive| PathBuf::from(format!("{}:/", drive)))
+        .filter(|drive| drive.is_dir())
+        .flat_map(|drive| MS_STORE_FOLDERS.iter().map(move |folder| drive.join(folder)))
+        .filter_map(|folder| read_dir(folder).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .flat_map(|path| [path.join(MS_STORE_CONTENT_FOLDER), path])
+        .find(|path| is_game_folder(game, path))
+}
+
+fn is_game_folder(game: &GameInfo, path: &Path) -> bool {
+    game.executable_path(path).is_some_and(|executable_path| executable_path.is_file())
+}
+
+/// This function looks for the game in the Steam libraries of the user, in Linux.
+fn steam_linux_install_location(game: &GameInfo) -> Option<PathBuf> {
+    let home_path = PathBuf::from(env::var("HOME").ok()?);
+
+    let mut libraries = vec![];
+    for steam_path in STEAM_LINUX_FOLDERS.iter().map(|folder| home_path.join(folder)).filter(|path| path.is_dir()) {
+        if let Ok(data) = read_to_string(steam_path.join(STEAM_LIBRARIES_FILE)) {
+            libraries.extend(data.lines()
+                .filter_map(|line| line.trim().strip_prefix("\"path\""))
+                .map(|value| PathBuf::from(value.trim().trim_matches('"'))));
+        }
+
+        libraries.push(steam_path);
+    }
+
+    // .steam/steam is usually a link to one of the other folders, so the same library may be there more than once.
+    let mut libraries = libraries.iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect::<Vec<_>>();
+    libraries.sort();
+    libraries.dedup();
+
+    libraries.iter()
+        .filter_map(|library| read_dir(library.join("steamapps").join("common")).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .find(|path| is_game_folder(game, path))
+}
+
+/// This function returns the config folder of the game inside its Proton prefix, if the game has one.
+fn proton_config_path(config_path: &Path, game_path: &Path) -> Option<PathBuf> {
+    let app_id = steam_app_id(game_path).ok()?;
+    let steamapps_path = game_path.parent()?.parent()?;
+    let prefix_path = steamapps_path.join("compatdata").join(app_id);
+    if !prefix_path.is_dir() {
+        return None;
+    }
+
+    Some(prefix_path.join(PROTON_APPDATA_FOLDER).join(CA_CONFIG_FOLDER).join(config_path.file_name()?))
+}
@@ -0,0 +1,67 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! This is the core of TWPatcher, a tool to patch Total War load orders with custom patches.
+//!
+//! The TWPatcher CLI is a thin layer over this library, so launchers and other tools can link it instead of shelling out to the CLI.
+//! The entry point is the [`Patcher`] builder:
+//!
+//! ```ignore
+//! use twpatcher_lib::Patcher;
+//!
+//! let outcome = Patcher::new("warhammer_3")
+//!     .load_order_file_name("used_mods.txt")
+//!     .skip_intros(true)
+//!     .unit_multiplier(1.5)
+//!     .run()?;
+//!
+//! println!("Pack generated in: {}.", outcome.pack_path().display());
+//! ```
+
+// Disabled `Clippy` linters, with the reasons why they were disabled.
+#![allow(
+    clippy::type_complexity,                // Disabled due to useless warnings.
+    clippy::too_many_arguments              // Disabled because it gets annoying really quick.
+)]
+
+use lazy_static::lazy_static;
+
+use std::path::PathBuf;
+
+pub use crate::patcher::{ErrorCode, init_game, init_thread_pool, PatchCache, PatchError, PatchOutcome, Patcher};
+
+pub mod app;
+pub mod conflicts;
+pub mod games;
+pub mod install_locator;
+pub mod logging;
+pub mod machine_translation;
+pub mod patches;
+pub mod progress;
+pub mod report;
+pub mod server;
+pub mod updater;
+pub mod utils;
+pub mod watcher;
+
+mod patcher;
+
+lazy_static!{
+
+    /// Path of the folder containing the executable. When used as a library, this is the folder of the program linking it.
+    #[derive(Debug)]
+    pub static ref PROGRAM_PATH: PathBuf = if cfg!(debug_assertions) {
+        std::env::current_dir().unwrap()
+    } else {
+        let mut path = std::env::current_exe().unwrap();
+        path.pop();
+        path
+    };
+}
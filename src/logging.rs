@@ -0,0 +1,287 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the logging system.
+//!
+//! Logs are printed to the terminal, and saved to a log file as JSON lines, one object per message, with the id of the run,
+//! the time, the level, the section of the run (startup, a feature, saving...) and the message.
+//!
+//! By default, each run gets its own log file in the logs folder of the config folder, and only the most recent ones are kept.
+
+use anyhow::{anyhow, Result};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+
+use std::collections::hash_map::RandomState;
+use std::fs::{DirBuilder, File, read_dir, remove_file};
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::utils::config_path;
+
+const LOGS_FOLDER: &str = "logs";
+const LOG_EXTENSION: &str = "jsonl";
+
+// Amount of log files kept in the logs folder.
+const LOGS_LIMIT: usize = 20;
+
+/// Section of the run the current messages belong to.
+static SECTION: RwLock<String> = RwLock::new(String::new());
+
+/// If only errors should be printed to the terminal. The log file still gets all the messages.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Levels used to filter the messages. They're set after parsing the CLI, as the logger needs to exist before that.
+static FILTER: RwLock<Filter> = RwLock::new(Filter::new());
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Logger writing to the terminal and to the log file.
+struct Logger {
+
+    /// Random id of this run, so messages from different runs can be told apart if they end in the same file.
+    run_id: String,
+
+    /// Log file. Not buffered, so nothing is lost if the program exits or crashes.
+    file: Mutex<File>,
+}
+
+/// Levels of the messages to log.
+struct Filter {
+
+    /// Level for the messages from TWPatcher.
+    level: LevelFilter,
+
+    /// Level for the messages from the libraries TWPatcher uses.
+    libraries_level: LevelFilter,
+
+    /// Levels for specific sections or modules. If a name is not provided, it applies to everything else.
+    directives: Vec<(Option<String>, LevelFilter)>,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl Filter {
+    const fn new() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            libraries_level: LevelFilter::Info,
+            directives: vec![],
+        }
+    }
+
+    /// This function returns the level for a message, checking first the section of the run, then the module, and then the defaults.
+    fn level(&self, target: &str, section: &str) -> LevelFilter {
+        if let Some((_, level)) = self.directives.iter().find(|(name, _)| name.as_deref() == Some(section)) {
+            return *level;
+        }
+
+        let module_level = self.directives.iter()
+            .filter_map(|(name, level)| name.as_ref().map(|name| (name, level)))
+            .filter(|(name, _)| target == name.as_str() || target.starts_with(&format!("{}::", name)))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, level)| *level);
+
+        if let Some(level) = module_level {
+            return level;
+        }
+
+        if let Some((_, level)) = self.directives.iter().find(|(name, _)| name.is_none()) {
+            return *level;
+        }
+
+        if target.starts_with(env!("CARGO_PKG_NAME")) { self.level } else { self.libraries_level }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match FILTER.read() {
+            Ok(filter) => metadata.level() <= filter.level(metadata.target(), &log_section()),
+            Err(_) => metadata.level() <= Level::Info,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = record.args().to_string();
+        match record.level() {
+            Level::Error => eprintln!("{}: {}", record.level(), message),
+            _ if QUIET.load(Ordering::Relaxed) => {},
+            Level::Warn => eprintln!("{}: {}", record.level(), message),
+            _ => println!("{}", message),
+        }
+
+        let line = json!({
+            "run_id": self.run_id,
+            "time": SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or_default(),
+            "level": record.level().as_str(),
+            "section": log_section(),
+            "target": record.target(),
+            "message": message,
+        });
+
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// This function initializes the logging system, returning the path of the log file.
+///
+/// If no path is provided, a new log file is created for this run in the logs folder, removing the oldest ones.
+pub fn init(log_path: Option<&Path>) -> Result<PathBuf> {
+    let run_id = run_id();
+    let log_path = match log_path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let folder = config_path()?.join(LOGS_FOLDER);
+            DirBuilder::new().recursive(true).create(&folder)?;
+            rotate_logs(&folder)?;
+
+            let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            folder.join(format!("twpatcher_{}_{}.{}", time, run_id, LOG_EXTENSION))
+        }
+    };
+
+    if let Some(parent) = log_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        DirBuilder::new().recursive(true).create(parent)?;
+    }
+
+    let file = File::options().create(true).append(true).open(&log_path)?;
+    log::set_boxed_logger(Box::new(Logger {
+        run_id,
+        file: Mutex::new(file),
+    }))?;
+    log::set_max_level(LevelFilter::Info);
+
+    // Make sure panics end up in the log too.
+    std::panic::set_hook(Box::new(|info| {
+        log::error!("{}", info);
+        log::logger().flush();
+    }));
+
+    set_log_section("startup");
+    Ok(log_path)
+}
+
+/// This function sets the levels of the messages to log, from the verbosity level and the log filters, if any.
+///
+/// Verbosity 1 shows debug messages from TWPatcher, 2 adds trace messages and debug messages from libraries, and 3 shows everything.
+pub fn set_log_filter(verbosity: u8, filters: Option<&str>) -> Result<()> {
+    let (level, libraries_level) = match verbosity {
+        0 => (LevelFilter::Info, LevelFilter::Info),
+        1 => (LevelFilter::Debug, LevelFilter::Info),
+        2 => (LevelFilter::Trace, LevelFilter::Debug),
+        _ => (LevelFilter::Trace, LevelFilter::Trace),
+    };
+
+    let directives = match filters {
+        Some(filters) => parse_log_filter(filters)?,
+        None => vec![],
+    };
+
+    let max_level = directives.iter()
+        .map(|(_, level)| *level)
+        .chain([level, libraries_level])
+        .max()
+        .unwrap_or(LevelFilter::Info);
+
+    if let Ok(mut filter) = FILTER.write() {
+        *filter = Filter {
+            level,
+            libraries_level,
+            directives,
+        };
+    }
+
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// This function parses log filters in the format used by env_logger: a list of `name=level` or `level` entries separated with commas.
+///
+/// A name without level enables all the messages for it.
+pub fn parse_log_filter(src: &str) -> Result<Vec<(Option<String>, LevelFilter)>> {
+    let mut directives = vec![];
+    for directive in src.split(',').map(|directive| directive.trim()).filter(|directive| !directive.is_empty()) {
+        match directive.split_once('=') {
+            Some((name, level)) => {
+                let level = level.trim().parse::<LevelFilter>().map_err(|_| anyhow!("Invalid log level {} in {}.", level, directive))?;
+                directives.push((Some(name.trim().to_owned()), level));
+            }
+            None => match directive.parse::<LevelFilter>() {
+                Ok(level) => directives.push((None, level)),
+                Err(_) => directives.push((Some(directive.to_owned()), LevelFilter::Trace)),
+            }
+        }
+    }
+
+    Ok(directives)
+}
+
+/// This function makes the logger only print errors to the terminal.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// This function sets the section of the run the next messages belong to.
+pub fn set_log_section(section: &str) {
+    if let Ok(mut current) = SECTION.write() {
+        *current = section.to_owned();
+    }
+}
+
+fn log_section() -> String {
+    SECTION.read().map(|section| section.to_owned()).unwrap_or_default()
+}
+
+/// This function returns a random id for this run.
+fn run_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or_default());
+    hasher.write_u32(std::process::id());
+    format!("{:016x}", hasher.finish())
+}
+
+/// This function removes the oldest log files, so there's space for a new one.
+fn rotate_logs(folder: &Path) -> Result<()> {
+    let mut logs = read_dir(folder)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == LOG_EXTENSION))
+        .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+        .collect::<Vec<_>>();
+
+    logs.sort_by_key(|(modified, _)| *modified);
+    while logs.len() >= LOGS_LIMIT {
+        let (_, path) = logs.remove(0);
+        remove_file(path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the machine translation support, used to fill the texts without a community translation.
+//!
+//! Results are cached per provider and language in the config folder, so repeated runs don't query the API again for the same texts.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use ureq::{Agent, AgentBuilder, Proxy};
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{DirBuilder, File, read_to_string};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use rpfm_lib::integrations::log::info;
+
+use crate::app::MachineTranslationProvider;
+use crate::utils::{config_path, proxy_url};
+
+const MACHINE_TRANSLATIONS_FOLDER: &str = "machine_translations";
+
+const DEEPL_API_KEY_VAR: &str = "TWPATCHER_DEEPL_API_KEY";
+const DEEPL_FREE_URL: &str = "https://api-free.deepl.com/v2/translate";
+const DEEPL_PRO_URL: &str = "https://api.deepl.com/v2/translate";
+
+const LIBRETRANSLATE_API_KEY_VAR: &str = "TWPATCHER_LIBRETRANSLATE_API_KEY";
+const LIBRETRANSLATE_URL_VAR: &str = "TWPATCHER_LIBRETRANSLATE_URL";
+const LIBRETRANSLATE_DEFAULT_URL: &str = "https://libretranslate.com";
+
+// Amount of texts sent on each request.
+const BATCH_SIZE: usize = 50;
+
+// Start and end of the game markup that must not be translated, like [[col:red]] or {{tr:text}}.
+const MARKUP_DELIMITERS: [(&str, &str); 2] = [("[[", "]]"), ("{{", "}}")];
+
+// Tag the game markup is replaced with before sending the texts.
+const MARKUP_TAG: &str = "x";
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Machine translator for a specific provider and language.
+pub struct MachineTranslator {
+    agent: Agent,
+    provider: MachineTranslationProvider,
+    api_key: Option<String>,
+    url: String,
+
+    /// Language code, as the provider expects it.
+    target_language: String,
+
+    /// If true, only cached texts are translated.
+    offline: bool,
+
+    /// English text -> translated text.
+    cache: HashMap<String, String>,
+    cache_path: PathBuf,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl MachineTranslator {
+
+    /// This function creates a new machine translator for the provided game language, loading its cache if found.
+    pub fn new(provider: MachineTranslationProvider, language: &str, offline: bool, proxy: Option<&str>) -> Result<Self> {
+        let (api_key, url) = match provider {
+            MachineTranslationProvider::Deepl => {
+                let api_key = env::var(DEEPL_API_KEY_VAR).map_err(|_| anyhow!("Machine translation with DeepL needs an API key in the {} environment variable.", DEEPL_API_KEY_VAR))?;
+                let url = if api_key.ends_with(":fx") { DEEPL_FREE_URL } else { DEEPL_PRO_URL };
+                (Some(api_key), url.to_owned())
+            }
+            MachineTranslationProvider::Libretranslate => {
+                let url = env::var(LIBRETRANSLATE_URL_VAR).unwrap_or_else(|_| LIBRETRANSLATE_DEFAULT_URL.to_owned());
+                (env::var(LIBRETRANSLATE_API_KEY_VAR).ok(), format!("{}/translate", url.trim_end_matches('/')))
+            }
+        };
+
+        let mut agent = AgentBuilder::new();
+        if let Some(proxy) = proxy_url(proxy) {
+            agent = agent.proxy(Proxy::new(proxy)?);
+        }
+
+        let target_language = target_language(provider, language)?;
+        let cache_path = config_path()?
+            .join(MACHINE_TRANSLATIONS_FOLDER)
+            .join(format!("{:?}_{}.json", provider, language).to_lowercase());
+
+        let cache = read_to_string(&cache_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            agent: agent.build(),
+            provider,
+            api_key,
+            url,
+            target_language,
+            offline,
+            cache,
+            cache_path,
+        })
+    }
+
+    /// This function translates the provided english texts, returning them in the same order.
+    ///
+    /// Only the texts not in the cache are sent to the provider. Texts that could not be translated are returned as they are.
+    pub fn translate(&mut self, texts: &[String]) -> Result<Vec<String>> {
+        if self.offline {
+            return Ok(self.cached(texts));
+        }
+
+        let mut missing = texts.iter()
+            .filter(|text| !text.is_empty() && !self.cache.contains_key(*text))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        missing.sort();
+        missing.dedup();
+
+        if !missing.is_empty() {
+            info!("  - Machine translating {} texts...", missing.len());
+        }
+
+        for batch in missing.chunks(BATCH_SIZE) {
+
+            // The markup is sent as tags the providers leave untouched, so it doesn't get translated or broken.
+            let (protected, markup): (Vec<_>, Vec<_>) = batch.iter().map(|text| protect_markup(text)).unzip();
+            let translated = match self.provider {
+                MachineTranslationProvider::Deepl => self.request_deepl(&protected)?,
+                MachineTranslationProvider::Libretranslate => self.request_libretranslate(&protected)?,
+            };
+
+            if translated.len() != batch.len() {
+                return Err(anyhow!("The machine translation provider returned {} texts, but {} were requested.", translated.len(), batch.len()));
+            }
+
+            let translated = translated.iter().zip(&markup).map(|(text, markup)| restore_markup(text, markup));
+            self.cache.extend(batch.iter().cloned().zip(translated));
+        }
+
+        Ok(self.cached(texts))
+    }
+
+    /// This function saves the cache to disk.
+    pub fn save(&self) -> Result<()> {
+        if let Some(folder) = self.cache_path.parent() {
+            DirBuilder::new().recursive(true).create(folder)?;
+        }
+
+        let mut file = BufWriter::new(File::create(&self.cache_path)?);
+        file.write_all(serde_json::to_string(&self.cache)?.as_bytes())?;
+        Ok(())
+    }
+
+    fn cached(&self, texts: &[String]) -> Vec<String> {
+        texts.iter()
+            .map(|text| self.cache.get(text).cloned().unwrap_or_else(|| text.to_owned()))
+            .collect()
+    }
+
+    fn request_deepl(&self, texts: &[String]) -> Result<Vec<String>> {
+        let response: Value = self.agent.post(&self.url)
+            .set("Authorization", &format!("DeepL-Auth-Key {}", self.api_key.as_deref().unwrap_or_default()))
+            .send_json(json!({
+                "text": texts,
+                "source_lang": "EN",
+                "target_lang": self.target_language,
+                "tag_handling": "xml",
+                "ignore_tags": [MARKUP_TAG],
+            }))?
+            .into_json()?;
+
+        response["translations"].as_array()
+            .map(|translations| translations.iter()
+                .map(|translation| translation["text"].as_str().unwrap_or_default().to_owned())
+                .collect())
+            .ok_or_else(|| anyhow!("Invalid response from DeepL: {}", response))
+    }
+
+    fn request_libretranslate(&self, texts: &[String]) -> Result<Vec<String>> {
+        let mut body = json!({
+            "q": texts,
+            "source": "en",
+            "target": self.target_language,
+            "format": "html",
+        });
+
+        if let Some(ref api_key) = self.api_key {
+            body["api_key"] = json!(api_key);
+        }
+
+        let response: Value = self.agent.post(&self.url)
+            .send_json(body)?
+            .into_json()?;
+
+        response["translatedText"].as_array()
+            .map(|translations| translations.iter()
+                .map(|translation| translation.as_str().unwrap_or_default().to_owned())
+                .collect())
+            .ok_or_else(|| anyhow!("Invalid response from LibreTranslate: {}", response))
+    }
+}
+
+/// This function returns the code a provider uses for one of the game's languages.
+fn target_language(provider: MachineTranslationProvider, language: &str) -> Result<String> {
+    let (deepl, libretranslate) = match language {
+        "br" => ("PT-BR", "pt"),
+        "cn" => ("ZH-HANS", "zh"),
+        "cz" => ("CS", "cs"),
+        "de" => ("DE", "de"),
+        "fr" => ("FR", "fr"),
+        "it" => ("IT", "it"),
+        "jp" => ("JA", "ja"),
+        "kr" => ("KO", "ko"),
+        "pl" => ("PL", "pl"),
+        "ru" => ("RU", "ru"),
+        "sp" => ("ES", "es"),
+        "tr" => ("TR", "tr"),
+        "zh" => ("ZH-HANT", "zt"),
+        _ => return Err(anyhow!("Language {} not supported for machine translation.", language)),
+    };
+
+    Ok(match provider {
+        MachineTranslationProvider::Deepl => deepl.to_owned(),
+        MachineTranslationProvider::Libretranslate => libretranslate.to_owned(),
+    })
+}
+
+/// This function replaces the game markup of a text with numbered tags, and escapes the rest of the text so it can be sent as XML.
+///
+/// Returns the text to send, and the markup replaced, in order.
+fn protect_markup(text: &str) -> (String, Vec<String>) {
+    let mut protected = String::with_capacity(text.len());
+    let mut markup = vec![];
+    let mut rest = text;
+
+    while let Some(character) = rest.chars().next() {
+        let len = markup_len(rest);
+        if len > 0 {
+            protected.push_str(&format!("<{} id=\"{}\"/>", MARKUP_TAG, markup.len()));
+            markup.push(rest[..len].to_owned());
+            rest = &rest[len..];
+            continue;
+        }
+
+        match character {
+            '&' => protected.push_str("&amp;"),
+            '<' => protected.push_str("&lt;"),
+            '>' => protected.push_str("&gt;"),
+            _ => protected.push(character),
+        }
+
+        rest = &rest[character.len_utf8()..];
+    }
+
+    (protected, markup)
+}
+
+/// This function returns the length of the game markup at the start of the text, or 0 if it doesn't start with markup.
+fn markup_len(text: &str) -> usize {
+    for (start, end) in MARKUP_DELIMITERS {
+        if let Some(len) = text.strip_prefix(start).and_then(|inner| inner.find(end)) {
+            return start.len() + len + end.len();
+        }
+    }
+
+    // Format specifiers, like %s, %d or %1.
+    let mut characters = text.chars();
+    match (characters.next(), characters.next()) {
+        (Some('%'), Some(character)) if character.is_ascii_alphanumeric() => 2,
+        _ => 0,
+    }
+}
+
+/// This function reverts [`protect_markup`] over a translated text, putting back the original markup.
+///
+/// Providers may return the tags in any of their equivalent forms, so all of them are replaced.
+fn restore_markup(text: &str, markup: &[String]) -> String {
+    let mut restored = text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+
+    for (index, markup) in markup.iter().enumerate() {
+        for tag in [format!("<{0} id=\"{1}\"/>", MARKUP_TAG, index), format!("<{0} id=\"{1}\" />", MARKUP_TAG, index), format!("<{0} id=\"{1}\"></{0}>", MARKUP_TAG, index)] {
+            restored = restored.replace(&tag, markup);
+        }
+    }
+
+    restored
+}
@@ -9,45 +9,36 @@
 //---------------------------------------------------------------------------//
 
 //! This is a small CLI tool to patch Total War load orders with custom patches.
+//!
+//! All the patching logic lives in the library. This only parses the arguments, checks for updates, and reports errors with the right exit code.
 
-// Disabled `Clippy` linters, with the reasons why they were disabled.
-#![allow(
-    clippy::type_complexity,                // Disabled due to useless warnings.
-    clippy::too_many_arguments              // Disabled because it gets annoying really quick.
-)]
-
-use clap::Parser;
-use lazy_static::lazy_static;
 
 #[cfg(target_os = "windows")]use std::fs::{read_dir, remove_dir_all};
+use std::fs::remove_file;
 use std::path::PathBuf;
 use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use common_utils::updater::*;
-
-use rpfm_lib::games::supported_games::SupportedGames;
-use rpfm_lib::integrations::{git::GitIntegration, log::*};
-use rpfm_lib::schema::*;
+use serde_json::{json, Value};
 
-use crate::app::Cli;
-use crate::games::*;
-use crate::utils::*;
+use rpfm_extensions::translator::TRANSLATED_PATH_OLD;
 
-mod app;
-mod games;
-mod utils;
+use rpfm_lib::files::{ContainerPath, pack::Pack};
+use rpfm_lib::games::supported_games::SupportedGames;
+use rpfm_lib::integrations::log::*;
 
-lazy_static!{
+use twpatcher_lib::{ErrorCode, init_game, init_thread_pool, PatchError, Patcher, PROGRAM_PATH};
+use twpatcher_lib::app::{CheckArgs, CleanArgs, Cli, Commands, GamesArgs, PatchArgs, SqlCommands, SqlValidateArgs, TranslateCommands, TranslateDiffArgs, TranslateExportArgs, UpdateChannelArg};
+use twpatcher_lib::games::*;
+use twpatcher_lib::install_locator::find_game_install_location;
+use twpatcher_lib::logging::{self, set_log_filter, set_quiet};
+use twpatcher_lib::progress::set_progress_mode;
+use twpatcher_lib::{server, watcher};
+use twpatcher_lib::updater::Updater;
+use twpatcher_lib::utils::*;
 
-    #[derive(Debug)]
-    pub static ref PROGRAM_PATH: PathBuf = if cfg!(debug_assertions) {
-        std::env::current_dir().unwrap()
-    } else {
-        let mut path = std::env::current_exe().unwrap();
-        path.pop();
-        path
-    };
-}
+/// If we should skip the wait before closing on errors.
+static NO_WAIT: AtomicBool = AtomicBool::new(false);
 
 const REPO_OWNER: &str = "Frodo45127";
 const REPO_NAME: &str = "twpatcher";
@@ -55,13 +46,34 @@ const REPO_NAME: &str = "twpatcher";
 /// Guess you know what this function does....
 fn main() {
 
-    let logger = Logger::init(&PathBuf::from("."), true, true, release_name!());
-    if logger.is_err() {
-        warn!("Logging initialization has failed. No logs will be saved.");
+    // Portable mode changes where the config is, so it needs to be enabled before reading the config file or creating the log.
+    let portable = init_portable_mode();
+
+    // The log file needs to be set up before parsing the cli, so it and quiet mode have to be read directly from the arguments.
+    set_quiet(std::env::args().any(|arg| arg == "--quiet" || arg == "-q"));
+    let log_path = log_file_arg();
+    match logging::init(log_path.as_deref()) {
+        Ok(path) => info!("Logging to: {}.", path.display()),
+        Err(error) => eprintln!("Logging initialization has failed. No logs will be saved: {}", error),
     }
 
-    // Parse the entire cli command.
-    let cli = Cli::parse();
+    if portable {
+        info!("Portable mode enabled. Using the config folder next to the executable, and not installing updates.");
+    }
+
+    // Parse the entire cli command, including the config file and the manifest, if any.
+    let cli = match Cli::parse_args() {
+        Ok(cli) => cli,
+        Err(error) => return error_path(&error.to_string(), ErrorCode::Generic),
+    };
+
+    NO_WAIT.store(cli.command.no_wait() || cli.command.quiet(), Ordering::Relaxed);
+    set_quiet(cli.command.quiet());
+    set_progress_mode(cli.command.progress());
+
+    if let Err(error) = set_log_filter(cli.command.verbosity(), cli.command.log_filter()) {
+        warn!("Invalid log filters. Using the default ones: {}", error);
+    }
 
     // Clean up folders from previous updates, if they exist. Windows-only.
     //
@@ -80,126 +92,344 @@ fn main() {
         }
     }
 
+    // The thread pool can only be configured once, so it's done before running anything.
+    init_thread_pool(cli.command.threads());
+
+    match cli.command {
+        Commands::Patch(args) if args.rollback => rollback(),
+        Commands::Patch(args) if args.serve => serve(&args),
+        Commands::Patch(args) if args.watch => watch(&args),
+        Commands::Patch(args) => patch(&args),
+        Commands::Translate(mut args) => match args.command.take() {
+            Some(TranslateCommands::Export(args)) => translate_export(args),
+            Some(TranslateCommands::Diff(args)) => translate_diff(&args),
+            None => patch(&args.into()),
+        },
+        Commands::Sql(mut args) => match args.command.take() {
+            Some(SqlCommands::Validate(args)) => sql_validate(args),
+            None => patch(&args.into()),
+        },
+        Commands::Check(args) => check(args),
+        Commands::Clean(args) => clean(&args),
+        Commands::Games(args) => games(&args),
+    }
+}
+
+/// This function checks for updates, and then patches the load order with the provided options.
+fn patch(cli: &PatchArgs) {
     // Perform an update check before doing anything else.
-    if !cli.skip_updates_check {
-        info!("Update Checks enabled. Checking if there are updates available.");
-
-        let updater = Updater::new(UpdateChannel::Stable, REPO_OWNER, REPO_NAME);
-        match updater.check(env!("CARGO_PKG_VERSION")) {
-            Ok(response) => match response {
-                APIResponse::NewBetaUpdate(update) |
-                APIResponse::NewStableUpdate(update) |
-                APIResponse::NewUpdateHotfix(update) => {
-                    info!("- New update available: {}. Downlaoding and installing update...", update);
-                    if let Err(error) = updater.download() {
-                        error!("- Error when downloading/installing the update: {}", error);
-                    } else {
-                        info!("- Update downloaded and installed. Restart the program to use it.");
-                    }
-                }
-                APIResponse::NoUpdate => info!("- No new updates available."),
-                APIResponse::UnknownVersion => info!("- Unknown Version returned from Update Check."),
+    if !cli.skip_updates_check && !cli.offline {
+        check_updates(cli);
+    }
+
+    let outcome = match Patcher::from_args(cli.clone()).run() {
+        Ok(outcome) => outcome,
+        Err(error) => error_path(error.message(), *error.code()),
+    };
+
+    info!("All done. Closing. Bye!");
+
+    // Launchers running in quiet mode need to know where the Pack is.
+    if cli.quiet {
+        println!("{}", outcome.pack_path().display());
+    }
+
+    let failed = outcome.report().failed_features();
+    if !failed.is_empty() {
+        let features = failed.iter().map(|(feature, _)| *feature).collect::<Vec<_>>();
+        error_path(&format!("The Pack has been generated, but the following features failed: {}.", features.join(", ")), ErrorCode::FeaturesFailed);
+    }
+
+    // Only save the profile if everything worked, so a broken set of options doesn't overwrite a working one.
+    if let Some(ref name) = cli.save_profile {
+        match cli.save_profile(name) {
+            Ok(_) => info!("Profile {} saved.", name),
+            Err(error) => warn!("Failed to save the profile {}: {}", name, error),
+        }
+    }
+
+    exit(0)
+}
+
+/// This function runs TWPatcher as a server, patching the load orders launchers request through stdin.
+fn serve(args: &PatchArgs) {
+
+    // Servers are run by launchers, so there's nobody to read the errors before closing.
+    NO_WAIT.store(true, Ordering::Relaxed);
+
+    if let Err(error) = server::serve(args) {
+        error_path(&error.to_string(), ErrorCode::Generic);
+    }
+
+    exit(0)
+}
+
+/// This function patches the load order, and then patches it again each time it changes.
+fn watch(cli: &PatchArgs) {
+    if !cli.skip_updates_check && !cli.offline {
+        check_updates(cli);
+    }
+
+    if let Err(error) = watcher::watch(cli) {
+        let code = error.downcast_ref::<PatchError>().map(|error| *error.code()).unwrap_or(ErrorCode::Generic);
+        error_path(&error.to_string(), code);
+    }
+
+    exit(0)
+}
+
+/// This function checks if there are updates available, and installs them unless only checks are enabled.
+fn check_updates(cli: &PatchArgs) {
+    info!("Update Checks enabled. Checking if there are updates available.");
+    info!("- Update channel: {:?}.", cli.update_channel);
+
+    let beta = cli.update_channel == UpdateChannelArg::Beta;
+    let updater = match Updater::new(REPO_OWNER, REPO_NAME, beta, cli.proxy.as_deref()) {
+        Ok(updater) => updater,
+        Err(error) => {
+            error!("- Update Checks failed due to: {}.", error);
+            return;
+        }
+    };
+
+    match updater.check(env!("CARGO_PKG_VERSION")) {
+        Ok(Some(release)) if cli.check_updates_only || is_portable() => {
+            info!("- New update available: {}. Not installing it, as only update checks are enabled.", release.version());
+        }
+        Ok(Some(release)) => {
+            info!("- New update available: {}. Downloading and installing update...", release.version());
+
+            // Keep the current version around, in case the update has a regression.
+            match backup_program(&PROGRAM_PATH, env!("CARGO_PKG_VERSION")) {
+                Ok(path) => info!("- Current version backed up to: {}. Use --rollback to restore it.", path.display()),
+                Err(error) => warn!("- Error backing up the current version. Rollback will not be available for this update: {}", error),
             }
 
-            Err(error) => {
-                error!("- Update Checks failed due to: {}.", error);
+            if let Err(error) = updater.install(&release, &PROGRAM_PATH) {
+                error!("- Error when downloading/installing the update: {}", error);
+            } else {
+                info!("- Update downloaded, verified and installed. Restart the program to use it.");
             }
         }
+        Ok(None) => info!("- No new updates available."),
+        Err(error) => error!("- Update Checks failed due to: {}.", error),
     }
+}
 
-    let game = match SupportedGames::default().game(&cli.game).cloned() {
-        Some(game) => game,
-        None => return error_path(&format!("Invalid game provided: {}", cli.game)),
-    };
+/// This function checks that the game, the load order and the schema can be found and loaded, without patching anything.
+fn check(args: CheckArgs) {
+    let patcher = Patcher::from_args(args.into());
+    let cli = patcher.args();
 
-    let game_path = match game.find_game_install_location() {
-        Ok(Some(game_path)) => game_path,
-        _ => return error_path("Game Path not found"),
-    };
+    let (game, game_path, data_path) = init_game(&cli.game, &cli.game_path, &cli.data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+    info!("Game found in: {}.", game_path.display());
+
+    let load_order = patcher.load_order(&game, &game_path, &data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+
+    info!("Load order found with {} mods.", load_order.len());
+    for entry in &load_order {
+        debug!("- {}", entry.to_string_lossy().replace("\\", "/"));
+    }
+
+    if let Err(error) = init_modded_pack(&game, &load_order) {
+        return error_path(&error.to_string(), ErrorCode::LoadOrderError);
+    }
+
+    info!("Mod data loaded.");
+
+    if let Err(error) = patcher.schema(&game, None) {
+        return error_path(error.message(), *error.code());
+    }
+
+    info!("Schema loaded. Everything seems to be in order. Bye!");
+    exit(0)
+}
+
+/// This function exports the texts of the load order to TSV files, so translators can use them as a base for new translations.
+fn translate_export(args: TranslateExportArgs) {
+    let output_path = args.output_path.clone();
+    let patcher = Patcher::from_args(args.into());
+    let cli = patcher.args();
 
-    let data_path = match game.data_path(&game_path) {
+    let (game, game_path, data_path) = init_game(&cli.game, &cli.game_path, &cli.data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+    let load_order = patcher.load_order(&game, &game_path, &data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+    let (schema, _) = patcher.schema(&game, None).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+
+    match export_translations(&game, &load_order, &schema, &output_path) {
+        Ok(count) => info!("Texts of {} Packs exported to {}.", count, output_path.join(game.key()).display()),
+        Err(error) => return error_path(&error.to_string(), ErrorCode::SaveError),
+    }
+
+    exit(0)
+}
+
+/// This function finds the english texts of the game that changed since the community translations were made, so they can be marked for retranslation.
+fn translate_diff(args: &TranslateDiffArgs) {
+    let (game, game_path, _) = init_game(&args.game, &args.game_path, &args.data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+
+    let translations_path = match translations_remote_path() {
         Ok(path) => path,
-        _ => return error_path("Data Path not found"),
+        Err(error) => return error_path(&error.to_string(), ErrorCode::Generic),
     };
 
-    let mut reserved_pack = match init_reserved_pack(&game) {
-        Ok(pack) => pack,
-        Err(error) => return error_path(&error.to_string()),
-    };
+    if !args.offline {
+        info!("Checking and downloading community translations...");
+
+        let _ = update_repo(&translations_path, args.translations_repo.as_deref().unwrap_or(TRANSLATIONS_REPO), TRANSLATIONS_BRANCH, TRANSLATIONS_REMOTE, args.proxy.as_deref());
 
+        info!("Checking and downloading community translations done.");
+    }
 
     let mut vanilla_pack = match init_vanilla_pack(&game, &game_path) {
-        Ok(pack) => pack,
-        Err(error) => return error_path(&error.to_string()),
+        Ok(mut pack) => {
+            retain_data_paths(&mut pack, &[ContainerPath::Folder("text/".to_owned()), ContainerPath::File(TRANSLATED_PATH_OLD.to_owned())]);
+            pack
+        }
+        Err(error) => return error_path(&error.to_string(), ErrorCode::GameNotFound),
     };
 
-    info!("Vanilla data loaded. Loading load order data for: {}.", game.display_name());
+    let diff = match vanilla_english_diff(&game, &mut vanilla_pack, &translations_path) {
+        Ok(diff) => diff,
+        Err(error) => return error_path(&error.to_string(), ErrorCode::Generic),
+    };
 
-    let load_order_path = game_path.join(&cli.load_order_file_name);
-    if cli.verbose {
-        info!("Load order file path: {}.", load_order_path.display());
+    if let Err(error) = diff.save(&args.output_path) {
+        return error_path(&error.to_string(), ErrorCode::SaveError);
     }
 
-    let load_order = match load_order_from_file(&load_order_path, &game, &game_path, &data_path) {
-        Ok(load_order) => load_order,
-        Err(error) => return error_path(&error.to_string()),
+    info!("Texts changed: {}. Texts added: {}. Texts removed: {}.", diff.count("changed"), diff.count("added"), diff.count("removed"));
+    info!("Differences saved to: {}.", args.output_path.display());
+    exit(0)
+}
+
+/// This function checks a SQL script against the game data, without patching anything.
+fn sql_validate(args: SqlValidateArgs) {
+    let (script_path, params) = args.sql_script.clone();
+    let patcher = Patcher::from_args(args.into());
+    let cli = patcher.args();
+
+    let (game, game_path, data_path) = init_game(&cli.game, &cli.game_path, &cli.data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+    let (schema, _) = patcher.schema(&game, None).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+
+    let data_paths = Some(vec![ContainerPath::Folder("db/".to_owned())]);
+    let vanilla_pack = patcher.vanilla_pack(&game, &game_path, &data_paths, None).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+
+    // The modded data is optional here, so only load it if a load order was provided.
+    let modded_pack = if cli.load_order_file_name.is_some() {
+        let load_order = patcher.load_order(&game, &game_path, &data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
+        init_modded_pack(&game, &load_order).unwrap_or_else(|error| error_path(&error.to_string(), ErrorCode::LoadOrderError))
+    } else {
+        Pack::default()
     };
 
-    info!("Load order found with the following mods:");
-    for entry in &load_order {
-        info!("- {}", entry.to_string_lossy().replace("\\", "/"));
+    info!("Validating SQL script: {}.", script_path.display());
+
+    match validate_sql_script(&vanilla_pack, &modded_pack, &schema, &script_path, &params) {
+        Ok(true) => info!("The SQL script is valid."),
+        Ok(false) => return error_path("The SQL script is not valid. Read this terminal for more info.", ErrorCode::SqlError),
+        Err(error) => return error_path(&error.to_string(), ErrorCode::SqlError),
     }
 
-    let mut modded_pack = match init_modded_pack(&game, &load_order) {
-        Ok(pack) => pack,
-        Err(error) => return error_path(&error.to_string()),
-    };
+    exit(0)
+}
 
-    info!("Mod data loaded.");
+/// This function removes the Pack generated by TWPatcher.
+fn clean(args: &CleanArgs) {
+    let (game, _, data_path) = init_game(&args.game, &args.game_path, &args.data_path).unwrap_or_else(|error| error_path(error.message(), *error.code()));
 
-    // Prepare the schemas. This includes downloading them in the background if we don't have them in RPFM's config folder or are outdated.
-    let schema = match schemas_path() {
-        Ok(local_path) => {
+    let custom_path = args.generated_pack_path.clone().map(PathBuf::from);
+    let path = reserved_pack_path(&game, &data_path, &custom_path);
+    if path.is_file() {
+        if let Err(error) = remove_file(&path) {
+            return error_path(&error.to_string(), ErrorCode::SaveError);
+        }
 
-            info!("Checking and downloading schema updates...");
+        info!("Removed Pack: {}.", path.display());
+    } else {
+        info!("No Pack found to remove in: {}.", path.display());
+    }
 
-            // For now, ignore this failure. This can happen due to network issues, and as long as we have a valid schema, we can ignore it.
-            let git_integration = GitIntegration::new(&local_path, SCHEMA_REPO, SCHEMA_BRANCH, SCHEMA_REMOTE);
-            let _ = git_integration.update_repo();
+    exit(0)
+}
 
-            info!("Checking and downloading schema updates done.");
+/// This function lists the supported games, and the install path, version and load order file of the installed ones.
+fn games(args: &GamesArgs) {
+    let supported_games = SupportedGames::default();
+    let games = supported_games.game_keys_sorted()
+        .iter()
+        .filter_map(|key| supported_games.game(key))
+        .map(|game| {
+            let game_path = find_game_install_location(game);
+            let data_path = game_path.as_ref().and_then(|path| game.data_path(path).ok());
+            let version = game_path.as_ref().and_then(|path| game.game_version_number(path));
+            let load_order_found = game_path.as_ref().is_some_and(|path| path.join(&args.load_order_file_name).is_file());
+
+            json!({
+                "key": game.key(),
+                "name": game.display_name(),
+                "installed": game_path.is_some(),
+                "game_path": game_path,
+                "data_path": data_path,
+                "version": version,
+                "load_order_file_found": load_order_found,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if args.json {
+        println!("{}", Value::Array(games));
+        exit(0)
+    }
 
-            match Schema::load(&local_path.join(game.schema_file_name()), None) {
-                Ok(schema) => schema,
-                Err(error) => return error_path(&error.to_string()),
-            }
-        },
-        Err(error) => return error_path(&error.to_string()),
-    };
+    info!("Installed games:");
+    for game in games.iter().filter(|game| game["installed"] == true) {
+        let version = game["version"].as_u64().map(|version| version.to_string()).unwrap_or_else(|| "unknown".to_owned());
+        let load_order = if game["load_order_file_found"] == true { "found" } else { "not found" };
+
+        info!("- {} ({}): {}. Version: {}. Load order file {}: {}.", game["key"].as_str().unwrap_or_default(), game["name"].as_str().unwrap_or_default(), game["game_path"].as_str().unwrap_or_default(), version, args.load_order_file_name, load_order);
+    }
 
-    info!("Schema loaded. Processing selected options...");
+    let not_installed = games.iter()
+        .filter(|game| game["installed"] == false)
+        .filter_map(|game| game["key"].as_str())
+        .collect::<Vec<_>>();
 
-    // Save it to disk once empty so its disk path is saved correctly.
-    let custom_path = cli.generated_pack_path.clone().map(PathBuf::from);
-    save_reserved_pack(&game, &mut reserved_pack, &load_order, &data_path, &custom_path).unwrap_or_else(|error| error_path(&error.to_string()));
+    if !not_installed.is_empty() {
+        info!("Supported, but not found: {}.", not_installed.join(", "));
+    }
 
-    // With all the needed data initialized, check what flags we passed through the cli.
-    prepare_launch_options(&cli, &game, &mut reserved_pack, &mut vanilla_pack, &mut modded_pack, &schema, &load_order, &game_path).unwrap_or_else(|error| error_path(&error.to_string()));
-    info!("Options processed. Saving Pack");
+    exit(0)
+}
 
-    // If everything worked as expected, save the reserved pack.
-    save_reserved_pack(&game, &mut reserved_pack, &load_order, &data_path, &custom_path).unwrap_or_else(|error| error_path(&error.to_string()));
+/// This function returns the path passed with --log-file, if any.
+fn log_file_arg() -> Option<PathBuf> {
+    let args = std::env::args().collect::<Vec<_>>();
+    args.iter()
+        .enumerate()
+        .find_map(|(index, arg)| match arg.strip_prefix("--log-file") {
+            Some("") => args.get(index + 1).map(PathBuf::from),
+            Some(value) => value.strip_prefix('=').map(PathBuf::from),
+            None => None,
+        })
+}
 
-    info!("All done. Closing. Bye!");
+/// This function restores the version of TWPatcher used before the last update.
+fn rollback() {
+    match rollback_program(&PROGRAM_PATH) {
+        Ok(version) => info!("Restored version {}. Use --skip-updates-check in the next executions to keep using it.", version),
+        Err(error) => error_path(&error.to_string(), ErrorCode::Generic),
+    }
 
     exit(0)
 }
 
-fn error_path(error: &str) {
+fn error_path(error: &str, code: ErrorCode) -> ! {
     error!("{}", error.to_string());
 
-    info!("This terminal will close itself in 60 seconds to give you some time to read the log, but if you want, you can close it now.");
-    std::thread::sleep(std::time::Duration::from_millis(60000));
+    if !NO_WAIT.load(Ordering::Relaxed) {
+        info!("This terminal will close itself in 60 seconds to give you some time to read the log, but if you want, you can close it now.");
+        std::thread::sleep(std::time::Duration::from_millis(60000));
+    }
 
-    exit(1);
+    exit(code as i32);
 }
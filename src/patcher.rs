@@ -0,0 +1,587 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the patch pipeline, and the builder used to configure it.
+//!
+//! The CLI is a thin layer over this: it parses the arguments, checks for updates, and runs a [`Patcher`] with them.
+
+use getset::Getters;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use rpfm_lib::files::{ContainerPath, pack::Pack};
+use rpfm_lib::games::{GameInfo, supported_games::SupportedGames};
+use rpfm_lib::integrations::log::*;
+use rpfm_lib::schema::*;
+
+use crate::app::{LoadOrderFormat, PatchArgs, UnitMultiplierRounding};
+use crate::conflicts::ConflictsReport;
+use crate::games::*;
+use crate::install_locator::find_game_install_location;
+use crate::logging::set_log_section;
+use crate::progress::Progress;
+use crate::report::{PackManifest, Report};
+use crate::utils::*;
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Exit codes returned on errors, so automation can tell what failed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(i32)]
+pub enum ErrorCode {
+    Generic = 1,
+    GameNotFound = 2,
+    LoadOrderError = 3,
+    SchemaError = 4,
+    SqlError = 5,
+    SaveError = 6,
+    FeaturesFailed = 7,
+}
+
+/// Error returned when patching fails, with the code of the step that failed.
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct PatchError {
+    code: ErrorCode,
+    message: String,
+}
+
+/// Result of a successful patch.
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct PatchOutcome {
+
+    /// Path of the generated Pack.
+    pack_path: PathBuf,
+
+    /// Report of the changes done to the generated Pack. Features that failed with `keep_going` enabled are in it.
+    report: Report,
+}
+
+/// Data kept in memory between patches, so patching the same game again doesn't need to load it again.
+///
+/// Used by the server mode. The vanilla data is reloaded if the game gets updated.
+#[derive(Default)]
+pub struct PatchCache {
+    game_key: String,
+
+    /// Modified time of the game's executable when the vanilla data was loaded.
+    executable_time: Option<u64>,
+
+    /// Vanilla data of the game, without filtering it by the data the features need.
+    vanilla_pack: Option<Pack>,
+
+    /// Schema, with the path and the revision it was loaded from.
+    schema: Option<(PathBuf, Option<String>, Schema)>,
+
+    /// Decoded vanilla tables and database with the modded data used by the SQL scripts.
+    sql: SqlCache,
+}
+
+/// Builder to patch a load order, for tools that want to use TWPatcher without shelling out to the CLI.
+///
+/// Options not covered by the builder can be set through [`Patcher::args_mut`], using the same names as the CLI arguments.
+///
+/// ```ignore
+/// let outcome = Patcher::new("warhammer_3")
+///     .load_order_file_name("used_mods.txt")
+///     .skip_intros(true)
+///     .unit_multiplier(1.5)
+///     .run()?;
+/// ```
+#[derive(Clone, Default)]
+pub struct Patcher {
+    args: PatchArgs,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl PatchError {
+    pub fn new(message: impl Display, code: ErrorCode) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl Error for PatchError {}
+
+impl Patcher {
+
+    /// This function creates a new patcher for the provided game, with all features disabled.
+    pub fn new(game: &str) -> Self {
+        Self {
+            args: PatchArgs {
+                game: game.to_owned(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// This function creates a new patcher from already parsed patch arguments.
+    pub fn from_args(args: PatchArgs) -> Self {
+        Self {
+            args,
+        }
+    }
+
+    /// This function returns the arguments the patcher is going to use.
+    pub fn args(&self) -> &PatchArgs {
+        &self.args
+    }
+
+    /// This function returns a mutable reference to the arguments the patcher is going to use, to set the options without a builder method.
+    pub fn args_mut(&mut self) -> &mut PatchArgs {
+        &mut self.args
+    }
+
+    /// This function sets the folder where the game is installed, instead of finding it automatically.
+    pub fn game_path(mut self, path: PathBuf) -> Self {
+        self.args.game_path = Some(path);
+        self
+    }
+
+    /// This function sets the data folder of the game, instead of finding it automatically.
+    pub fn data_path(mut self, path: PathBuf) -> Self {
+        self.args.data_path = Some(path);
+        self
+    }
+
+    /// This function sets the name of the load order file to patch, relative to the game folder.
+    pub fn load_order_file_name(mut self, name: &str) -> Self {
+        self.args.load_order_file_name = Some(name.to_owned());
+        self
+    }
+
+    /// This function sets the format of the load order file.
+    pub fn load_order_format(mut self, format: LoadOrderFormat) -> Self {
+        self.args.load_order_format = format;
+        self
+    }
+
+    /// This function sets the list of Pack names to patch, instead of reading them from a load order file.
+    pub fn mods(mut self, mods: &[String]) -> Self {
+        self.args.mods = Some(mods.to_vec());
+        self
+    }
+
+    /// This function sets the Runcher profile to read the load order from.
+    pub fn runcher_profile(mut self, profile: &str) -> Self {
+        self.args.runcher_profile = Some(profile.to_owned());
+        self
+    }
+
+    /// This function sets where to save the generated Pack, instead of the default path.
+    pub fn generated_pack_path(mut self, path: &str) -> Self {
+        self.args.generated_pack_path = Some(path.to_owned());
+        self
+    }
+
+    /// This function disables any network access, using only the cached data.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.args.offline = offline;
+        self
+    }
+
+    /// This function makes the patcher continue with the rest of the features when one of them fails.
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.args.keep_going = keep_going;
+        self
+    }
+
+    /// This function enables or disables skipping the intro videos.
+    pub fn skip_intros(mut self, skip: bool) -> Self {
+        self.args.skip_intro_videos = skip;
+        self
+    }
+
+    /// This function enables or disables the script logging of the game.
+    pub fn enable_logging(mut self, enable: bool) -> Self {
+        self.args.enable_logging = enable;
+        self
+    }
+
+    /// This function enables or disables removing the trait limit of characters.
+    pub fn remove_trait_limit(mut self, remove: bool) -> Self {
+        self.args.remove_trait_limit = remove;
+        self
+    }
+
+    /// This function sets the multiplier applied to the size of the units.
+    pub fn unit_multiplier(mut self, multiplier: f64) -> Self {
+        self.args.unit_multiplier = Some(multiplier);
+        self
+    }
+
+    /// This function sets how to round the unit sizes after applying the unit multiplier.
+    pub fn unit_multiplier_rounding(mut self, rounding: UnitMultiplierRounding) -> Self {
+        self.args.unit_multiplier_rounding = rounding;
+        self
+    }
+
+    /// This function sets the maximum size of the units after applying the unit multiplier.
+    pub fn unit_size_cap(mut self, cap: i32) -> Self {
+        self.args.unit_size_cap = Some(cap);
+        self
+    }
+
+    /// This function sets the file with the units that should not be scaled by the unit multiplier.
+    pub fn unit_multiplier_exclude(mut self, path: &Path) -> Self {
+        self.args.unit_multiplier_exclude = Some(path.to_path_buf());
+        self
+    }
+
+    /// This function sets the language to apply the community translations for.
+    pub fn translation_language(mut self, language: &str) -> Self {
+        self.args.translation_language = Some(language.to_owned());
+        self
+    }
+
+    /// This function adds a SQL script to run over the load order, with its parameters.
+    pub fn sql_script(mut self, path: PathBuf, params: &[String]) -> Self {
+        self.args.sql_script.get_or_insert_with(Vec::new).push((path, params.to_vec()));
+        self
+    }
+
+    /// This function patches the load order, returning the path of the generated Pack and the report of the changes.
+    ///
+    /// This doesn't check for TWPatcher updates. That's left to the program using it.
+    pub fn run(&self) -> Result<PatchOutcome, PatchError> {
+        self.patch(None)
+    }
+
+    /// This function patches the load order like [`Patcher::run`], but reusing the data in the cache, and keeping the loaded data there for the next patches.
+    pub fn run_with_cache(&self, cache: &mut PatchCache) -> Result<PatchOutcome, PatchError> {
+        self.patch(Some(cache))
+    }
+
+    /// This function loads the vanilla data and the schema of the game into the cache, so the first patch doesn't need to load them.
+    pub fn warm_cache(&self, cache: &mut PatchCache) -> Result<(), PatchError> {
+        let (game, game_path, _) = init_game(&self.args.game, &self.args.game_path, &self.args.data_path)?;
+        cache.set_game(&game);
+
+        self.vanilla_pack(&game, &game_path, &None, Some(&mut *cache))?;
+        self.schema(&game, Some(cache))?;
+        Ok(())
+    }
+
+    fn patch(&self, mut cache: Option<&mut PatchCache>) -> Result<PatchOutcome, PatchError> {
+        let cli = &self.args;
+
+        if cli.offline {
+            info!("Offline mode enabled. Skipping update checks and downloads.");
+        }
+
+        let (game, game_path, data_path) = init_game(&cli.game, &cli.game_path, &cli.data_path)?;
+        if let Some(ref mut cache) = cache {
+            cache.set_game(&game);
+        }
+
+        let mut reserved_pack = init_reserved_pack(&game).map_err(|error| PatchError::new(error, ErrorCode::Generic))?;
+
+        // Only load the game data the enabled features actually need.
+        let data_paths = required_data_paths(cli, &game);
+        let needs_data = data_paths.as_ref().map(|paths| !paths.is_empty()).unwrap_or(true);
+
+        let mut vanilla_pack = if needs_data {
+            let pack = self.vanilla_pack(&game, &game_path, &data_paths, cache.as_deref_mut())?;
+            info!("Vanilla data loaded.");
+            pack
+        } else {
+            info!("None of the enabled features need the game data. Skipping loading it.");
+            Pack::default()
+        };
+
+        set_log_section("load_order");
+        info!("Loading load order data for: {}.", game.display_name());
+
+        let load_order = self.load_order(&game, &game_path, &data_path)?;
+
+        info!("Load order found with the following mods:");
+        for entry in &load_order {
+            info!("- {}", entry.to_string_lossy().replace("\\", "/"));
+        }
+
+        let mut modded_pack = if needs_data {
+            let mut pack = init_modded_pack(&game, &load_order).map_err(|error| PatchError::new(error, ErrorCode::LoadOrderError))?;
+            if let Some(ref paths) = data_paths {
+                retain_data_paths(&mut pack, paths);
+            }
+
+            pack
+        } else {
+            Pack::default()
+        };
+
+        info!("Mod data loaded.");
+
+        set_log_section("schema");
+
+        let (schema, schema_path) = self.schema(&game, cache.as_deref_mut())?;
+
+        info!("Schema loaded.");
+
+        if let Some(ref path) = cli.conflicts_report {
+            info!("Checking conflicts between mods...");
+
+            match ConflictsReport::new(&load_order, &game, &schema).and_then(|conflicts| conflicts.save(path)) {
+                Ok(_) => info!("Conflicts report saved to: {}.", path.display()),
+                Err(error) => warn!("Failed to generate the conflicts report at {}: {}", path.display(), error),
+            }
+        }
+
+        info!("Processing selected options...");
+
+        // Save it to disk once empty so its disk path is saved correctly.
+        let custom_path = cli.generated_pack_path.clone().map(PathBuf::from);
+        let dependencies = if cli.no_pack_dependencies { None } else { Some(cli.pack_dependencies) };
+        save_reserved_pack(&game, &mut reserved_pack, &load_order, &data_path, &custom_path, cli.compress, dependencies).map_err(|error| PatchError::new(error, ErrorCode::SaveError))?;
+
+        // With all the needed data initialized, check what flags we passed through the cli.
+        let mut report = Report::new(&mut reserved_pack, &game);
+        prepare_launch_options(cli, &game, &mut reserved_pack, &mut vanilla_pack, &mut modded_pack, &schema, &schema_path, &load_order, &game_path, &mut report, cache.map(|cache| &mut cache.sql)).map_err(|error| {
+            let code = if error.downcast_ref::<SqlScriptsError>().is_some() { ErrorCode::SqlError } else { ErrorCode::Generic };
+            PatchError::new(error, code)
+        })?;
+
+        set_log_section("save");
+        info!("Options processed. Saving Pack");
+
+        report.count_changed_rows(&mut reserved_pack, &vanilla_pack, &modded_pack, &schema);
+        report.log();
+
+        if let Some(ref path) = cli.report {
+            if let Err(error) = report.save(path) {
+                warn!("Failed to save the report to {}: {}", path.display(), error);
+            }
+        }
+
+        // If everything worked as expected, save the reserved pack.
+        let progress = Progress::new("pack_save", 0);
+        save_reserved_pack(&game, &mut reserved_pack, &load_order, &data_path, &custom_path, cli.compress, dependencies).map_err(|error| PatchError::new(error, ErrorCode::SaveError))?;
+        progress.finish();
+
+        let pack_path = reserved_pack_path(&game, &data_path, &custom_path);
+        match PackManifest::new(&mut reserved_pack, &pack_path, &game, &load_order, &report).and_then(|manifest| manifest.save(&pack_path)) {
+            Ok(path) => info!("Pack manifest saved to: {}.", path.display()),
+            Err(error) => warn!("Failed to save the manifest of the Pack: {}", error),
+        }
+
+        Ok(PatchOutcome {
+            pack_path,
+            report,
+        })
+    }
+
+    /// This function finds the load order to patch, validates it, and removes the excluded Packs from it.
+    pub fn load_order(&self, game: &GameInfo, game_path: &Path, data_path: &Path) -> Result<Vec<PathBuf>, PatchError> {
+        let cli = &self.args;
+
+        let load_order = match (&cli.runcher_profile, &cli.mods, &cli.mods_from) {
+            (Some(profile_name), _, _) => {
+                debug!("Runcher profile: {}.", profile_name);
+
+                load_order_from_runcher_profile(profile_name, game, game_path, data_path, !cli.no_movie_scan)
+            }
+            (_, Some(mods), _) => load_order_from_list(mods, game, game_path, data_path, !cli.no_movie_scan),
+            (_, _, Some(mods_from)) => {
+                debug!("Mod list path: {}.", mods_from.display());
+
+                load_order_from_list_file(mods_from, game, game_path, data_path, !cli.no_movie_scan)
+            }
+            _ => {
+                load_order_file_path(cli.load_order_file_name.as_deref().unwrap_or_default(), game, game_path)
+                    .and_then(|load_order_path| {
+                        debug!("Load order file path: {}.", load_order_path.display());
+
+                        load_order_from_file(&load_order_path, cli.load_order_format, game, game_path, data_path, !cli.no_movie_scan)
+                    })
+            }
+        };
+
+        let mut load_order = load_order.and_then(|load_order| validate_load_order(load_order, game, cli.strict))
+            .map_err(|error| PatchError::new(error, ErrorCode::LoadOrderError))?;
+
+        if let Some(ref patterns) = cli.exclude_pack {
+            load_order.retain(|path| {
+                let pack_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                let is_excluded = patterns.iter().any(|pattern| glob_match(pattern, &pack_name));
+                if is_excluded {
+                    info!("Excluding {} from the load order.", pack_name);
+                }
+
+                !is_excluded
+            });
+        }
+
+        Ok(load_order)
+    }
+
+    /// This function loads the vanilla data of the game, keeping only the paths provided, if any.
+    pub fn vanilla_pack(&self, game: &GameInfo, game_path: &Path, data_paths: &Option<Vec<ContainerPath>>, cache: Option<&mut PatchCache>) -> Result<Pack, PatchError> {
+        let progress = Progress::new("vanilla_merge", 0);
+        let mut pack = match cache {
+            Some(cache) => {
+                let executable_time = game.executable_path(game_path).and_then(|path| modified_time(&path));
+                if cache.vanilla_pack.is_none() || cache.executable_time != executable_time {
+                    cache.vanilla_pack = Some(init_vanilla_pack(game, game_path).map_err(|error| PatchError::new(error, ErrorCode::Generic))?);
+                    cache.executable_time = executable_time;
+                } else {
+                    info!("Using the cached vanilla data.");
+                }
+
+                cache.vanilla_pack.clone().unwrap_or_default()
+            }
+            None => init_vanilla_pack(game, game_path).map_err(|error| PatchError::new(error, ErrorCode::Generic))?,
+        };
+
+        if let Some(ref paths) = data_paths {
+            retain_data_paths(&mut pack, paths);
+        }
+
+        progress.finish();
+        Ok(pack)
+    }
+
+    /// This function updates and loads the schema of the game, returning it with the path it was loaded from.
+    pub fn schema(&self, game: &GameInfo, cache: Option<&mut PatchCache>) -> Result<(Schema, PathBuf), PatchError> {
+        let cli = &self.args;
+
+        // Prepare the schemas. This includes downloading them in the background if we don't have them in RPFM's config folder or are outdated.
+        //
+        // Custom schema repos are cloned to their own folder, so they don't mess with the default one.
+        let local_path = match (&cli.schema_path, &cli.schema_repo) {
+            (Some(path), _) => Ok(path.to_path_buf()),
+            (None, Some(repo)) => custom_schemas_path(repo),
+            (None, None) => schemas_path(),
+        };
+
+        let local_path = local_path.map_err(|error| PatchError::new(error, ErrorCode::SchemaError))?;
+        let schema_path = local_path.join(game.schema_file_name());
+
+        // Schemas are only updated when loaded, so a server doesn't hit the network on each patch.
+        if let Some((cached_path, cached_rev, schema)) = cache.as_ref().and_then(|cache| cache.schema.as_ref()) {
+            if *cached_path == schema_path && *cached_rev == cli.schema_rev {
+                info!("Using the cached schema.");
+                return Ok((schema.clone(), schema_path));
+            }
+        }
+
+        // Local schema folders are used as they are.
+        if cli.schema_path.is_none() {
+            let schema_repo = cli.schema_repo.as_deref().unwrap_or(SCHEMA_REPO);
+
+            // Return to the branch before updating, in case the schemas were pinned to a revision in a previous execution.
+            if local_path.join(".git").is_dir() {
+                if let Err(error) = checkout_repo_revision(&local_path, None, SCHEMA_BRANCH) {
+                    warn!("Failed to restore the schemas to the {} branch: {}", SCHEMA_BRANCH, error);
+                }
+            }
+
+            if !cli.offline && !cli.skip_schema_update {
+                info!("Checking and downloading schema updates from {}...", schema_repo);
+
+                // For now, ignore this failure. This can happen due to network issues, and as long as we have a valid schema, we can ignore it.
+                let _ = update_repo(&local_path, schema_repo, SCHEMA_BRANCH, SCHEMA_REMOTE, cli.proxy.as_deref());
+
+                info!("Checking and downloading schema updates done.");
+            }
+
+            if let Some(ref revision) = cli.schema_rev {
+                info!("Using schemas from revision: {}.", revision);
+
+                checkout_repo_revision(&local_path, Some(revision), SCHEMA_BRANCH).map_err(|error| PatchError::new(error, ErrorCode::SchemaError))?;
+            }
+        }
+
+        if cli.schema_path.is_some() && !schema_path.is_file() {
+            return Err(PatchError::new(format!("Schema not found in {}.", schema_path.display()), ErrorCode::SchemaError));
+        }
+
+        if cli.offline && !schema_path.is_file() {
+            return Err(PatchError::new(format!("Schema not found in {}, and it cannot be downloaded in offline mode. Run TWPatcher once without --offline to download it.", schema_path.display()), ErrorCode::SchemaError));
+        }
+
+        let schema = Schema::load(&schema_path, None).map_err(|error| PatchError::new(error, ErrorCode::SchemaError))?;
+        if let Some(cache) = cache {
+            cache.schema = Some((schema_path.clone(), cli.schema_rev.clone(), schema.clone()));
+        }
+
+        Ok((schema, schema_path))
+    }
+}
+
+impl PatchCache {
+
+    /// This function empties the cache if it has data from another game.
+    fn set_game(&mut self, game: &GameInfo) {
+        if self.game_key != game.key() {
+            *self = Self {
+                game_key: game.key().to_owned(),
+                ..Default::default()
+            };
+        }
+    }
+}
+
+/// This function limits the threads used for decoding and processing the data, if requested.
+///
+/// The thread pool is global and can only be configured once per process, so it has to be called at startup, before patching anything.
+/// Later calls, like the ones with the threads of each server request, are ignored with a warning.
+pub fn init_thread_pool(threads: Option<u32>) {
+    if let Some(threads) = threads {
+        if let Err(error) = rayon::ThreadPoolBuilder::new().num_threads(threads as usize).build_global() {
+            warn!("Failed to limit the amount of threads to {}: {}", threads, error);
+        }
+    }
+}
+
+/// This function returns the info of the provided game, its install path, and its data path.
+///
+/// If the paths are provided, they're used instead of the ones found automatically.
+pub fn init_game(game_key: &str, game_path: &Option<PathBuf>, data_path: &Option<PathBuf>) -> Result<(GameInfo, PathBuf, PathBuf), PatchError> {
+    let game = SupportedGames::default().game(game_key).cloned()
+        .ok_or_else(|| PatchError::new(format!("Invalid game provided: {}", game_key), ErrorCode::GameNotFound))?;
+
+    let game_path = match game_path {
+        Some(game_path) if game_path.is_dir() => game_path.to_path_buf(),
+        Some(game_path) => return Err(PatchError::new(format!("Game Path {} not found", game_path.display()), ErrorCode::GameNotFound)),
+        None => match find_game_install_location(&game) {
+            Some(game_path) => game_path,
+
+            // Most games have the data folder directly inside the game folder, so we can use it if it was provided.
+            _ => match data_path.as_ref().and_then(|data_path| data_path.parent()) {
+                Some(game_path) => game_path.to_path_buf(),
+                None => return Err(PatchError::new("Game Path not found. If the game is installed, provide its folder with --game-path", ErrorCode::GameNotFound)),
+            }
+        }
+    };
+
+    let data_path = match data_path {
+        Some(data_path) if data_path.is_dir() => data_path.to_path_buf(),
+        Some(data_path) => return Err(PatchError::new(format!("Data Path {} not found", data_path.display()), ErrorCode::GameNotFound)),
+        None => game.data_path(&game_path).map_err(|_| PatchError::new("Data Path not found. If the game is installed, provide its data folder with --data-path", ErrorCode::GameNotFound))?,
+    };
+
+    Ok((game, game_path, data_path))
+}
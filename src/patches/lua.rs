@@ -0,0 +1,278 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the Lua patches.
+//!
+//! Lua patches are scripts executed over the load order, with access to a `twpatcher` global with the following API:
+//!
+//! - `twpatcher.game`: key of the game being patched.
+//! - `twpatcher.db_load(table_name)`: returns a list with all the tables of that type in the load order, sorted by priority (last one wins).
+//!   Each table is a Lua table with a `path`, a `columns` list, and a `rows` list, where each row is a Lua table indexed by column name.
+//! - `twpatcher.db_save(table)`: saves a table returned by `db_load` (with all the changes done to its rows) to the generated pack.
+//! - `twpatcher.loc_get(key)`: returns the text of a loc key in the load order, or nil if not found.
+//! - `twpatcher.loc_set(key, text)`: sets the text of a loc key in the generated pack.
+//! - `twpatcher.text_read(path)`: returns the contents of a text file in the load order, or nil if not found.
+//! - `twpatcher.text_write(path, contents)`: writes a text file to the generated pack.
+//! - `twpatcher.log(message)`: writes a message to the log.
+
+use anyhow::{anyhow, Result};
+use mlua::{Lua, Table, Value};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use rpfm_lib::files::{Container, db::DB, DecodeableExtraData, EncodeableExtraData, FileType, loc::Loc, pack::Pack, RFile, RFileDecoded, table::DecodedData, text::Text};
+use rpfm_lib::games::GameInfo;
+use rpfm_lib::integrations::log::info;
+use rpfm_lib::schema::Schema;
+
+use crate::games::load_order_tables;
+
+// Loc file where the texts set by Lua patches are saved. The ! makes it take priority over other loc files.
+const LUA_PATCHES_LOC_PATH: &str = "text/db/!twpatcher_lua_patches.loc";
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+/// This function executes a Lua patch over the load order, saving the results in the reserved pack.
+pub fn apply_lua_patch(path: &Path, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema) -> Result<()> {
+    let script = read_to_string(path)?;
+    let lua = Lua::new();
+
+    let reserved_pack = RefCell::new(reserved_pack);
+    let loaded_tables: RefCell<HashMap<String, DB>> = RefCell::new(HashMap::new());
+    let locs: RefCell<Option<HashMap<String, String>>> = RefCell::new(None);
+    let new_locs: RefCell<Vec<(String, String)>> = RefCell::new(vec![]);
+
+    let mut dec_extra_data = DecodeableExtraData::default();
+    dec_extra_data.set_schema(Some(schema));
+    let dec_extra_data = Some(dec_extra_data);
+    let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
+
+    lua.scope(|scope| {
+        let api = lua.create_table()?;
+        api.set("game", game.key())?;
+
+        api.set("db_load", scope.create_function(|lua, table_name: String| {
+            let table_name = table_name.strip_suffix("_tables").unwrap_or(&table_name).to_owned();
+            let table_folder = format!("db/{}_tables/", table_name);
+            let tables = load_order_tables(&reserved_pack.borrow(), vanilla_pack, modded_pack, &table_folder);
+
+            let list = lua.create_table()?;
+            for mut table in tables {
+                if let Ok(Some(RFileDecoded::DB(data))) = table.decode(&dec_extra_data, false, true) {
+                    list.push(db_to_lua(lua, table.path_in_container_raw(), &data)?)?;
+                    loaded_tables.borrow_mut().insert(table.path_in_container_raw().to_owned(), data);
+                }
+            }
+
+            Ok(list)
+        })?)?;
+
+        api.set("db_save", scope.create_function(|_, table: Table| {
+            let path: String = table.get("path")?;
+            let mut data = loaded_tables.borrow().get(&path).cloned()
+                .ok_or_else(|| mlua::Error::external(anyhow!("Table {} has not been loaded with db_load.", path)))?;
+
+            db_from_lua(&table, &mut data)?;
+
+            let mut file = RFile::new_from_decoded(&RFileDecoded::DB(data), 0, &path);
+            file.encode(&enc_extra_data, false, true, false).map_err(mlua::Error::external)?;
+            reserved_pack.borrow_mut().insert(file).map_err(mlua::Error::external)?;
+            Ok(())
+        })?)?;
+
+        api.set("loc_get", scope.create_function(|_, key: String| {
+            if locs.borrow().is_none() {
+                *locs.borrow_mut() = Some(load_order_locs(&reserved_pack.borrow(), vanilla_pack, modded_pack));
+            }
+
+            let new_text = new_locs.borrow().iter().rev().find(|(new_key, _)| *new_key == key).map(|(_, text)| text.to_owned());
+            Ok(new_text.or_else(|| locs.borrow().as_ref().and_then(|locs| locs.get(&key).cloned())))
+        })?)?;
+
+        api.set("loc_set", scope.create_function(|_, (key, text): (String, String)| {
+            new_locs.borrow_mut().push((key, text));
+            Ok(())
+        })?)?;
+
+        api.set("text_read", scope.create_function(|_, path: String| {
+            let reserved_pack = reserved_pack.borrow();
+            let file = [&**reserved_pack, modded_pack, vanilla_pack].iter()
+                .find_map(|pack| pack.file(&path, false).cloned());
+
+            match file {
+                Some(mut file) => match file.decode(&dec_extra_data, false, true) {
+                    Ok(Some(RFileDecoded::Text(text))) => Ok(Some(text.contents().to_owned())),
+                    _ => Ok(None),
+                },
+                None => Ok(None),
+            }
+        })?)?;
+
+        api.set("text_write", scope.create_function(|_, (path, contents): (String, String)| {
+            let mut text = Text::default();
+            text.set_contents(contents);
+
+            let file = RFile::new_from_decoded(&RFileDecoded::Text(text), 0, &path);
+            reserved_pack.borrow_mut().insert(file).map_err(mlua::Error::external)?;
+            Ok(())
+        })?)?;
+
+        api.set("log", scope.create_function(|_, message: String| {
+            info!("    - Lua: {}", message);
+            Ok(())
+        })?)?;
+
+        lua.globals().set("twpatcher", api)?;
+        lua.load(&script).set_name(path.to_string_lossy()).exec()
+    }).map_err(|error| anyhow!("Error executing Lua patch {}: {}", path.to_string_lossy(), error))?;
+
+    // Save the texts the script set, if any, keeping the ones set by previous scripts unless this one changed them.
+    let new_locs = new_locs.into_inner();
+    if !new_locs.is_empty() {
+        let reserved_pack = reserved_pack.into_inner();
+        let mut loc_data = match reserved_pack.files_mut().get_mut(LUA_PATCHES_LOC_PATH).map(|file| file.decode(&None, false, true)) {
+            Some(Ok(Some(RFileDecoded::Loc(loc)))) => loc.data().to_vec(),
+            _ => vec![],
+        };
+
+        for (key, text) in new_locs {
+            match loc_data.iter_mut().find(|row| row[0].data_to_string() == key) {
+                Some(row) => row[1] = DecodedData::StringU16(text),
+                None => loc_data.push(vec![
+                    DecodedData::StringU16(key),
+                    DecodedData::StringU16(text),
+                    DecodedData::Boolean(false),
+                ]),
+            }
+        }
+
+        let mut loc = Loc::new();
+        loc.set_data(&loc_data)?;
+
+        let file = RFile::new_from_decoded(&RFileDecoded::Loc(loc), 0, LUA_PATCHES_LOC_PATH);
+        reserved_pack.files_mut().insert(LUA_PATCHES_LOC_PATH.to_owned(), file);
+    }
+
+    Ok(())
+}
+
+/// This function converts a decoded table into a Lua table.
+fn db_to_lua(lua: &Lua, path: &str, data: &DB) -> mlua::Result<Table> {
+    let fields = data.definition().fields_processed();
+
+    let table = lua.create_table()?;
+    table.set("path", path)?;
+    table.set("columns", lua.create_sequence_from(fields.iter().map(|field| field.name().to_owned()))?)?;
+
+    let rows = lua.create_table()?;
+    for row in data.data().iter() {
+        let lua_row = lua.create_table()?;
+        for (field, cell) in fields.iter().zip(row.iter()) {
+            lua_row.set(field.name(), cell_to_lua(lua, cell)?)?;
+        }
+
+        rows.push(lua_row)?;
+    }
+
+    table.set("rows", rows)?;
+    Ok(table)
+}
+
+/// This function replaces the rows of a decoded table with the ones of a Lua table.
+///
+/// Missing or invalid values in a row are replaced with the default value of their column.
+fn db_from_lua(table: &Table, data: &mut DB) -> mlua::Result<()> {
+    let fields = data.definition().fields_processed();
+    let rows: Table = table.get("rows")?;
+
+    let mut new_rows = vec![];
+    for lua_row in rows.sequence_values::<Table>() {
+        let lua_row = lua_row?;
+        let mut row = data.new_row();
+
+        for (field, cell) in fields.iter().zip(row.iter_mut()) {
+            let value: Value = lua_row.get(field.name())?;
+            cell_from_lua(&value, cell);
+        }
+
+        new_rows.push(row);
+    }
+
+    *data.data_mut() = new_rows;
+    Ok(())
+}
+
+fn cell_to_lua(lua: &Lua, cell: &DecodedData) -> mlua::Result<Value> {
+    Ok(match cell {
+        DecodedData::Boolean(value) => Value::Boolean(*value),
+        DecodedData::I16(value) => Value::Integer(*value as i64),
+        DecodedData::I32(value) => Value::Integer(*value as i64),
+        DecodedData::I64(value) => Value::Integer(*value),
+        DecodedData::F32(value) => Value::Number(*value as f64),
+        DecodedData::F64(value) => Value::Number(*value),
+        _ => Value::String(lua.create_string(&*cell.data_to_string())?),
+    })
+}
+
+/// This function sets the value of a cell from a Lua value. Returns false if the value is not valid for the cell's type.
+fn cell_from_lua(value: &Value, cell: &mut DecodedData) -> bool {
+    let number = match value {
+        Value::Integer(value) => Some(*value as f64),
+        Value::Number(value) => Some(*value),
+        _ => None,
+    };
+
+    match (value, number, cell) {
+        (Value::Boolean(value), _, DecodedData::Boolean(cell)) => *cell = *value,
+        (_, Some(number), DecodedData::I16(cell)) => *cell = number.round() as i16,
+        (_, Some(number), DecodedData::I32(cell)) => *cell = number.round() as i32,
+        (_, Some(number), DecodedData::I64(cell)) => *cell = number.round() as i64,
+        (_, Some(number), DecodedData::F32(cell)) => *cell = number as f32,
+        (_, Some(number), DecodedData::F64(cell)) => *cell = number,
+        (Value::String(value), _, DecodedData::StringU8(cell)) |
+        (Value::String(value), _, DecodedData::StringU16(cell)) |
+        (Value::String(value), _, DecodedData::OptionalStringU8(cell)) |
+        (Value::String(value), _, DecodedData::OptionalStringU16(cell)) => *cell = value.to_string_lossy(),
+        _ => return false,
+    }
+
+    true
+}
+
+/// This function returns all the loc entries in the load order, keeping only the one that takes priority for each key.
+fn load_order_locs(reserved_pack: &Pack, vanilla_pack: &Pack, modded_pack: &Pack) -> HashMap<String, String> {
+    let mut locs = HashMap::new();
+
+    // Locs are read from the pack with the highest priority first, so the first entry found is the one the game uses.
+    for pack in [reserved_pack, modded_pack, vanilla_pack] {
+        let mut files = pack.files_by_type(&[FileType::Loc])
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        files.sort_by_key(|rfile| rfile.path_in_container_raw().to_string());
+
+        for mut file in files {
+            if let Ok(Some(RFileDecoded::Loc(loc))) = file.decode(&None, false, true) {
+                for row in loc.data().iter() {
+                    locs.entry(row[0].data_to_string().to_string())
+                        .or_insert_with(|| row[1].data_to_string().to_string());
+                }
+            }
+        }
+    }
+
+    locs
+}
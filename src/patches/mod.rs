@@ -0,0 +1,214 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the declarative table patches.
+//!
+//! A table patch file is a TOML file with one or more patches like this one:
+//!
+//! ```toml
+//! [[patch]]
+//! table = "main_units"
+//! column = "campaign_cap"
+//! operation = "set"
+//! value = -1
+//!
+//! # Optional. Only rows where all the columns match their pattern are patched. Patterns support * and ?.
+//! [patch.filter]
+//! unit = "wh_main_emp_*"
+//! ```
+//!
+//! Supported operations are `set`, `multiply` and `add`. `multiply` and `add` only work on numeric columns.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use rpfm_lib::files::{db::DB, pack::Pack, table::DecodedData};
+use rpfm_lib::games::GameInfo;
+use rpfm_lib::integrations::log::{info, warn};
+use rpfm_lib::schema::Schema;
+
+use crate::games::patch_load_order_tables;
+use crate::utils::glob_match;
+
+pub mod lua;
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// A file with table patches.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TablePatchFile {
+    #[serde(rename = "patch", default)]
+    patches: Vec<TablePatch>,
+}
+
+/// A single patch over a column of a table.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TablePatch {
+
+    /// Name of the table, with or without the `_tables` suffix.
+    table: String,
+
+    /// Column to edit.
+    column: String,
+
+    /// Column -> pattern, with * and ? as wildcards. Rows have to match the pattern of every column to be patched.
+    #[serde(default)]
+    filter: BTreeMap<String, String>,
+
+    operation: TablePatchOperation,
+    value: TablePatchValue,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TablePatchOperation {
+    Set,
+    Multiply,
+    Add,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum TablePatchValue {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl TablePatchFile {
+
+    /// This function reads and parses a table patch file from disk.
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = read_to_string(path)?;
+        let file: Self = toml::from_str(&data).map_err(|error| anyhow!("Error parsing table patch file {}: {}", path.to_string_lossy(), error))?;
+
+        for patch in &file.patches {
+            if patch.operation != TablePatchOperation::Set && !matches!(patch.value, TablePatchValue::Number(_)) {
+                return Err(anyhow!("Error in table patch file {}: operation {:?} over column {} of table {} needs a numeric value.", path.to_string_lossy(), patch.operation, patch.column, patch.table));
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// This function applies all the patches of the file over the load order, saving the results in the reserved pack.
+    pub fn apply(&self, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema) -> Result<()> {
+        for patch in &self.patches {
+            patch.apply(game, reserved_pack, vanilla_pack, modded_pack, schema)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TablePatch {
+
+    /// This function applies the patch over all the tables of its type in the load order.
+    pub fn apply(&self, game: &GameInfo, reserved_pack: &mut Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema) -> Result<()> {
+        let table_name = self.table.strip_suffix("_tables").unwrap_or(&self.table);
+        let table_folder = format!("db/{}_tables/", table_name);
+
+        let mut patched_rows = 0;
+        let mut invalid_rows = 0;
+        patch_load_order_tables(game, reserved_pack, vanilla_pack, modded_pack, schema, &table_folder, |data| {
+            let (patched, invalid) = self.apply_to_table(data);
+            patched_rows += patched;
+            invalid_rows += invalid;
+        })?;
+
+        if invalid_rows > 0 {
+            warn!("Value {:?} is not valid for the type of column {} of table {}. Skipped {} rows.", self.value, self.column, self.table, invalid_rows);
+        }
+
+        info!("  - Table patch over {}/{}: {} rows patched.", table_name, self.column, patched_rows);
+        Ok(())
+    }
+
+    /// This function applies the patch over a single table, returning the amount of rows edited,
+    /// and the amount of rows skipped because the value is not valid for the column's type.
+    fn apply_to_table(&self, data: &mut DB) -> (usize, usize) {
+        let column = match data.definition().column_position_by_name(&self.column) {
+            Some(column) => column,
+            None => {
+                warn!("Column {} not found in table {}. Skipping patch.", self.column, self.table);
+                return (0, 0);
+            }
+        };
+
+        let mut filters = vec![];
+        for (filter_column, pattern) in &self.filter {
+            match data.definition().column_position_by_name(filter_column) {
+                Some(filter_column) => filters.push((filter_column, pattern)),
+                None => {
+                    warn!("Filter column {} not found in table {}. Skipping patch.", filter_column, self.table);
+                    return (0, 0);
+                }
+            }
+        }
+
+        let mut patched_rows = 0;
+        let mut invalid_rows = 0;
+        for row in data.data_mut() {
+            let is_match = filters.iter().all(|(filter_column, pattern)| match row.get(*filter_column) {
+                Some(cell) => glob_match(pattern, &cell.data_to_string()),
+                None => false,
+            });
+
+            if is_match {
+                if let Some(cell) = row.get_mut(column) {
+                    if self.apply_to_cell(cell) {
+                        patched_rows += 1;
+                    } else {
+                        invalid_rows += 1;
+                    }
+                }
+            }
+        }
+
+        (patched_rows, invalid_rows)
+    }
+
+    /// This function applies the patch over a single cell. Returns false if the value is not valid for the cell's type.
+    fn apply_to_cell(&self, cell: &mut DecodedData) -> bool {
+        match (&self.value, cell) {
+            (TablePatchValue::Number(value), DecodedData::I16(cell)) => *cell = self.edit(*cell as f64, *value).round() as i16,
+            (TablePatchValue::Number(value), DecodedData::I32(cell)) => *cell = self.edit(*cell as f64, *value).round() as i32,
+            (TablePatchValue::Number(value), DecodedData::I64(cell)) => *cell = self.edit(*cell as f64, *value).round() as i64,
+            (TablePatchValue::Number(value), DecodedData::F32(cell)) => *cell = self.edit(*cell as f64, *value) as f32,
+            (TablePatchValue::Number(value), DecodedData::F64(cell)) => *cell = self.edit(*cell, *value),
+            (TablePatchValue::Boolean(value), DecodedData::Boolean(cell)) => *cell = *value,
+            (TablePatchValue::Text(value), DecodedData::StringU8(cell)) |
+            (TablePatchValue::Text(value), DecodedData::StringU16(cell)) |
+            (TablePatchValue::Text(value), DecodedData::OptionalStringU8(cell)) |
+            (TablePatchValue::Text(value), DecodedData::OptionalStringU16(cell)) => *cell = value.to_owned(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn edit(&self, current: f64, value: f64) -> f64 {
+        match self.operation {
+            TablePatchOperation::Set => value,
+            TablePatchOperation::Multiply => current * value,
+            TablePatchOperation::Add => current + value,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the progress reporting of the long steps of a run.
+//!
+//! Progress is either logged every 10%, or printed to stdout as JSON lines, one per event, for launchers to parse.
+
+use serde_json::json;
+
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use rpfm_lib::integrations::log::info;
+
+use crate::app::ProgressMode;
+
+/// How to report progress. Stored as the index of the ProgressMode variant.
+static MODE: AtomicU8 = AtomicU8::new(ProgressMode::Text as u8);
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Progress of a long step. Steps without a total only report when they start and finish.
+pub struct Progress {
+    step: &'static str,
+    total: usize,
+    current: AtomicUsize,
+
+    /// Last percentage reported, so we don't report the same one more than once.
+    reported: AtomicUsize,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl Progress {
+
+    /// This function starts reporting the progress of a step.
+    pub fn new(step: &'static str, total: usize) -> Self {
+        report(step, "started", 0, total);
+
+        Self {
+            step,
+            total,
+            current: AtomicUsize::new(0),
+            reported: AtomicUsize::new(0),
+        }
+    }
+
+    /// This function marks one more item of the step as done.
+    pub fn inc(&self) {
+        let current = self.current.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.total == 0 {
+            return;
+        }
+
+        // Only report every 10% in text mode, and every 1% in json mode.
+        let step = if mode() == ProgressMode::Json { 1 } else { 10 };
+        let percent = (current * 100 / self.total) / step * step;
+        if percent > self.reported.fetch_max(percent, Ordering::Relaxed) && percent < 100 {
+            report(self.step, "progress", current, self.total);
+        }
+    }
+
+    /// This function marks the step as finished.
+    pub fn finish(self) {
+        report(self.step, "finished", self.current.load(Ordering::Relaxed), self.total);
+    }
+}
+
+/// This function sets how progress is reported.
+pub fn set_progress_mode(mode: ProgressMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+fn mode() -> ProgressMode {
+    match MODE.load(Ordering::Relaxed) {
+        x if x == ProgressMode::Json as u8 => ProgressMode::Json,
+        x if x == ProgressMode::None as u8 => ProgressMode::None,
+        _ => ProgressMode::Text,
+    }
+}
+
+fn report(step: &str, event: &str, current: usize, total: usize) {
+    match mode() {
+        ProgressMode::Text => if event == "progress" {
+            info!("  - {}: {}/{} ({}%).", step, current, total, current * 100 / total);
+        },
+        ProgressMode::Json => println!("{}", json!({
+            "event": event,
+            "step": step,
+            "current": current,
+            "total": total,
+        })),
+        ProgressMode::None => {},
+    }
+}
@@ -0,0 +1,452 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the report of the changes done to the generated pack.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{DirBuilder, File, read};
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rpfm_lib::files::{Container, DecodeableExtraData, EncodeableExtraData, FileType, pack::Pack, RFile, RFileDecoded};
+use rpfm_lib::games::GameInfo;
+use rpfm_lib::integrations::log::{error, info};
+use rpfm_lib::schema::Schema;
+
+use crate::utils::content_hash;
+
+// Extension added to the name of the generated Pack for its manifest.
+const PACK_MANIFEST_EXTENSION: &str = ".manifest.json";
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Report of the files in the generated pack, and the features that produced them.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    files: BTreeMap<String, ReportEntry>,
+
+    /// Status of the features that edited the Pack or failed.
+    features: BTreeMap<String, FeatureStatus>,
+
+    /// Fingerprints of the files in the reserved pack the last time we checked it.
+    #[serde(skip)]
+    fingerprints: HashMap<String, Option<u64>>,
+
+    /// Copies of the files in the reserved pack the last time we checked it, to undo the changes of failed features. Only kept if requested.
+    #[serde(skip)]
+    snapshot: Option<HashMap<String, RFile>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReportEntry {
+
+    /// Features that created or edited the file, in the order they were applied.
+    features: Vec<String>,
+
+    /// Amount of rows added or changed against the vanilla/modded table this one overrides. DB tables only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows_changed: Option<usize>,
+
+    /// Amount of rows of the table. DB tables only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows_total: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FeatureStatus {
+    success: bool,
+
+    /// Error the feature failed with, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Manifest of the contents of the generated Pack, saved next to it so launchers can check what it contains and if it's outdated.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PackManifest {
+
+    /// Version of TWPatcher that generated the Pack.
+    version: String,
+
+    /// Size and hash of the whole Pack file.
+    size: u64,
+    hash: String,
+
+    /// Names of the Packs of the load order the Pack was generated for, in load order.
+    load_order: Vec<String>,
+
+    /// Path of each file in the Pack -> its info.
+    files: BTreeMap<String, PackManifestEntry>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PackManifestEntry {
+
+    /// Size of the file, uncompressed.
+    size: usize,
+
+    /// Hash of the contents of the file.
+    hash: String,
+
+    /// Features that created or edited the file.
+    features: Vec<String>,
+}
+
+/// Report of the loc keys that couldn't be translated, per Pack.
+#[derive(Clone, Debug, Default)]
+pub struct TranslationReport {
+
+    /// Pack name -> list of (key, reason, text) of the keys not translated from that Pack.
+    packs: BTreeMap<String, Vec<(String, String, String)>>,
+
+    /// Loc key -> name of the Pack it comes from.
+    key_packs: HashMap<String, String>,
+}
+
+/// Differences between the english texts of the game and the ones the community translations are based on.
+#[derive(Clone, Debug, Default)]
+pub struct VanillaLocDiff {
+
+    /// Loc key -> (status, old text, new text). Status is one of "changed", "added" or "removed".
+    entries: BTreeMap<String, (String, String, String)>,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl Report {
+
+    /// This function creates a new report, taking the current contents of the reserved pack as a base.
+    pub fn new(reserved_pack: &mut Pack, game: &GameInfo) -> Self {
+        Self {
+            files: BTreeMap::new(),
+            features: BTreeMap::new(),
+            fingerprints: fingerprints(reserved_pack, game),
+            snapshot: None,
+        }
+    }
+
+    /// This function starts keeping copies of the files in the reserved pack, so the changes of failed features can be undone.
+    ///
+    /// After the first call, only the files each feature edits are copied again, so we don't clone the whole pack for every feature.
+    pub fn keep_snapshot(&mut self, reserved_pack: &Pack) {
+        if self.snapshot.is_none() {
+            self.snapshot = Some(reserved_pack.files().clone());
+        }
+    }
+
+    /// This function undoes all the changes done to the reserved pack since the last call to [Report::tag], if we're keeping a snapshot.
+    pub fn rollback(&self, reserved_pack: &mut Pack, game: &GameInfo) {
+        if let Some(snapshot) = &self.snapshot {
+            let new_fingerprints = fingerprints(reserved_pack, game);
+            let changed_paths = new_fingerprints.iter()
+                .filter(|(path, fingerprint)| self.fingerprints.get(*path) != Some(fingerprint))
+                .map(|(path, _)| path)
+                .chain(self.fingerprints.keys().filter(|path| !new_fingerprints.contains_key(*path)));
+
+            for path in changed_paths {
+                match snapshot.get(path) {
+                    Some(file) => reserved_pack.files_mut().insert(path.to_owned(), file.clone()),
+                    None => reserved_pack.files_mut().remove(path),
+                };
+            }
+        }
+    }
+
+    /// This function tags all the files added or edited in the reserved pack since the last call with the provided feature.
+    pub fn tag(&mut self, reserved_pack: &mut Pack, game: &GameInfo, feature: &str) {
+        let new_fingerprints = fingerprints(reserved_pack, game);
+
+        for (path, fingerprint) in &new_fingerprints {
+            let is_changed = match self.fingerprints.get(path) {
+                Some(old_fingerprint) => old_fingerprint != fingerprint,
+                None => true,
+            };
+
+            if is_changed {
+                let entry = self.files.entry(path.to_owned()).or_default();
+                if !entry.features.iter().any(|x| x == feature) {
+                    entry.features.push(feature.to_owned());
+                }
+
+                if let (Some(snapshot), Some(file)) = (&mut self.snapshot, reserved_pack.files().get(path)) {
+                    snapshot.insert(path.to_owned(), file.clone());
+                }
+            }
+        }
+
+        if let Some(snapshot) = &mut self.snapshot {
+            snapshot.retain(|path, _| new_fingerprints.contains_key(path));
+        }
+
+        self.fingerprints = new_fingerprints;
+    }
+
+    /// This function records if a feature succeeded or failed. Features that succeeded are only recorded if they edited the Pack.
+    pub fn set_feature_status(&mut self, feature: &str, error: Option<String>) {
+        let edited_pack = self.files.values().any(|entry| entry.features.iter().any(|x| x == feature));
+        if edited_pack || error.is_some() {
+            self.features.insert(feature.to_owned(), FeatureStatus {
+                success: error.is_none(),
+                error,
+            });
+        }
+    }
+
+    /// This function returns the features that failed, with their errors.
+    pub fn failed_features(&self) -> Vec<(&str, &str)> {
+        self.features.iter()
+            .filter_map(|(feature, status)| status.error.as_deref().map(|error| (feature.as_str(), error)))
+            .collect()
+    }
+
+    /// This function calculates the amount of rows changed in each table of the report against the table it overrides.
+    pub fn count_changed_rows(&mut self, reserved_pack: &mut Pack, vanilla_pack: &Pack, modded_pack: &Pack, schema: &Schema) {
+        let mut dec_extra_data = DecodeableExtraData::default();
+        dec_extra_data.set_schema(Some(schema));
+        let dec_extra_data = Some(dec_extra_data);
+
+        for (path, entry) in self.files.iter_mut() {
+            let rows = match reserved_pack.file_mut(path, false) {
+                Some(file) if file.file_type() == FileType::DB => table_rows(file, &dec_extra_data),
+                _ => None,
+            };
+
+            if let Some(rows) = rows {
+
+                // Vanilla tables are renamed with a ~ to give them low priority, so we need to remove it to find the original.
+                let base_path = match path.rsplit_once('/') {
+                    Some((folder, name)) => format!("{}/{}", folder, name.trim_start_matches('~')),
+                    None => path.to_owned(),
+                };
+
+                let base_rows = [modded_pack, vanilla_pack].iter()
+                    .find_map(|pack| pack.file(&base_path, false).cloned())
+                    .and_then(|mut file| table_rows(&mut file, &dec_extra_data))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+
+                entry.rows_changed = Some(rows.iter().filter(|row| !base_rows.contains(*row)).count());
+                entry.rows_total = Some(rows.len());
+            }
+        }
+    }
+
+    /// This function writes the report to the log.
+    pub fn log(&self) {
+        info!("Report of the generated Pack:");
+
+        for (path, entry) in &self.files {
+            match (entry.rows_changed, entry.rows_total) {
+                (Some(changed), Some(total)) => info!("- {}: {} ({} of {} rows changed).", path, entry.features.join(", "), changed, total),
+                _ => info!("- {}: {}.", path, entry.features.join(", ")),
+            }
+        }
+
+        let failed = self.failed_features();
+        if !failed.is_empty() {
+            error!("{} features failed:", failed.len());
+            for (feature, error) in failed {
+                error!("- {}: {}", feature, error);
+            }
+        }
+    }
+
+    /// This function saves the report to disk, as a JSON file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl PackManifest {
+
+    /// This function creates the manifest of the provided Pack, already saved to disk, using the report to know which features produced each file.
+    pub fn new(reserved_pack: &mut Pack, pack_path: &Path, game: &GameInfo, load_order: &[PathBuf], report: &Report) -> Result<Self> {
+        let pack_data = read(pack_path)?;
+        let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
+
+        let mut files = BTreeMap::new();
+        for file in reserved_pack.files_mut().values_mut() {
+            let data = file.encode(&enc_extra_data, false, false, true)?.unwrap_or_default();
+            let features = report.files.get(file.path_in_container_raw())
+                .map(|entry| entry.features.to_vec())
+                .unwrap_or_default();
+
+            files.insert(file.path_in_container_raw().to_owned(), PackManifestEntry {
+                size: data.len(),
+                hash: content_hash(&data),
+                features,
+            });
+        }
+
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            size: pack_data.len() as u64,
+            hash: content_hash(&pack_data),
+            load_order: load_order.iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .collect(),
+            files,
+        })
+    }
+
+    /// This function saves the manifest next to the Pack, as a JSON file, returning its path.
+    pub fn save(&self, pack_path: &Path) -> Result<PathBuf> {
+        let mut path = pack_path.as_os_str().to_owned();
+        path.push(PACK_MANIFEST_EXTENSION);
+        let path = PathBuf::from(path);
+
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(path)
+    }
+}
+
+impl TranslationReport {
+
+    /// This function registers the Pack a loc key comes from, so we know where keys dropped later come from.
+    pub fn track(&mut self, pack_name: &str, key: &str) {
+        self.key_packs.insert(key.to_owned(), pack_name.to_owned());
+    }
+
+    /// This function adds a key of a Pack that couldn't be translated to the report.
+    pub fn add(&mut self, pack_name: &str, key: &str, reason: &str, text: &str) {
+        self.track(pack_name, key);
+        self.packs.entry(pack_name.to_owned())
+            .or_default()
+            .push((key.to_owned(), reason.to_owned(), text.to_owned()));
+    }
+
+    /// This function adds a key dropped by the optimizer to the report, under the Pack it comes from.
+    ///
+    /// Keys not coming from any Pack (like the ones in the vanilla fixes) are added under "vanilla".
+    pub fn add_dropped(&mut self, key: &str, text: &str) {
+        let pack_name = self.key_packs.get(key).cloned().unwrap_or_else(|| "vanilla".to_owned());
+        self.packs.entry(pack_name)
+            .or_default()
+            .push((key.to_owned(), "dropped_by_optimizer".to_owned(), text.to_owned()));
+    }
+
+    /// This function saves the report to the provided folder, as a TSV file per Pack.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        DirBuilder::new().recursive(true).create(path)?;
+
+        for (pack_name, entries) in &self.packs {
+            let mut file = BufWriter::new(File::create(path.join(format!("{}.tsv", pack_name)))?);
+            file.write_all(b"key\treason\ttext\n")?;
+
+            for (key, reason, text) in entries {
+                file.write_all(format!("{}\t{}\t{}\n", escape_tsv(key), reason, escape_tsv(text)).as_bytes())?;
+            }
+        }
+
+        info!("Translation report saved to: {}.", path.display());
+        Ok(())
+    }
+}
+
+impl VanillaLocDiff {
+
+    /// This function compares the old english texts against the new ones.
+    pub fn new(old: &HashMap<String, String>, new: &HashMap<String, String>) -> Self {
+        let mut entries = BTreeMap::new();
+
+        for (key, new_text) in new {
+            match old.get(key) {
+                Some(old_text) if old_text != new_text => { entries.insert(key.to_owned(), ("changed".to_owned(), old_text.to_owned(), new_text.to_owned())); },
+                Some(_) => {},
+                None => { entries.insert(key.to_owned(), ("added".to_owned(), String::new(), new_text.to_owned())); },
+            }
+        }
+
+        for (key, old_text) in old {
+            if !new.contains_key(key) {
+                entries.insert(key.to_owned(), ("removed".to_owned(), old_text.to_owned(), String::new()));
+            }
+        }
+
+        Self {
+            entries,
+        }
+    }
+
+    /// This function returns the amount of keys with a status.
+    pub fn count(&self, status: &str) -> usize {
+        self.entries.values().filter(|(entry_status, _, _)| entry_status == status).count()
+    }
+
+    /// This function saves the differences to a TSV file. Changed keys are the ones whose translations need to be marked as needing retranslation.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(folder) = path.parent() {
+            DirBuilder::new().recursive(true).create(folder)?;
+        }
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(b"key\tstatus\told_text\tnew_text\n")?;
+
+        for (key, (status, old_text, new_text)) in &self.entries {
+            file.write_all(format!("{}\t{}\t{}\t{}\n", escape_tsv(key), status, escape_tsv(old_text), escape_tsv(new_text)).as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn escape_tsv(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+/// This function returns a fingerprint of the contents of each file of a pack.
+///
+/// Files that are only decoded are encoded to get their fingerprint, so edits to them are detected too.
+fn fingerprints(pack: &mut Pack, game: &GameInfo) -> HashMap<String, Option<u64>> {
+    let enc_extra_data = Some(EncodeableExtraData::new_from_game_info(game));
+
+    pack.files_mut()
+        .iter_mut()
+        .map(|(path, file)| {
+            let fingerprint = match file.cached() {
+                Ok(data) => Some(data_fingerprint(data)),
+                Err(_) => file.encode(&enc_extra_data, false, false, true).ok().flatten().map(|data| data_fingerprint(&data)),
+            };
+
+            (path.to_owned(), fingerprint)
+        })
+        .collect()
+}
+
+fn data_fingerprint(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// This function returns the rows of a table, as strings so they can be easily compared.
+fn table_rows(file: &mut RFile, dec_extra_data: &Option<DecodeableExtraData>) -> Option<Vec<String>> {
+    match file.decode(dec_extra_data, false, true) {
+        Ok(Some(RFileDecoded::DB(data))) => Some(data.data()
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.data_to_string().to_string()).collect::<Vec<_>>().join("\t"))
+            .collect()),
+        _ => None,
+    }
+}
@@ -0,0 +1,122 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the server mode, used by launchers to patch load orders without paying the startup cost on each patch.
+//!
+//! Requests are JSON-RPC 2.0 messages read from stdin, one per line. Responses are printed to stdout, one per line,
+//! with the progress of the running patch printed as JSON lines before them. Logs are only saved to the log file.
+
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+
+use std::fmt::Display;
+use std::io::{BufRead, stdin};
+
+use rpfm_lib::integrations::log::{info, warn};
+
+use crate::app::{PatchArgs, ProgressMode};
+use crate::logging::{set_log_section, set_quiet};
+use crate::patcher::{PatchCache, Patcher};
+use crate::progress::set_progress_mode;
+
+// Error codes defined by JSON-RPC. Errors while patching use the exit codes instead.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// This function runs the server until stdin is closed or a shutdown request is received.
+///
+/// The vanilla data and the schema of the game in the arguments are loaded at the start, so the first patch is fast too.
+pub fn serve(args: &PatchArgs) -> Result<()> {
+    set_quiet(true);
+    set_progress_mode(ProgressMode::Json);
+    set_log_section("server");
+
+    let mut cache = PatchCache::default();
+    if let Err(error) = Patcher::from_args(args.clone()).warm_cache(&mut cache) {
+        warn!("Failed to preload the data of {}. It'll be loaded on the first patch: {}", args.game, error);
+    }
+
+    info!("Server ready. Waiting for requests.");
+    notify("ready", json!({ "version": env!("CARGO_PKG_VERSION") }));
+
+    for line in stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => request,
+            Err(error) => {
+                respond(&Value::Null, Err(rpc_error(PARSE_ERROR, error)));
+                continue;
+            }
+        };
+
+        let id = request.get("id").cloned().unwrap_or_default();
+        match request.get("method").and_then(|method| method.as_str()) {
+            Some("patch") => {
+                let response = patch(args, request.get("params").unwrap_or(&Value::Null), &mut cache);
+                set_log_section("server");
+                respond(&id, response);
+            }
+            Some("shutdown") => {
+                respond(&id, Ok(Value::Null));
+                break;
+            }
+            Some(method) => respond(&id, Err(rpc_error(METHOD_NOT_FOUND, format!("Unknown method: {}.", method)))),
+            None => respond(&id, Err(rpc_error(INVALID_REQUEST, "Missing method."))),
+        }
+    }
+
+    info!("Server stopped.");
+    Ok(())
+}
+
+/// This function patches the load order with the options of a request, using the game of the server if the request doesn't have one.
+fn patch(args: &PatchArgs, params: &Value, cache: &mut PatchCache) -> Result<Value, Value> {
+    let mut options = match params {
+        Value::Object(options) => options.clone(),
+        Value::Null => Map::new(),
+        _ => return Err(rpc_error(INVALID_PARAMS, "The params of a patch request must be an object with the options to use.")),
+    };
+
+    options.entry("game").or_insert_with(|| json!(args.game));
+
+    let patch_args = PatchArgs::from_options(&options).map_err(|error| rpc_error(INVALID_PARAMS, error))?;
+    let outcome = Patcher::from_args(patch_args).run_with_cache(cache).map_err(|error| rpc_error(*error.code() as i32, error))?;
+
+    Ok(json!({
+        "pack_path": outcome.pack_path(),
+        "report": outcome.report(),
+    }))
+}
+
+fn rpc_error(code: i32, message: impl Display) -> Value {
+    json!({
+        "code": code,
+        "message": message.to_string(),
+    })
+}
+
+fn respond(id: &Value, response: Result<Value, Value>) {
+    let message = match response {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+
+    println!("{}", message);
+}
+
+fn notify(method: &str, params: Value) {
+    println!("{}", json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
@@ -0,0 +1,176 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the update support, to check the GitHub releases for new versions and install them.
+//!
+//! Release files are downloaded once, checked against the SHA-256 checksum published with them, and installed from the same downloaded data,
+//! so truncated or tampered downloads are never installed.
+
+use anyhow::{anyhow, Result};
+use getset::Getters;
+use serde_json::Value;
+use ureq::{Agent, AgentBuilder, Proxy};
+use zip::ZipArchive;
+
+use std::fs::{DirBuilder, File, rename};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use rpfm_lib::integrations::log::info;
+
+use crate::utils::{content_hash, proxy_url};
+
+// Extension of the release assets with the SHA-256 checksum of each release file.
+const UPDATE_CHECKSUM_EXTENSION: &str = ".sha256";
+
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Updater for the releases of a GitHub repo.
+pub struct Updater {
+    agent: Agent,
+    owner: String,
+    repo: String,
+
+    /// If beta releases should be considered too.
+    beta: bool,
+}
+
+/// Release available to update to, with the files for this platform.
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct Release {
+    version: String,
+    file_name: String,
+    file_url: String,
+
+    /// Url of the checksum of the release file, if one was published.
+    checksum_url: Option<String>,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl Updater {
+
+    /// This function creates a new updater for the provided repo, using the proxy if there's one.
+    pub fn new(owner: &str, repo: &str, beta: bool, proxy: Option<&str>) -> Result<Self> {
+        let mut agent = AgentBuilder::new();
+        if let Some(proxy) = proxy_url(proxy) {
+            agent = agent.proxy(Proxy::new(proxy)?);
+        }
+
+        Ok(Self {
+            agent: agent.build(),
+            owner: owner.to_owned(),
+            repo: repo.to_owned(),
+            beta,
+        })
+    }
+
+    /// This function returns the latest release of the repo, if it's newer than the provided version.
+    ///
+    /// Releases without a file for this platform are ignored, as only some platforms get releases.
+    pub fn check(&self, current_version: &str) -> Result<Option<Release>> {
+        let releases: Value = self.agent.get(&format!("https://api.github.com/repos/{}/{}/releases", self.owner, self.repo))
+            .set("User-Agent", &self.repo)
+            .call()?
+            .into_json()?;
+
+        let release = releases.as_array()
+            .and_then(|releases| releases.iter().find(|release| !release["draft"].as_bool().unwrap_or_default() && (self.beta || !release["prerelease"].as_bool().unwrap_or_default())))
+            .ok_or_else(|| anyhow!("No release found in {}/{}.", self.owner, self.repo))?;
+
+        let version = release["tag_name"].as_str().unwrap_or_default().trim_start_matches('v').to_owned();
+        if version_number(&version)? <= version_number(current_version)? {
+            return Ok(None);
+        }
+
+        let assets = release["assets"].as_array().cloned().unwrap_or_default();
+        let asset_url = |name: &str| assets.iter()
+            .find(|asset| asset["name"].as_str() == Some(name))
+            .and_then(|asset| asset["browser_download_url"].as_str())
+            .map(|url| url.to_owned());
+
+        // Release files are named after the target they're built for, like x86_64-pc-windows-msvc.
+        let file_name = match assets.iter()
+            .filter_map(|asset| asset["name"].as_str())
+            .find(|name| !name.ends_with(UPDATE_CHECKSUM_EXTENSION) && name.contains(std::env::consts::OS) && name.contains(std::env::consts::ARCH)) {
+            Some(file_name) => file_name,
+            None => {
+                info!("- Version {} has no release for this platform.", version);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(Release {
+            version,
+            file_name: file_name.to_owned(),
+            file_url: asset_url(file_name).ok_or_else(|| anyhow!("No download found for {}.", file_name))?,
+            checksum_url: asset_url(&format!("{}{}", file_name, UPDATE_CHECKSUM_EXTENSION)),
+        }))
+    }
+
+    /// This function downloads the provided release, checks it against its checksum, and installs it in the program folder.
+    ///
+    /// The replaced files are moved to an update folder, which is cleaned on the next start, as a running executable cannot be overwritten on Windows.
+    pub fn install(&self, release: &Release, program_path: &Path) -> Result<()> {
+        let checksum_url = release.checksum_url.as_ref().ok_or_else(|| anyhow!("No checksum published for {}.", release.file_name))?;
+
+        // The checksum file may be in the sha256sum format, with the file name after the checksum.
+        let checksum = self.agent.get(checksum_url).call()?.into_string()?;
+        let checksum = checksum.split_whitespace().next().unwrap_or_default().to_lowercase();
+
+        let mut data = vec![];
+        self.agent.get(&release.file_url).call()?.into_reader().read_to_end(&mut data)?;
+
+        let file_checksum = content_hash(&data);
+        if file_checksum != checksum {
+            return Err(anyhow!("Checksum mismatch for {}: expected {}, got {}.", release.file_name, checksum, file_checksum));
+        }
+
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        let old_files_path = program_path.join(format!("update_{}", env!("CARGO_PKG_VERSION")));
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+
+            let relative_path = file.enclosed_name().ok_or_else(|| anyhow!("Invalid path in {}: {}.", release.file_name, file.name()))?;
+            let dest_path = program_path.join(&relative_path);
+            if dest_path.is_file() {
+                let old_path = old_files_path.join(&relative_path);
+                if let Some(parent) = old_path.parent() {
+                    DirBuilder::new().recursive(true).create(parent)?;
+                }
+
+                rename(&dest_path, &old_path)?;
+            }
+
+            if let Some(parent) = dest_path.parent() {
+                DirBuilder::new().recursive(true).create(parent)?;
+            }
+
+            std::io::copy(&mut file, &mut File::create(&dest_path)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// This function turns a version like 0.9.11 into a list of numbers, so versions can be compared.
+fn version_number(version: &str) -> Result<Vec<u32>> {
+    version.split('.')
+        .map(|number| number.parse().map_err(|_| anyhow!("Invalid version: {}.", version)))
+        .collect()
+}
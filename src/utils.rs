@@ -10,28 +10,229 @@
 
 use anyhow::{anyhow, Result};
 use directories::ProjectDirs;
+use git2::{build::{CheckoutBuilder, RepoBuilder}, FetchOptions, ProxyOptions, Repository, ResetType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 
-use std::fs::File;
-use std::io::{BufReader, Cursor, Read};
+use std::collections::HashMap;
+use std::fs::{copy, DirBuilder, File, read_dir, read_to_string, remove_dir_all, rename};
+use std::io::{BufReader, BufWriter, Cursor, Read, stdin, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
 
 use rpfm_lib::binary::ReadBytes;
-use rpfm_lib::files::{EncodeableExtraData, pack::Pack};
+use rpfm_lib::compression::CompressionFormat;
+use rpfm_lib::files::{Container, ContainerPath, EncodeableExtraData, loc::Loc, pack::Pack, RFile, RFileDecoded, table::DecodedData};
 use rpfm_lib::games::{GameInfo, pfh_file_type::PFHFileType, supported_games::*};
-use rpfm_lib::integrations::log::info;
+use rpfm_lib::integrations::{git::GitIntegration, log::{info, warn}};
 use rpfm_lib::utils::{files_from_subdir, path_to_absolute_path};
 
+use crate::app::{LoadOrderFormat, PackCompression, PackDependencies};
+use crate::install_locator::game_config_path;
+use crate::PROGRAM_PATH;
+
 // Default generated pack names. These are tested and work on their respective games.
 pub const RESERVED_PACK_NAME: &str = "zzzzzzzzzzzzzzzzzzzzrun_you_fool_thron.pack";
 pub const RESERVED_PACK_NAME_ALTERNATIVE: &str = "!!!!!!!!!!!!!!!!!!!!!run_you_fool_thron.pack";
 
 const SCHEMAS_FOLDER: &str = "schemas";
+const SCHEMAS_CUSTOM_FOLDER: &str = "schemas_custom";
 const TRANSLATIONS_LOCAL_FOLDER: &str = "translations_local";
 const TRANSLATIONS_REMOTE_FOLDER: &str = "translations_remote";
 
+const MOVIE_SCAN_CACHE_FILE_NAME: &str = "movie_scan_cache.json";
+
+const VANILLA_INDEX_FOLDER: &str = "vanilla_index";
+
+const TRANSLATION_CACHE_FOLDER: &str = "translation_cache";
+
+const RUNCHER_PROFILES_FOLDER: &str = "profiles";
+
+const UPDATE_BACKUPS_FOLDER: &str = "update_backups";
+
+// Amount of previous versions kept in the update backups folder.
+const UPDATE_BACKUPS_LIMIT: usize = 3;
+
+const SQL_PRESETS_FOLDER: &str = "sql_presets";
+const SQL_SCRIPT_EXTENSION: &str = "sql";
+const SQL_PARAMS_EXTENSION: &str = "params";
+
+// Portable mode keeps the config in this folder, next to the executable. It's enabled by this file, or by --portable.
+const PORTABLE_CONFIG_FOLDER: &str = "config";
+const PORTABLE_MARKER_FILE_NAME: &str = "portable.txt";
+
+/// If we're running in portable mode.
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+// Environment variable with the proxy to use in network operations.
+const PROXY_VAR: &str = "TWPATCHER_PROXY";
+
+const STEAM_APP_ID_FILE_NAME: &str = "steam_appid.txt";
+
 const USER_SCRIPT_FILE_NAME: &str = "user.script.txt";
 const USER_SCRIPT_EMPIRE_FILE_NAME: &str = "user.empire_script.txt";
 
+//-------------------------------------------------------------------------------//
+//                              Enums & Structs
+//-------------------------------------------------------------------------------//
+
+/// Cache of the Packs checked when searching for movie Packs, so we don't need to open them again if they didn't change.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct MovieScanCache {
+    packs: HashMap<String, MovieScanCacheEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct MovieScanCacheEntry {
+
+    /// Last modification time of the Pack, in milliseconds since the unix epoch.
+    modified: u64,
+    is_movie: bool,
+}
+
+/// Index of the vanilla files of a game, so we don't need to read all the vanilla Packs on each run.
+#[derive(Debug, Deserialize, Serialize)]
+struct VanillaIndex {
+
+    /// Last modification time of the game's executable when the index was built, in milliseconds since the unix epoch.
+    exe_modified: u64,
+
+    /// Path, size and last modification time of each CA Pack when the index was built, as DLCs and hotfixes may change them without touching the executable.
+    ca_packs: Vec<(PathBuf, u64, u64)>,
+    files: HashMap<String, RFile>,
+}
+
+/// Cache of the translated loc of a game, so we don't need to rebuild it if nothing changed since the last run.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TranslationCache {
+
+    /// Hash of everything used to build the loc: load order, languages, translations and options.
+    key: String,
+    path: String,
+    rows: Vec<(String, String, bool)>,
+}
+
+//-------------------------------------------------------------------------------//
+//                             Implementations
+//-------------------------------------------------------------------------------//
+
+impl MovieScanCache {
+
+    /// This function loads the cache from the config folder. If it cannot be loaded, an empty cache is returned.
+    fn load() -> Self {
+        config_path()
+            .and_then(|path| read_to_string(path.join(MOVIE_SCAN_CACHE_FILE_NAME)).map_err(From::from))
+            .and_then(|data| serde_json::from_str(&data).map_err(From::from))
+            .unwrap_or_default()
+    }
+
+    /// This function saves the cache to the config folder, dropping the entries of Packs that no longer exist.
+    fn save(&mut self) -> Result<()> {
+        self.packs.retain(|path, _| Path::new(path).is_file());
+
+        let path = config_path()?;
+        DirBuilder::new().recursive(true).create(&path)?;
+        let mut file = BufWriter::new(File::create(path.join(MOVIE_SCAN_CACHE_FILE_NAME))?);
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// This function checks if a Pack is a movie Pack, using the cached result if the Pack didn't change since it was cached.
+    fn is_movie(&mut self, path: &Path, game: &GameInfo) -> bool {
+        let modified = match path.metadata().and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified.duration_since(UNIX_EPOCH).map(|time| time.as_millis() as u64).unwrap_or_default(),
+            Err(_) => return false,
+        };
+
+        let key = path.to_string_lossy().to_string();
+        if let Some(entry) = self.packs.get(&key) {
+            if entry.modified == modified {
+                return entry.is_movie;
+            }
+        }
+
+        let is_movie = match Pack::read_and_merge(&[path.to_path_buf()], game, true, false, false) {
+            Ok(pack) => pack.pfh_file_type() == PFHFileType::Movie,
+            Err(_) => return false,
+        };
+
+        self.packs.insert(key, MovieScanCacheEntry { modified, is_movie });
+        is_movie
+    }
+}
+
+impl VanillaIndex {
+
+    /// This function saves the index to the provided path.
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(folder) = path.parent() {
+            DirBuilder::new().recursive(true).create(folder)?;
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(file, self)?;
+        Ok(())
+    }
+}
+
+impl TranslationCache {
+
+    /// This function creates a new cache for the provided loc rows.
+    pub fn new(key: &str, path: &str, rows: &[Vec<DecodedData>]) -> Self {
+        Self {
+            key: key.to_owned(),
+            path: path.to_owned(),
+            rows: rows.iter()
+                .map(|row| (row[0].data_to_string().to_string(), row[1].data_to_string().to_string(), matches!(row[2], DecodedData::Boolean(true))))
+                .collect(),
+        }
+    }
+
+    /// This function loads the cache of the provided game, if it exists and it was built with the same key.
+    pub fn load(game: &GameInfo, key: &str) -> Option<Self> {
+        let file = File::open(Self::path(game).ok()?).ok()?;
+        bincode::deserialize_from::<_, Self>(BufReader::new(file)).ok()
+            .filter(|cache| cache.key == key)
+    }
+
+    /// This function saves the cache of the provided game to the config folder.
+    pub fn save(&self, game: &GameInfo) -> Result<()> {
+        let path = Self::path(game)?;
+        if let Some(folder) = path.parent() {
+            DirBuilder::new().recursive(true).create(folder)?;
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(file, self)?;
+        Ok(())
+    }
+
+    /// This function turns the cached rows back into a loc file.
+    pub fn to_rfile(&self) -> Result<RFile> {
+        let data = self.rows.iter()
+            .map(|(key, text, tooltip)| vec![
+                DecodedData::StringU16(key.to_owned()),
+                DecodedData::StringU16(text.to_owned()),
+                DecodedData::Boolean(*tooltip),
+            ])
+            .collect::<Vec<_>>();
+
+        let mut loc = Loc::new();
+        loc.set_data(&data)?;
+        Ok(RFile::new_from_decoded(&RFileDecoded::Loc(loc), 0, &self.path))
+    }
+
+    pub fn path_in_container(&self) -> &str {
+        &self.path
+    }
+
+    fn path(game: &GameInfo) -> Result<PathBuf> {
+        config_path().map(|path| path.join(TRANSLATION_CACHE_FOLDER).join(format!("{}.bin", game.key())))
+    }
+}
+
 //-------------------------------------------------------------------------------//
 //                             Util functions.
 //-------------------------------------------------------------------------------//
@@ -44,14 +245,45 @@ pub fn translations_remote_path() -> Result<PathBuf> {
     config_path().map(|path| path.join(TRANSLATIONS_REMOTE_FOLDER))
 }
 
+pub fn sql_presets_path() -> Result<PathBuf> {
+    config_path().map(|path| path.join(SQL_PRESETS_FOLDER))
+}
+
 pub fn schemas_path() -> Result<PathBuf> {
     config_path().map(|path| path.join(SCHEMAS_FOLDER))
 }
 
+/// This function returns the folder where a custom schemas repo is cloned. Each repo gets its own folder.
+pub fn custom_schemas_path(repo_url: &str) -> Result<PathBuf> {
+    let folder_name = repo_url.chars()
+        .map(|character| if character.is_ascii_alphanumeric() { character } else { '_' })
+        .collect::<String>();
+
+    config_path().map(|path| path.join(SCHEMAS_CUSTOM_FOLDER).join(folder_name))
+}
+
+/// This function enables portable mode if it has been requested through the CLI, or through a portable.txt file next to the executable.
+///
+/// It needs to be called before anything reads the config folder, as portable mode moves it next to the executable.
+pub fn init_portable_mode() -> bool {
+    let portable = std::env::args().any(|arg| arg == "--portable") || PROGRAM_PATH.join(PORTABLE_MARKER_FILE_NAME).is_file();
+    PORTABLE.store(portable, Ordering::Relaxed);
+    portable
+}
+
+/// This function returns if we're running in portable mode.
+pub fn is_portable() -> bool {
+    PORTABLE.load(Ordering::Relaxed)
+}
+
 /// This function returns the current config path, or an error if said path is not available.
 ///
 /// Note: On `Debug´ mode this project is the project from where you execute one of RPFM's programs, which should be the root of the repo.
 pub fn config_path() -> Result<PathBuf> {
+    if PORTABLE.load(Ordering::Relaxed) {
+        return Ok(PROGRAM_PATH.join(PORTABLE_CONFIG_FOLDER));
+    }
+
     if cfg!(debug_assertions) { std::env::current_dir().map_err(From::from) } else {
         {
             match ProjectDirs::from("com", "FrodoWazEre", "twpatcher") {
@@ -62,6 +294,103 @@ pub fn config_path() -> Result<PathBuf> {
     }
 }
 
+pub fn update_backups_path() -> Result<PathBuf> {
+    Ok(config_path()?.join(UPDATE_BACKUPS_FOLDER))
+}
+
+/// This function copies the files of the program folder to a backup folder for the provided version, so they can be restored if an update breaks something.
+///
+/// Only the most recent backups are kept.
+pub fn backup_program(program_path: &Path, version: &str) -> Result<PathBuf> {
+    let backup_path = update_backups_path()?.join(version);
+    for path in program_files(program_path)? {
+        let dest_path = backup_path.join(path.strip_prefix(program_path)?);
+        if let Some(parent) = dest_path.parent() {
+            DirBuilder::new().recursive(true).create(parent)?;
+        }
+
+        copy(&path, &dest_path)?;
+    }
+
+    let mut backups = update_backups()?;
+    while backups.len() > UPDATE_BACKUPS_LIMIT {
+        let (_, oldest) = backups.remove(0);
+        remove_dir_all(oldest)?;
+    }
+
+    Ok(backup_path)
+}
+
+/// This function restores the program folder from the most recent backup, returning the restored version.
+///
+/// The current files are moved to an update folder, which is cleaned on the next start, as a running executable cannot be overwritten on Windows.
+pub fn rollback_program(program_path: &Path) -> Result<String> {
+    let (version, backup_path) = update_backups()?
+        .pop()
+        .ok_or_else(|| anyhow!("No previous version found to rollback to."))?;
+
+    let old_files_path = program_path.join(format!("update_rollback_{}", env!("CARGO_PKG_VERSION")));
+    for path in program_files(program_path)? {
+        let dest_path = old_files_path.join(path.strip_prefix(program_path)?);
+        if let Some(parent) = dest_path.parent() {
+            DirBuilder::new().recursive(true).create(parent)?;
+        }
+
+        rename(&path, &dest_path)?;
+    }
+
+    for path in files_from_subdir(&backup_path, true)? {
+        let dest_path = program_path.join(path.strip_prefix(&backup_path)?);
+        if let Some(parent) = dest_path.parent() {
+            DirBuilder::new().recursive(true).create(parent)?;
+        }
+
+        copy(&path, &dest_path)?;
+    }
+
+    remove_dir_all(&backup_path)?;
+    Ok(version)
+}
+
+/// This function returns the files of the program folder, excluding the leftovers of previous updates.
+fn program_files(program_path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(files_from_subdir(program_path, true)?
+        .into_iter()
+        .filter(|path| path.strip_prefix(program_path)
+            .ok()
+            .and_then(|path| path.components().next())
+            .map(|component| !component.as_os_str().to_string_lossy().starts_with("update"))
+            .unwrap_or(false))
+        .collect())
+}
+
+/// This function returns the available update backups, as (version, path), sorted from oldest to newest.
+fn update_backups() -> Result<Vec<(String, PathBuf)>> {
+    let backups_path = update_backups_path()?;
+    if !backups_path.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = read_dir(backups_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path.file_name()?.to_string_lossy().to_string(), path)))
+        .collect::<Vec<_>>();
+
+    backups.sort_by_key(|(modified, _, _)| *modified);
+    Ok(backups.into_iter().map(|(_, version, path)| (version, path)).collect())
+}
+
+pub fn runcher_config_path() -> Result<PathBuf> {
+    if cfg!(debug_assertions) { std::env::current_dir().map_err(From::from) } else {
+        match ProjectDirs::from("com", "FrodoWazEre", "runcher") {
+            Some(proj_dirs) => Ok(proj_dirs.config_dir().to_path_buf()),
+            None => Err(anyhow!("Failed to get Runcher's config path."))
+        }
+    }
+}
+
 pub fn rpfm_config_path() -> Result<PathBuf> {
     if cfg!(debug_assertions) { std::env::current_dir().map_err(From::from) } else {
         match ProjectDirs::from("com", "FrodoWazEre", "rpfm") {
@@ -71,23 +400,144 @@ pub fn rpfm_config_path() -> Result<PathBuf> {
     }
 }
 
-/// This function returns the paths of all the modded packs, in the order they're loaded.
-pub fn load_order_from_file(load_order_path: &Path, game: &GameInfo, game_path: &Path, data_path: &Path) -> Result<Vec<PathBuf>> {
+/// This function checks out the provided revision of a git repo, leaving it in a detached state.
+///
+/// If no revision is provided and the repo is in a detached state, the repo is returned to the provided branch.
+pub fn checkout_repo_revision(repo_path: &Path, revision: Option<&str>, branch: &str) -> Result<()> {
+    let repo = Repository::open(repo_path)?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force();
 
-    // Note: Shogun 2 can be utf_16, but we assume people has the last version, where the file is utf_8.
-    let (load_order_path, is_utf_16) = if *game.raw_db_version() >= 1 {
-        (load_order_path.to_path_buf(), false)
+    match revision {
+        Some(revision) => {
+            let object = repo.revparse_single(revision).map_err(|error| anyhow!("Revision {} not found in {}: {}", revision, repo_path.display(), error))?;
+            repo.checkout_tree(&object, Some(&mut checkout))?;
+            repo.set_head_detached(object.peel_to_commit()?.id())?;
+        }
+        None => if repo.head_detached()? {
+            repo.set_head(&format!("refs/heads/{}", branch))?;
+            repo.checkout_head(Some(&mut checkout))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// This function returns the proxy to use in network operations, if any.
+///
+/// The provided one takes priority. Otherwise, it's read from the TWPATCHER_PROXY environment variable, falling back to the usual proxy variables.
+pub fn proxy_url(proxy: Option<&str>) -> Option<String> {
+    proxy.filter(|proxy| !proxy.is_empty())
+        .map(|proxy| proxy.to_owned())
+        .or_else(|| [PROXY_VAR, "HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|proxy| !proxy.is_empty())))
+}
+
+/// This function downloads or updates the provided git repo, using the proxy if there's one.
+///
+/// If the repo was cloned from another url (for example, when switching to a mirror), the remote is updated to the new url.
+pub fn update_repo(local_path: &Path, url: &str, branch: &str, remote: &str, proxy: Option<&str>) -> Result<()> {
+    let repo = Repository::open(local_path).ok();
+    if let Some(ref repo) = repo {
+        if repo.find_remote(remote).ok().and_then(|remote| remote.url().map(|url| url.to_owned())).as_deref() != Some(url) {
+            repo.remote_set_url(remote, url)?;
+        }
+    }
+
+    let proxy = match proxy_url(proxy) {
+        Some(proxy) => proxy,
+        None => return GitIntegration::new(local_path, url, branch, remote).update_repo().map(|_| ()).map_err(From::from),
+    };
+
+    let mut proxy_options = ProxyOptions::new();
+    proxy_options.url(&proxy);
+
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.proxy_options(proxy_options);
+
+    match repo {
+        Some(repo) => {
+            repo.find_remote(remote)?.fetch(&[branch], Some(&mut fetch_options), None)?;
+
+            let commit = repo.find_reference(&format!("refs/remotes/{}/{}", remote, branch))?.peel_to_commit()?;
+            let mut checkout = CheckoutBuilder::new();
+            checkout.force();
+
+            repo.reset(commit.as_object(), ResetType::Hard, Some(&mut checkout))?;
+        }
+        None => {
+            DirBuilder::new().recursive(true).create(local_path)?;
+            RepoBuilder::new()
+                .branch(branch)
+                .fetch_options(fetch_options)
+                .clone(url, local_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// This function checks if the provided text matches a simple glob pattern.
+///
+/// Only `*` (any amount of characters) and `?` (one character) are supported. Matching is case-insensitive.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let mut pattern_pos = 0;
+    let mut text_pos = 0;
+    let mut last_star = None;
+    let mut last_star_text_pos = 0;
+
+    while text_pos < text.len() {
+        if pattern_pos < pattern.len() && (pattern[pattern_pos] == '?' || pattern[pattern_pos] == text[text_pos]) {
+            pattern_pos += 1;
+            text_pos += 1;
+        } else if pattern_pos < pattern.len() && pattern[pattern_pos] == '*' {
+            last_star = Some(pattern_pos);
+            last_star_text_pos = text_pos;
+            pattern_pos += 1;
+        } else if let Some(star_pos) = last_star {
+            pattern_pos = star_pos + 1;
+            last_star_text_pos += 1;
+            text_pos = last_star_text_pos;
+        } else {
+            return false;
+        }
+    }
+
+    // Trailing stars match the empty string.
+    pattern[pattern_pos..].iter().all(|character| *character == '*')
+}
+
+/// This function returns the path of the load order file of the game.
+///
+/// Older games always load the mods from the user script in their config folder, so the provided file name is only used by the newer ones.
+pub fn load_order_file_path(load_order_file_name: &str, game: &GameInfo, game_path: &Path) -> Result<PathBuf> {
+    if *game.raw_db_version() >= 1 {
+        Ok(game_path.join(load_order_file_name))
     } else {
-        let config_path = game.config_path(game_path).ok_or(anyhow!("Error getting the game's config path."))?;
+        let config_path = game_config_path(game, game_path).ok_or(anyhow!("Error getting the game's config path."))?;
         let scripts_path = config_path.join("scripts");
 
         // Empire has its own user script.
         if game.key() == KEY_EMPIRE {
-            (scripts_path.join(USER_SCRIPT_EMPIRE_FILE_NAME), true)
+            Ok(scripts_path.join(USER_SCRIPT_EMPIRE_FILE_NAME))
         } else {
-            (scripts_path.join(USER_SCRIPT_FILE_NAME), true)
+            Ok(scripts_path.join(USER_SCRIPT_FILE_NAME))
         }
-    };
+    }
+}
+
+/// This function returns the paths of all the modded packs, in the order they're loaded.
+///
+/// The load order path is expected to come from [load_order_file_path]. Load order files in formats other than the game's user script
+/// are converted to an user script before processing them.
+pub fn load_order_from_file(load_order_path: &Path, load_order_format: LoadOrderFormat, game: &GameInfo, game_path: &Path, data_path: &Path, movie_scan: bool) -> Result<Vec<PathBuf>> {
+
+    // Note: Shogun 2 can be utf_16, but we assume people has the last version, where the file is utf_8.
+    let is_utf_16 = *game.raw_db_version() < 1;
 
     let mut file = BufReader::new(File::open(load_order_path)?);
     let string = if is_utf_16 {
@@ -101,6 +551,137 @@ pub fn load_order_from_file(load_order_path: &Path, game: &GameInfo, game_path:
         string
     };
 
+    // Older games always use the user script, so the format only matters for the newer ones.
+    let string = if is_utf_16 {
+        string
+    } else {
+        match load_order_format {
+            LoadOrderFormat::Ca => string,
+            LoadOrderFormat::Wh3mm => script_from_wh3mm(&string, game_path)?,
+            LoadOrderFormat::Kmm => script_from_kmm(&string, game_path),
+            LoadOrderFormat::TxtList => script_from_txt_list(&string),
+        }
+    };
+
+    load_order_from_script(&string, game, game_path, data_path, movie_scan)
+}
+
+/// This function converts a WH3 Mod Manager load order export to an user script.
+///
+/// The export is a JSON list of mods, each one with its pack `name` and, optionally, an `isEnabled` flag.
+fn script_from_wh3mm(string: &str, game_path: &Path) -> Result<String> {
+    let mods: Value = serde_json::from_str(string).map_err(|error| anyhow!("Error parsing WH3 Mod Manager load order: {}", error))?;
+    let mods = mods.as_array().ok_or_else(|| anyhow!("Error parsing WH3 Mod Manager load order: expected a list of mods."))?;
+
+    let mut script = workshop_working_directories(game_path);
+    for entry in mods {
+        let is_enabled = entry.get("isEnabled").and_then(|value| value.as_bool()).unwrap_or(true);
+        if let Some(pack_name) = entry.get("name").and_then(|value| value.as_str()) {
+            if is_enabled {
+                script.push_str(&format!("mod \"{}\";\n", pack_name));
+            }
+        }
+    }
+
+    Ok(script)
+}
+
+/// This function converts a Kaedrin Mod Manager load order export to an user script.
+///
+/// The export is an XML file with a `<mod>` element per pack, with `name` and `enabled` attributes.
+fn script_from_kmm(string: &str, game_path: &Path) -> String {
+    let mut script = workshop_working_directories(game_path);
+    for element in string.split("<mod ").skip(1).filter_map(|element| element.split_once('>').map(|(attributes, _)| attributes)) {
+        let is_enabled = xml_attribute(element, "enabled").map(|value| value != "false").unwrap_or(true);
+        if let Some(pack_name) = xml_attribute(element, "name") {
+            if is_enabled {
+                script.push_str(&format!("mod \"{}\";\n", pack_name));
+            }
+        }
+    }
+
+    script
+}
+
+/// This function converts a plain list of Packs or Workshop item ids to an user script.
+fn script_from_txt_list(string: &str) -> String {
+    string.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|pack_name| format!("mod \"{}\";\n", pack_name))
+        .collect()
+}
+
+/// This function returns the value of an attribute from the attribute list of a XML element.
+fn xml_attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let start = attributes.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = attributes[start..].find('"')?;
+    Some(&attributes[start..start + end])
+}
+
+/// This function returns the Workshop item folders of the game as working directories for an user script.
+///
+/// Mod managers don't copy Workshop mods to /data, so we need them to find the packs referenced by name.
+fn workshop_working_directories(game_path: &Path) -> String {
+    let mut script = String::new();
+    if let Ok(workshop_path) = workshop_content_path(game_path) {
+        if let Ok(entries) = read_dir(workshop_path) {
+            for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+                script.push_str(&format!("add_working_directory \"{}\";\n", entry.path().to_string_lossy()));
+            }
+        }
+    }
+
+    script
+}
+
+/// This function returns the path of one of Runcher's profiles for the provided game.
+pub fn runcher_profile_path(profile_name: &str, game: &GameInfo) -> Result<PathBuf> {
+    Ok(runcher_config_path()?.join(RUNCHER_PROFILES_FOLDER).join(format!("{}_{}.json", game.key(), profile_name)))
+}
+
+/// This function reads the load order from one of Runcher's profiles for the provided game.
+///
+/// The profile's mod list is turned into an user script, so it goes through the same logic as a load order file.
+pub fn load_order_from_runcher_profile(profile_name: &str, game: &GameInfo, game_path: &Path, data_path: &Path, movie_scan: bool) -> Result<Vec<PathBuf>> {
+    let profile_path = runcher_profile_path(profile_name, game)?;
+    let profile: Value = serde_json::from_str(&read_to_string(&profile_path)
+        .map_err(|error| anyhow!("Error reading Runcher profile {}: {}", profile_path.display(), error))?)?;
+
+    let mods = profile.get("load_order")
+        .and_then(|load_order| load_order.get("mods"))
+        .and_then(|mods| mods.as_array())
+        .ok_or_else(|| anyhow!("Runcher profile {} doesn't contain a load order.", profile_path.display()))?;
+
+    let mut script = workshop_working_directories(game_path);
+    for pack_name in mods.iter().filter_map(|pack_name| pack_name.as_str()) {
+        script.push_str(&format!("mod \"{}\";\n", pack_name));
+    }
+
+    load_order_from_script(&script, game, game_path, data_path, movie_scan)
+}
+
+/// This function returns the paths of the provided Packs or Workshop items, in the order they were provided.
+pub fn load_order_from_list(pack_names: &[String], game: &GameInfo, game_path: &Path, data_path: &Path, movie_scan: bool) -> Result<Vec<PathBuf>> {
+    let script = script_from_txt_list(&pack_names.join("\n"));
+    load_order_from_script(&script, game, game_path, data_path, movie_scan)
+}
+
+/// This function returns the paths of the Packs listed in the provided file, or in stdin if the path is -.
+pub fn load_order_from_list_file(list_path: &Path, game: &GameInfo, game_path: &Path, data_path: &Path, movie_scan: bool) -> Result<Vec<PathBuf>> {
+    let mut string = String::new();
+    if list_path == Path::new("-") {
+        stdin().read_to_string(&mut string)?;
+    } else {
+        string = read_to_string(list_path)?;
+    }
+
+    load_order_from_script(&script_from_txt_list(&string), game, game_path, data_path, movie_scan)
+}
+
+/// This function returns the paths of the Packs in the provided user script, in load order.
+fn load_order_from_script(string: &str, game: &GameInfo, game_path: &Path, data_path: &Path, movie_scan: bool) -> Result<Vec<PathBuf>> {
+
     // First, get all working paths.
     let mut working_paths = vec![data_path.to_path_buf()];
     working_paths.append(&mut string.lines()
@@ -108,14 +689,39 @@ pub fn load_order_from_file(load_order_path: &Path, game: &GameInfo, game_path:
         .map(|x| path_to_absolute_path(&PathBuf::from(x[23..x.len() - 2].trim().to_owned()), true))
         .collect::<Vec<_>>());
 
-    let mut mod_paths = string.lines()
+    // Workshop items can be referenced by their id. We only resolve them if needed, because finding the appid is not free.
+    let mut workshop_path = None;
+    let mut mod_paths = vec![];
+    for pack_name in string.lines()
         .filter(|x| x.starts_with("mod \""))
-        .map(|x| x[5..x.len() - 2].trim().to_owned())
-        .filter_map(|pack_name| working_paths.iter()
-            .position(|path| path.join(&pack_name).is_file())
-            .map(|x| working_paths[x].join(&pack_name))
-        )
-        .collect::<Vec<_>>();
+        .map(|x| x[5..x.len() - 2].trim().to_owned()) {
+
+        if is_workshop_id(&pack_name) {
+            if workshop_path.is_none() {
+                workshop_path = Some(workshop_content_path(game_path)?);
+            }
+
+            if let Some(ref workshop_path) = workshop_path {
+                mod_paths.append(&mut workshop_item_packs(workshop_path, &pack_name)?);
+            }
+        } else {
+            let mut paths = working_paths.iter()
+                .map(|path| path.join(&pack_name))
+                .filter(|path| path.is_file());
+
+            // Packs not found are kept with their /data path, so they're reported when validating the load order.
+            match paths.next() {
+                Some(path) => {
+                    for shadowed_path in paths {
+                        warn!("- {} shadows {}, which has the same name and will be ignored.", path.display(), shadowed_path.display());
+                    }
+
+                    mod_paths.push(path);
+                }
+                None => mod_paths.push(data_path.join(&pack_name)),
+            }
+        }
+    }
 
     let excluded_movie_paths = string.lines()
         .filter(|x| x.starts_with("exclude_pack_file \""))
@@ -127,32 +733,151 @@ pub fn load_order_from_file(load_order_path: &Path, game: &GameInfo, game_path:
         .collect::<Vec<_>>();
 
     // We need to get the movie packs. Instead of checking every pack, we check the ones not already in the mod list, and not known as CA paths.
-    let vanilla_paths = game.ca_packs_paths(game_path)?
-        .iter()
-        .map(|x| path_to_absolute_path(x, true))
-        .collect::<Vec<_>>();
+    //
+    // As opening packs is slow on big mod folders, the result for each pack is cached until the pack changes.
+    if movie_scan {
+        let vanilla_paths = game.ca_packs_paths(game_path)?
+            .iter()
+            .map(|x| path_to_absolute_path(x, true))
+            .collect::<Vec<_>>();
+
+        let mut cache = MovieScanCache::load();
+
+        // /data is already included here.
+        for working_path in &working_paths {
+            if let Ok(mut paths) = files_from_subdir(working_path, false) {
+                paths.retain(|x| x.extension().is_some() && x.extension().unwrap() == "pack");
+                paths.iter_mut().for_each(|x| *x = path_to_absolute_path(x, true));
 
-    // /data is already included here.
-    for working_path in &working_paths {
-        if let Ok(mut paths) = files_from_subdir(working_path, false) {
-            paths.retain(|x| x.extension().is_some() && x.extension().unwrap() == "pack");
-            paths.iter_mut().for_each(|x| *x = path_to_absolute_path(x, true));
-
-            for path in &paths {
-                if !mod_paths.contains(path) && !vanilla_paths.contains(path) && !excluded_movie_paths.contains(path) {
-                    if let Ok(pack) = Pack::read_and_merge(&[path.to_path_buf()], game, true, false, false) {
-                        if pack.pfh_file_type() == PFHFileType::Movie {
-                            mod_paths.push(path.to_path_buf());
-                        }
+                for path in &paths {
+                    if !mod_paths.contains(path) && !vanilla_paths.contains(path) && !excluded_movie_paths.contains(path) && cache.is_movie(path, game) {
+                        mod_paths.push(path.to_path_buf());
                     }
                 }
             }
         }
+
+        if let Err(error) = cache.save() {
+            warn!("Failed to save the movie scan cache: {}", error);
+        }
     }
 
     Ok(mod_paths)
 }
 
+/// This function checks that all the Packs in the load order exist, can be read, are for the provided game,
+/// and that there are no Packs with the same name in it.
+///
+/// Problems are reported as warnings, and the offending Packs are removed from the load order.
+/// If `strict` is true, problems are returned as an error instead.
+pub fn validate_load_order(load_order: Vec<PathBuf>, game: &GameInfo, strict: bool) -> Result<Vec<PathBuf>> {
+    let mut issues = vec![];
+    let mut valid_paths = vec![];
+
+    for (index, path) in load_order.iter().enumerate() {
+        let pack_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+        let issue = if !path.is_file() {
+            Some(format!("{}: not found in any of the load order folders.", pack_name))
+        } else {
+            match Pack::read_and_merge(&[path.to_path_buf()], game, true, false, false) {
+                Ok(pack) => {
+                    let expected_version = game.pfh_version_by_file_type(pack.pfh_file_type());
+                    if pack.pfh_version() != expected_version {
+                        Some(format!("{}: is a {:?} Pack, but {} expects {:?} Packs. It's probably for another game.", pack_name, pack.pfh_version(), game.display_name(), expected_version))
+                    } else if load_order[..index].iter().any(|other| other.file_name() == path.file_name()) {
+                        Some(format!("{}: is already in the load order from another folder, so this one ({}) will be ignored.", pack_name, path.display()))
+                    } else {
+                        None
+                    }
+                }
+                Err(error) => Some(format!("{}: cannot be read: {}", pack_name, error)),
+            }
+        };
+
+        match issue {
+            Some(issue) => {
+                warn!("- {}", issue);
+                issues.push(issue);
+            }
+            None => valid_paths.push(path.to_path_buf()),
+        }
+    }
+
+    if strict && !issues.is_empty() {
+        return Err(anyhow!("The load order has the following problems:\n- {}", issues.join("\n- ")));
+    }
+
+    Ok(valid_paths)
+}
+
+/// This function checks if a load order entry is a Steam Workshop item id instead of a Pack name.
+pub fn is_workshop_id(entry: &str) -> bool {
+    !entry.is_empty() && entry.chars().all(|character| character.is_ascii_digit())
+}
+
+/// This function returns the Steam AppID of the game installed in the provided path.
+///
+/// It first checks for a steam_appid.txt in the game folder, then for the Steam manifest of the game.
+pub fn steam_app_id(game_path: &Path) -> Result<String> {
+    let app_id_path = game_path.join(STEAM_APP_ID_FILE_NAME);
+    if let Ok(app_id) = read_to_string(app_id_path) {
+        let app_id = app_id.trim();
+        if is_workshop_id(app_id) {
+            return Ok(app_id.to_owned());
+        }
+    }
+
+    // Steam manifests are in steamapps, and games are in steamapps/common/game_folder.
+    let install_dir = game_path.file_name().map(|name| name.to_string_lossy().to_string()).ok_or_else(|| anyhow!("Invalid game path: {}.", game_path.display()))?;
+    let steamapps_path = game_path.parent().and_then(|path| path.parent()).ok_or_else(|| anyhow!("The game doesn't seem to be installed through Steam."))?;
+
+    for manifest_path in read_dir(steamapps_path)?.flatten().map(|entry| entry.path()) {
+        let file_name = manifest_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if let Some(app_id) = file_name.strip_prefix("appmanifest_").and_then(|name| name.strip_suffix(".acf")) {
+            if let Ok(manifest) = read_to_string(&manifest_path) {
+                let is_game_manifest = manifest.lines()
+                    .filter_map(|line| line.trim().strip_prefix("\"installdir\""))
+                    .any(|value| value.trim().trim_matches('"').eq_ignore_ascii_case(&install_dir));
+
+                if is_game_manifest {
+                    return Ok(app_id.to_owned());
+                }
+            }
+        }
+    }
+
+    Err(anyhow!("Steam AppID of the game not found. Workshop items cannot be resolved without it."))
+}
+
+/// This function returns the path where Steam downloads the Workshop items of the game installed in the provided path.
+pub fn workshop_content_path(game_path: &Path) -> Result<PathBuf> {
+    let app_id = steam_app_id(game_path)?;
+    let steamapps_path = game_path.parent().and_then(|path| path.parent()).ok_or_else(|| anyhow!("The game doesn't seem to be installed through Steam."))?;
+    Ok(steamapps_path.join("workshop").join("content").join(app_id))
+}
+
+/// This function returns the Packs of a Workshop item, sorted by name.
+pub fn workshop_item_packs(workshop_path: &Path, item_id: &str) -> Result<Vec<PathBuf>> {
+    let item_path = workshop_path.join(item_id);
+    if !item_path.is_dir() {
+        return Err(anyhow!("Workshop item {} not found in {}. Make sure you're subscribed to it, and Steam finished downloading it.", item_id, workshop_path.display()));
+    }
+
+    let mut packs = read_dir(&item_path)?
+        .flatten()
+        .map(|entry| path_to_absolute_path(&entry.path(), true))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "pack"))
+        .collect::<Vec<_>>();
+
+    if packs.is_empty() {
+        return Err(anyhow!("Workshop item {} doesn't contain any Pack.", item_id));
+    }
+
+    packs.sort();
+    Ok(packs)
+}
+
 pub fn init_reserved_pack(game: &GameInfo) -> Result<Pack> {
 
     // Generate the reserved pack.
@@ -165,8 +890,131 @@ pub fn init_reserved_pack(game: &GameInfo) -> Result<Pack> {
     Ok(reserved_pack)
 }
 
+/// This function returns the SQL scripts in a folder, sorted by name, with their params.
+///
+/// The params of a script are read from a file next to it with the same name and the .params extension, one param per line.
+/// Empty lines and lines starting with # are ignored. Scripts without a params file use the default values of their params.
+pub fn sql_scripts_from_dir(path: &Path) -> Result<Vec<(PathBuf, Vec<String>)>> {
+    if !path.is_dir() {
+        return Err(anyhow!("Path {} doesn't belong to a valid folder.", path.display()));
+    }
+
+    let mut paths = files_from_subdir(path, false)?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case(SQL_SCRIPT_EXTENSION)));
+    paths.sort();
+
+    paths.into_iter()
+        .map(|path| {
+            let params_path = path.with_extension(SQL_PARAMS_EXTENSION);
+            let params = if params_path.is_file() {
+                read_to_string(&params_path)?
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.to_owned())
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            Ok((path, params))
+        })
+        .collect()
+}
+
+/// This function returns the path of a SQL script from the community scripts repo, and the names of the available ones if it's not found.
+pub fn sql_preset_path(game: &GameInfo, name: &str) -> Result<PathBuf> {
+    let folder = sql_presets_path()?.join(game.key());
+    let path = folder.join(format!("{}.{}", name, SQL_SCRIPT_EXTENSION));
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let available = files_from_subdir(&folder, false)
+        .unwrap_or_default()
+        .iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case(SQL_SCRIPT_EXTENSION)))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .collect::<Vec<_>>();
+
+    Err(anyhow!("SQL preset {} not found for {}. Available presets: {}.", name, game.key(), available.join(", ")))
+}
+
+/// This function returns the last modification time of a file, in milliseconds since the unix epoch.
+pub fn modified_time(path: &Path) -> Option<u64> {
+    path.metadata().ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_millis() as u64)
+}
+
+/// This function returns the SHA-256 hash of the provided data, as an hex string.
+///
+/// Used for the hashes saved to disk, as the hasher of the std can change between Rust versions.
+pub fn content_hash(data: &[u8]) -> String {
+    hash_to_string(Sha256::digest(data).as_slice())
+}
+
+/// This function turns a hash into an hex string.
+pub fn hash_to_string(hash: &[u8]) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// This function loads the vanilla data of the game.
+///
+/// The index of the vanilla files is cached in the config folder, and only rebuilt when the game's executable or its CA Packs change.
 pub fn init_vanilla_pack(game: &GameInfo, game_path: &Path) -> Result<Pack> {
-    Pack::read_and_merge_ca_packs(game, game_path).map_err(From::from)
+    let exe_modified = game.executable_path(game_path).and_then(|path| modified_time(&path));
+    let ca_packs = ca_packs_state(game, game_path);
+
+    let cache_path = config_path().map(|path| path.join(VANILLA_INDEX_FOLDER).join(format!("{}.bin", game.key())));
+
+    if let (Some(exe_modified), Some(ca_packs), Ok(cache_path)) = (exe_modified, &ca_packs, &cache_path) {
+        if let Ok(file) = File::open(cache_path) {
+            if let Ok(index) = bincode::deserialize_from::<_, VanillaIndex>(BufReader::new(file)) {
+                if index.exe_modified == exe_modified && index.ca_packs == *ca_packs {
+                    let mut pack = Pack::default();
+                    pack.files_mut().extend(index.files);
+                    return Ok(pack);
+                }
+            }
+        }
+    }
+
+    let pack = Pack::read_and_merge_ca_packs(game, game_path)?;
+
+    if let (Some(exe_modified), Some(ca_packs), Ok(cache_path)) = (exe_modified, ca_packs, cache_path) {
+        let index = VanillaIndex {
+            exe_modified,
+            ca_packs,
+            files: pack.files().clone(),
+        };
+
+        if let Err(error) = index.save(&cache_path) {
+            warn!("Failed to save the vanilla index cache: {}", error);
+        }
+    }
+
+    Ok(pack)
+}
+
+/// This function returns the path, size and last modification time of each CA Pack of the game, sorted by path.
+fn ca_packs_state(game: &GameInfo, game_path: &Path) -> Option<Vec<(PathBuf, u64, u64)>> {
+    let mut paths = game.ca_packs_paths(game_path).ok()?;
+    paths.sort();
+
+    paths.into_iter()
+        .map(|path| {
+            let size = path.metadata().ok()?.len();
+            let modified = modified_time(&path)?;
+            Some((path, size, modified))
+        })
+        .collect()
+}
+
+/// This function removes from a Pack all the files not in the provided paths.
+pub fn retain_data_paths(pack: &mut Pack, paths: &[ContainerPath]) {
+    pack.files_mut().retain(|path, _| paths.iter().any(|container_path| path.starts_with(container_path.path_raw())));
 }
 
 pub fn init_modded_pack(game: &GameInfo, paths: &[PathBuf]) -> Result<Pack> {
@@ -177,7 +1025,8 @@ pub fn init_modded_pack(game: &GameInfo, paths: &[PathBuf]) -> Result<Pack> {
     }
 }
 
-pub fn save_reserved_pack(game: &GameInfo, pack: &mut Pack, mod_paths: &[PathBuf], data_path: &Path, custom_path: &Option<PathBuf>) -> Result<()> {
+/// This function returns the path where the reserved pack is saved.
+pub fn reserved_pack_path(game: &GameInfo, data_path: &Path, custom_path: &Option<PathBuf>) -> PathBuf {
 
     // We need to use an alternative name for Shogun 2, Rome 2, Attila and Thrones because their load order logic for movie packs seems... either different or broken.
     let reserved_pack_name = if game.key() == KEY_SHOGUN_2 || game.key() == KEY_ROME_2 || game.key() == KEY_ATTILA || game.key() == KEY_THRONES_OF_BRITANNIA {
@@ -186,10 +1035,14 @@ pub fn save_reserved_pack(game: &GameInfo, pack: &mut Pack, mod_paths: &[PathBuf
         RESERVED_PACK_NAME
     };
 
-    let temp_path = match custom_path {
+    match custom_path {
         Some(custom_path) => custom_path.to_path_buf(),
         None => data_path.join(reserved_pack_name),
-    };
+    }
+}
+
+pub fn save_reserved_pack(game: &GameInfo, pack: &mut Pack, mod_paths: &[PathBuf], data_path: &Path, custom_path: &Option<PathBuf>, compression: Option<PackCompression>, dependencies: Option<PackDependencies>) -> Result<()> {
+    let temp_path = reserved_pack_path(game, data_path, custom_path);
 
     info!("Saving Pack to: {}", temp_path.display());
 
@@ -197,23 +1050,77 @@ pub fn save_reserved_pack(game: &GameInfo, pack: &mut Pack, mod_paths: &[PathBuf
     encode_data.set_nullify_dates(true);
     encode_data.set_game_info(Some(&game));
 
-    // Set the dependencies to be the entire load order. Fake for older games because it seems to crash for them.
+    // Set the dependencies to be the entire load order, unless disabled. Fake for older games because it seems to crash for them.
     //
     // Real for newer games, as they crash if the dependencies are not set correctly.
     //
     // NOTE: Warhammer 1 may need to be here too.
-    if game.key() != KEY_EMPIRE &&
-        game.key() != KEY_NAPOLEON &&
-        game.key() != KEY_SHOGUN_2 &&
-        game.key() != KEY_ROME_2 &&
-        game.key() != KEY_ATTILA &&
-        game.key() != KEY_THRONES_OF_BRITANNIA {
-        let pack_names = mod_paths.iter().map(|path| (true, path.file_name().unwrap().to_string_lossy().to_string())).collect::<Vec<_>>();
-        pack.set_dependencies(pack_names);
-    } else {
-        let pack_names = mod_paths.iter().map(|path| (false, path.file_name().unwrap().to_string_lossy().to_string())).collect::<Vec<_>>();
-        pack.set_dependencies(pack_names);
+    match dependencies {
+        Some(dependencies) => {
+            let is_hard = match dependencies {
+                PackDependencies::Auto => game.key() != KEY_EMPIRE &&
+                    game.key() != KEY_NAPOLEON &&
+                    game.key() != KEY_SHOGUN_2 &&
+                    game.key() != KEY_ROME_2 &&
+                    game.key() != KEY_ATTILA &&
+                    game.key() != KEY_THRONES_OF_BRITANNIA,
+                PackDependencies::Hard => true,
+                PackDependencies::Soft => false,
+            };
+
+            let pack_names = mod_paths.iter().map(|path| (is_hard, path.file_name().unwrap().to_string_lossy().to_string())).collect::<Vec<_>>();
+            pack.set_dependencies(pack_names);
+        }
+        None => pack.set_dependencies(vec![]),
+    }
+
+    if let Some(compression) = compression {
+        let format = match compression {
+            PackCompression::None => CompressionFormat::None,
+            PackCompression::Lzma => CompressionFormat::Lzma1,
+            PackCompression::Zstd => CompressionFormat::Zstd,
+        };
+
+        // Games only support some formats, so the Pack may end up with a different one.
+        let format_used = pack.set_compression_format(format, game);
+        if format_used != format {
+            warn!("{} doesn't support {:?} compression. Using {:?} instead.", game.display_name(), format, format_used);
+        }
     }
 
     pack.save(Some(&temp_path), game, &Some(encode_data)).map_err(From::from)
 }
+
+//---------------------------------------------------------------------------//
+//                                  Tests
+//---------------------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_literals() {
+        assert!(glob_match("main_units", "main_units"));
+        assert!(glob_match("Main_Units", "main_units"));
+        assert!(!glob_match("main_units", "main_units_tables"));
+        assert!(!glob_match("main_units", "main_unit"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("wh3_*", "wh3_main_unit"));
+        assert!(glob_match("*_cap", "campaign_cap"));
+        assert!(glob_match("*cap*", "campaign_cap_extra"));
+        assert!(glob_match("a*b*c", "aXXbYYbZZc"));
+        assert!(!glob_match("a*b*c", "aXXbYYbZZ"));
+        assert!(glob_match("unit_?", "unit_1"));
+        assert!(!glob_match("unit_?", "unit_"));
+        assert!(!glob_match("unit_?", "unit_12"));
+        assert!(glob_match("**a", "a"));
+    }
+}
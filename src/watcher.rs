@@ -0,0 +1,120 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2025-2025 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Total War Patcher (TWPatcher) project,
+// which can be found here: https://github.com/Frodo45127/twpatcher.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/twpatcher/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+//! Module with the watch mode, which patches the load order again each time it changes.
+
+use anyhow::{anyhow, Result};
+use notify::{Event, RecursiveMode, Watcher};
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use rpfm_lib::games::GameInfo;
+use rpfm_lib::integrations::log::{error, info, warn};
+
+use crate::app::PatchArgs;
+use crate::logging::set_log_section;
+use crate::patcher::{init_game, PatchCache, Patcher};
+use crate::utils::{load_order_file_path, reserved_pack_path, runcher_profile_path};
+
+// Time to wait for more changes after detecting one, so files saved in several writes only trigger one patch.
+const DEBOUNCE_TIME: Duration = Duration::from_millis(1000);
+
+/// This function patches the load order, and then patches it again each time it changes, until the program is closed.
+pub fn watch(args: &PatchArgs) -> Result<()> {
+    let (game, game_path, data_path) = init_game(&args.game, &args.game_path, &args.data_path)?;
+    let files = watched_files(args, &game, &game_path)?;
+    if files.is_empty() && !args.watch_data {
+        return Err(anyhow!("There's nothing to watch, as the load order is not read from a file. Use --watch-data to watch the data folder."));
+    }
+
+    // Editors usually replace files instead of writing them, so we need to watch their folders, not the files.
+    let (sender, receiver) = channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for folder in files.iter().filter_map(|file| file.parent()) {
+        watcher.watch(folder, RecursiveMode::NonRecursive)?;
+    }
+
+    if args.watch_data {
+        watcher.watch(&data_path, RecursiveMode::NonRecursive)?;
+    }
+
+    // The generated Pack is in the data folder, so we need to ignore its changes, or we'll be patching forever.
+    let custom_path = args.generated_pack_path.clone().map(PathBuf::from);
+    let pack_path = reserved_pack_path(&game, &data_path, &custom_path);
+
+    let patcher = Patcher::from_args(args.clone());
+    let mut cache = PatchCache::default();
+    regenerate(&patcher, &mut cache);
+
+    loop {
+        let event = match receiver.recv()? {
+            Ok(event) => event,
+            Err(error) => {
+                warn!("Error watching for changes: {}", error);
+                continue;
+            }
+        };
+
+        if event.kind.is_access() {
+            continue;
+        }
+
+        let changed = event.paths.iter().find(|path| {
+            files.contains(*path) || (args.watch_data && path.parent() == Some(data_path.as_path()) && **path != pack_path && is_pack(path))
+        });
+
+        if let Some(changed) = changed {
+
+            // Wait until the changes stop, so we only patch once.
+            while receiver.recv_timeout(DEBOUNCE_TIME).is_ok() {}
+
+            set_log_section("watch");
+            info!("Changes detected in {}. Regenerating the Pack...", changed.display());
+            regenerate(&patcher, &mut cache);
+        }
+    }
+}
+
+/// This function returns the files the load order is read from.
+fn watched_files(args: &PatchArgs, game: &GameInfo, game_path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(match (&args.runcher_profile, &args.mods, &args.mods_from) {
+        (Some(profile_name), _, _) => vec![runcher_profile_path(profile_name, game)?],
+        (_, Some(_), _) => vec![],
+        (_, _, Some(mods_from)) if mods_from.as_os_str() == "-" => vec![],
+        (_, _, Some(mods_from)) => vec![std::path::absolute(mods_from)?],
+        _ => vec![load_order_file_path(args.load_order_file_name.as_deref().unwrap_or_default(), game, game_path)?],
+    })
+}
+
+fn regenerate(patcher: &Patcher, cache: &mut PatchCache) {
+    match patcher.run_with_cache(cache) {
+        Ok(outcome) => {
+            set_log_section("watch");
+
+            let failed = outcome.report().failed_features();
+            if !failed.is_empty() {
+                let features = failed.iter().map(|(feature, _)| *feature).collect::<Vec<_>>();
+                warn!("The Pack has been generated, but the following features failed: {}.", features.join(", "));
+            }
+
+            info!("Pack regenerated: {}. Watching for changes...", outcome.pack_path().display());
+        }
+        Err(error) => {
+            set_log_section("watch");
+            error!("Failed to regenerate the Pack: {}. Watching for changes...", error);
+        }
+    }
+}
+
+fn is_pack(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pack"))
+}